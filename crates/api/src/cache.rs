@@ -0,0 +1,57 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use types::SolveResult;
+
+/// Bounded least-recently-used store of solve results keyed by the
+/// envelope's reproduce token.
+pub struct ResultCache {
+    capacity: usize,
+    inner: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    results: HashMap<String, SolveResult>,
+    /// Keys from least to most recently used.
+    order: VecDeque<String>,
+}
+
+impl Entries {
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(pos).expect("position is in range");
+            self.order.push_back(key);
+        }
+    }
+}
+
+impl ResultCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::default(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<SolveResult> {
+        let mut e = self.inner.lock().unwrap_or_else(|p| p.into_inner());
+        let hit = e.results.get(key).cloned()?;
+        e.touch(key);
+        Some(hit)
+    }
+
+    pub fn put(&self, key: String, result: SolveResult) {
+        let mut e = self.inner.lock().unwrap_or_else(|p| p.into_inner());
+        if e.results.insert(key.clone(), result).is_some() {
+            e.touch(&key);
+            return;
+        }
+        e.order.push_back(key);
+        while e.order.len() > self.capacity {
+            if let Some(old) = e.order.pop_front() {
+                e.results.remove(&old);
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::error::ApiError;
+
+/// JSON body extractor whose errors name the offending field, e.g.
+/// `instance.courses[0].countPerWeek: invalid type: string "2", expected u32`.
+/// With `?strict=true` it also rejects fields the target type does not
+/// know, so a typo like `countperweek` fails instead of silently
+/// defaulting.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned + Serialize,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let strict = req
+            .uri()
+            .query()
+            .is_some_and(|q| q.split('&').any(|p| p == "strict" || p == "strict=true"));
+        let Json(body) = Json::<Value>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let parsed: T = serde_path_to_error::deserialize(&body)
+            .map_err(|e| ApiError(format!("{}: {}", e.path(), e.inner())).into_response())?;
+        if strict {
+            let echoed = serde_json::to_value(&parsed)
+                .map_err(|e| ApiError(e.to_string()).into_response())?;
+            if let Some(path) = unknown_field(&body, &echoed, "") {
+                return Err(ApiError(format!("unknown field {path}")).into_response());
+            }
+        }
+        Ok(ApiJson(parsed))
+    }
+}
+
+/// Path of the first key in `body` with no counterpart in `echoed`, the
+/// parsed body serialized back. Explicit nulls may be dropped by optional
+/// fields and are not reported.
+fn unknown_field(body: &Value, echoed: &Value, path: &str) -> Option<String> {
+    match (body, echoed) {
+        (Value::Object(a), Value::Object(b)) => a.iter().find_map(|(k, v)| {
+            let p = if path.is_empty() {
+                k.clone()
+            } else {
+                format!("{path}.{k}")
+            };
+            match b.get(k) {
+                Some(e) => unknown_field(v, e, &p),
+                None if v.is_null() => None,
+                None => Some(p),
+            }
+        }),
+        (Value::Array(a), Value::Array(b)) => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .find_map(|(i, (v, e))| unknown_field(v, e, &format!("{path}[{i}]"))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::StatusCode;
+    use types::SolveEnvelope;
+
+    /// Posts the example envelope with `edit` applied to its first course.
+    async fn post(
+        query: &str,
+        edit: impl FnOnce(&mut serde_json::Map<String, Value>),
+    ) -> Result<SolveEnvelope, (StatusCode, String)> {
+        post_envelope(query, |body| {
+            edit(body["instance"]["courses"][0].as_object_mut().unwrap())
+        })
+        .await
+    }
+
+    /// Posts the example envelope with `edit` applied to the whole body.
+    async fn post_envelope(
+        query: &str,
+        edit: impl FnOnce(&mut Value),
+    ) -> Result<SolveEnvelope, (StatusCode, String)> {
+        let mut body: Value =
+            serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+        // The example's display names are not part of the schema.
+        for list in ["teachers", "groups", "courses"] {
+            for entity in body["instance"][list].as_array_mut().unwrap() {
+                entity.as_object_mut().unwrap().remove("name");
+            }
+        }
+        edit(&mut body);
+        let req = Request::post(format!("/v1/solve{query}"))
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        match ApiJson::<SolveEnvelope>::from_request(req, &()).await {
+            Ok(ApiJson(env)) => Ok(env),
+            Err(resp) => {
+                let status = resp.status();
+                let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                Err((status, String::from_utf8(bytes.to_vec()).unwrap()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn misspelled_field_is_reported_by_path() {
+        let (status, msg) = post("", |c| {
+            let n = c.remove("countPerWeek").unwrap();
+            c.insert("countperweek".into(), n);
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(msg, "instance.courses[0]: missing field `countPerWeek`");
+
+        // A typo of an optional field only fails in strict mode.
+        let typo = |c: &mut serde_json::Map<String, Value>| {
+            c.insert("prioirty".into(), 3.into());
+        };
+        assert!(post("", typo).await.is_ok());
+        let (status, msg) = post("?strict=true", typo).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(msg, "unknown field instance.courses[0].prioirty");
+    }
+
+    #[tokio::test]
+    async fn unknown_solver_kind_names_the_accepted_ones() {
+        let (status, msg) = post_envelope("", |body| body["params"]["solver"] = "genetic".into())
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            msg,
+            "params.solver: must be one of: Milp, Heuristic (got \"genetic\")"
+        );
+    }
+}
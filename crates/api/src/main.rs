@@ -1,10 +1,20 @@
+mod cache;
 mod error;
+mod extract;
 mod state;
 mod telemetry;
 pub mod routes {
+    pub mod assignments;
+    pub mod compare;
     pub mod explain;
+    pub mod export;
     pub mod health;
+    pub mod instance;
     pub mod jobs;
+    pub mod masks;
+    pub mod policy;
+    pub mod report;
+    pub mod schema;
     pub mod solve;
     pub mod validate;
 }
@@ -25,23 +35,72 @@ use utoipa_swagger_ui::SwaggerUi;
             routes::solve::solve,
             routes::jobs::status,
             routes::jobs::result,
+            routes::jobs::cancel,
+            routes::jobs::retune,
             routes::validate::validate_handler,
+            routes::validate::validate_timeslots,
             routes::explain::explain,
+            routes::explain::gap,
             routes::solve::reoptimize,
+            routes::solve::solve_batch,
+            routes::compare::compare,
+            routes::assignments::validate_assignments,
+            routes::assignments::merge,
+            routes::export::export,
+            routes::export::solve_and_export,
+            routes::export::export_lp,
+            routes::schema::envelope,
+            routes::schema::instance,
+            routes::masks::preview,
+            routes::instance::stats,
+            routes::policy::resolve,
+            routes::report::availability,
+            routes::report::conflict_graph,
+            routes::report::teacher_timetables,
+            routes::report::bottlenecks,
         ),
         components(schemas(
             types::Instance, types::Teacher, types::Group, types::Room, types::Course,
             types::Policy, types::SoftWeights, types::SolveParams, types::SolveEnvelope,
             types::SolveResult, types::Assignment, types::Violation, types::SolverKind,
-            types::TeacherPrefs, types::DayOfWeek, types::Equip, types::TimeslotId,
-            types::TeacherId, types::GroupId, types::RoomId, types::CourseId,
+            types::GreedyOrder, types::TeacherPrefs, types::GroupPrefs, types::DayOfWeek,
+            types::EmptyAvailability, types::Equip, types::TimeslotId, types::TeacherId,
+            types::GroupId, types::RoomId, types::CourseId, types::ObjectiveTerm, types::Parity,
             jobs::JobId, jobs::JobStatus,
             routes::validate::ValidationReport,
+            routes::validate::TimeslotReport,
             routes::solve::JobCreated,
+            routes::solve::BatchItem,
             routes::explain::ExplainIn,
             routes::explain::ExplainOut,
+            routes::explain::GapOut,
             routes::explain::Weights,
-            routes::explain::Counts
+            routes::explain::Counts,
+            routes::explain::AttributedAssignment,
+            routes::compare::CompareIn,
+            routes::compare::CompareOut,
+            routes::compare::CompareSide,
+            sched_core::diff::ScheduleDiff,
+            routes::assignments::AssignmentsIn,
+            routes::assignments::AssignmentsReport,
+            routes::assignments::MergeIn,
+            routes::assignments::MergeOut,
+            sched_core::feasibility::CourseCoverage,
+            routes::export::ExportIn,
+            routes::export::ExportFormat,
+            types::SlotTime,
+            routes::export::SolveExportIn,
+            routes::masks::MaskPreviewIn,
+            routes::masks::MaskPreviewOut,
+            routes::instance::InstanceStats,
+            routes::jobs::Retuned,
+            routes::policy::ResolveIn,
+            routes::report::AvailabilityIn,
+            routes::report::AvailabilityOut,
+            routes::report::TimetablesIn,
+            routes::report::TimetableEntry,
+            routes::report::Bottleneck,
+            sched_core::feasibility::Conflict
         )),
         tags(
             (name = "unischedule", description = "Scheduling API")
@@ -61,11 +120,46 @@ async fn main() -> anyhow::Result<()> {
     let app = Router::new()
         .route("/v1/health", get(routes::health::health))
         .route("/v1/solve", post(routes::solve::solve))
+        .route("/v1/solve/batch", post(routes::solve::solve_batch))
         .route("/v1/reoptimize", post(routes::solve::reoptimize))
         .route("/v1/validate", post(routes::validate::validate_handler))
+        .route(
+            "/v1/timeslots/validate",
+            post(routes::validate::validate_timeslots),
+        )
         .route("/v1/explain", post(routes::explain::explain))
+        .route("/v1/explain/gap", post(routes::explain::gap))
+        .route("/v1/export", post(routes::export::export))
+        .route("/v1/solve/export", post(routes::export::solve_and_export))
+        .route("/v1/export/lp", post(routes::export::export_lp))
+        .route("/v1/compare", post(routes::compare::compare))
+        .route(
+            "/v1/assignments/validate",
+            post(routes::assignments::validate_assignments),
+        )
+        .route("/v1/merge", post(routes::assignments::merge))
+        .route("/v1/schema/envelope", get(routes::schema::envelope))
+        .route("/v1/schema/instance", get(routes::schema::instance))
+        .route("/v1/masks/preview", post(routes::masks::preview))
+        .route("/v1/instance/stats", post(routes::instance::stats))
+        .route("/v1/policy/resolve", post(routes::policy::resolve))
+        .route(
+            "/v1/report/availability",
+            post(routes::report::availability),
+        )
+        .route(
+            "/v1/report/conflict-graph",
+            post(routes::report::conflict_graph),
+        )
+        .route(
+            "/v1/report/teacher-timetables",
+            post(routes::report::teacher_timetables),
+        )
+        .route("/v1/report/bottlenecks", post(routes::report::bottlenecks))
         .route("/v1/jobs/:id", get(routes::jobs::status))
         .route("/v1/jobs/:id/result", get(routes::jobs::result))
+        .route("/v1/jobs/:id/cancel", post(routes::jobs::cancel))
+        .route("/v1/jobs/:id/retune", post(routes::jobs::retune))
         .merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()))
         .with_state(app_state);
 
@@ -0,0 +1,189 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::ToSchema;
+
+use sched_core::feasibility::{all_hard_violations, coverage, dangling_references, CourseCoverage};
+use types::{Assignment, Instance, Violation};
+
+#[derive(Deserialize, ToSchema)]
+pub struct AssignmentsIn {
+    pub instance: Instance,
+    pub assignments: Vec<Assignment>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AssignmentsReport {
+    pub ok: bool,
+    pub hard_violations: Vec<Violation>,
+    pub coverage: Vec<CourseCoverage>,
+    pub references_ok: bool,
+    pub dangling: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/assignments/validate",
+    request_body = AssignmentsIn,
+    responses(
+    (status = 200, description = "Hard-constraint audit of an externally produced schedule", body = AssignmentsReport)
+    )
+)]
+pub async fn validate_assignments(Json(input): Json<AssignmentsIn>) -> Json<AssignmentsReport> {
+    let violations = all_hard_violations(&input.instance, &input.assignments, &[]);
+    let dangling = dangling_references(&input.instance, &input.assignments);
+    Json(AssignmentsReport {
+        ok: violations.is_empty() && dangling.is_empty(),
+        hard_violations: violations,
+        coverage: coverage(&input.instance, &input.assignments),
+        references_ok: dangling.is_empty(),
+        dangling,
+    })
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct MergeIn {
+    pub instance: Instance,
+    pub parts: Vec<Vec<Assignment>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MergeOut {
+    pub assignments: Vec<Assignment>,
+    /// True when the merge adds no hard violation of its own.
+    pub ok: bool,
+    /// Hard violations of the combined schedule that no single part has.
+    pub introduced: Vec<Violation>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/merge",
+    request_body = MergeIn,
+    responses(
+    (status = 200, description = "Partial schedules combined, with the clashes between them", body = MergeOut)
+    )
+)]
+pub async fn merge(Json(input): Json<MergeIn>) -> Json<MergeOut> {
+    let inst = &input.instance;
+    let key = |a: &Assignment| {
+        (
+            a.courseId.0.clone(),
+            a.timeslot.0.clone(),
+            a.roomId.0.clone(),
+            a.teacherId.0.clone(),
+        )
+    };
+    // A meeting present in several parts (e.g. pinned into each
+    // per-department solve) is kept once.
+    let mut seen = HashSet::new();
+    let assignments: Vec<Assignment> = input
+        .parts
+        .iter()
+        .flatten()
+        .filter(|a| seen.insert(key(a)))
+        .cloned()
+        .collect();
+    let own: Vec<serde_json::Value> = input
+        .parts
+        .iter()
+        .flat_map(|p| all_hard_violations(inst, p, &[]))
+        .map(|v| serde_json::json!(v))
+        .collect();
+    let introduced: Vec<Violation> = all_hard_violations(inst, &assignments, &[])
+        .into_iter()
+        .filter(|v| !own.contains(&serde_json::json!(v)))
+        .collect();
+    Json(MergeOut {
+        assignments,
+        ok: introduced.is_empty(),
+        introduced,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{CourseId, RoomId, SolveEnvelope, TeacherId, TimeslotId};
+
+    fn small() -> Instance {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        env.instance
+    }
+
+    fn meet(course: &str, teacher: &str, ts: &str, room: &str) -> Assignment {
+        Assignment {
+            courseId: CourseId(course.into()),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId(room.into()),
+            teacherId: TeacherId(teacher.into()),
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn duplicate_booking_is_a_hard_violation() {
+        let Json(out) = validate_assignments(Json(AssignmentsIn {
+            instance: small(),
+            assignments: vec![
+                meet("c_alg", "t_kovalenko", "mon.1", "r101"),
+                meet("c_prog", "t_petrenko", "mon.1", "r101"),
+            ],
+        }))
+        .await;
+        assert!(!out.ok);
+        assert!(out.references_ok);
+        assert!(out.hard_violations.iter().any(|v| v.r#type == "room_clash"));
+        let alg = out
+            .coverage
+            .iter()
+            .find(|c| c.courseId.0 == "c_alg")
+            .unwrap();
+        assert_eq!((alg.placed, alg.required), (1, 2));
+    }
+
+    #[tokio::test]
+    async fn dangling_course_id_is_reported() {
+        let Json(out) = validate_assignments(Json(AssignmentsIn {
+            instance: small(),
+            assignments: vec![meet("c_missing", "t_kovalenko", "mon.1", "r101")],
+        }))
+        .await;
+        assert!(!out.ok);
+        assert!(!out.references_ok);
+        assert_eq!(out.dangling, vec!["course:c_missing".to_string()]);
+    }
+
+    fn parts(prog_room: &str) -> MergeIn {
+        MergeIn {
+            instance: small(),
+            parts: vec![
+                vec![
+                    meet("c_alg", "t_kovalenko", "mon.1", "r101"),
+                    meet("c_alg", "t_kovalenko", "tue.1", "r101"),
+                ],
+                ["mon.1", "tue.2", "thu.1"]
+                    .into_iter()
+                    .map(|ts| meet("c_prog", "t_petrenko", ts, prog_room))
+                    .collect(),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn conflict_free_parts_merge_cleanly() {
+        let Json(out) = merge(Json(parts("r202"))).await;
+        assert!(out.ok, "{:?}", out.introduced);
+        assert_eq!(out.assignments.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn parts_sharing_a_room_report_the_clash() {
+        let Json(out) = merge(Json(parts("r101"))).await;
+        assert!(!out.ok);
+        assert_eq!(out.assignments.len(), 5);
+        assert!(out.introduced.iter().all(|v| v.r#type == "room_clash"));
+        assert!(!out.introduced.is_empty());
+    }
+}
@@ -0,0 +1,120 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use sched_core::diff::{diff_schedules, ScheduleDiff};
+use sched_core::feasibility::all_hard_violations;
+use types::{Assignment, Instance, Violation};
+
+use crate::routes::explain::{breakdown, ExplainOut};
+
+#[derive(Deserialize, ToSchema)]
+pub struct CompareIn {
+    pub instance: Instance,
+    pub a: Vec<Assignment>,
+    pub b: Vec<Assignment>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CompareSide {
+    #[serde(flatten)]
+    pub breakdown: ExplainOut,
+    pub hard_violations: Vec<Violation>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CompareOut {
+    pub a: CompareSide,
+    pub b: CompareSide,
+    pub diff: ScheduleDiff,
+    /// "a", "b" or "tie": fewer hard violations wins, then lower objective.
+    pub better: &'static str,
+}
+
+fn side(inst: &Instance, assignments: &[Assignment]) -> CompareSide {
+    CompareSide {
+        breakdown: breakdown(inst, assignments),
+        hard_violations: all_hard_violations(inst, assignments, &[]),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/compare",
+    request_body = CompareIn,
+    responses(
+    (status = 200, description = "Side-by-side breakdown of two schedules", body = CompareOut)
+    )
+)]
+pub async fn compare(Json(input): Json<CompareIn>) -> Json<CompareOut> {
+    let a = side(&input.instance, &input.a);
+    let b = side(&input.instance, &input.b);
+    let key = |s: &CompareSide| (s.hard_violations.len(), s.breakdown.objective);
+    let better = match key(&a).partial_cmp(&key(&b)) {
+        Some(std::cmp::Ordering::Less) => "a",
+        Some(std::cmp::Ordering::Greater) => "b",
+        _ => "tie",
+    };
+    Json(CompareOut {
+        diff: diff_schedules(&input.a, &input.b),
+        a,
+        b,
+        better,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{CourseId, RoomId, SolveEnvelope, TeacherId, TimeslotId};
+
+    fn meet(ts: &str) -> Assignment {
+        Assignment {
+            courseId: CourseId("c_alg".into()),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r101".into()),
+            teacherId: TeacherId("t_kovalenko".into()),
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn fewer_windows_wins() {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let mut instance = env.instance;
+        instance.teachers[0]
+            .available
+            .push(TimeslotId("mon.3".into()));
+        let Json(out) = compare(Json(CompareIn {
+            instance,
+            a: vec![meet("mon.1"), meet("mon.2")],
+            b: vec![meet("mon.1"), meet("mon.3")],
+        }))
+        .await;
+        assert_eq!(out.a.hard_violations.len(), out.b.hard_violations.len());
+        assert!(out.a.breakdown.objective < out.b.breakdown.objective);
+        assert_eq!(out.better, "a");
+    }
+
+    #[tokio::test]
+    async fn weekly_cap_counts_as_a_hard_violation() {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let mut instance = env.instance;
+        instance.groups[0].max_weekly = Some(1);
+        let Json(out) = compare(Json(CompareIn {
+            instance,
+            a: vec![meet("mon.1"), meet("mon.2")],
+            b: vec![meet("mon.1")],
+        }))
+        .await;
+        let capped = |s: &CompareSide| {
+            s.hard_violations
+                .iter()
+                .any(|v| v.r#type == "group_weekly_cap")
+        };
+        assert!(capped(&out.a));
+        assert!(!capped(&out.b));
+    }
+}
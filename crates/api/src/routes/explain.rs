@@ -1,9 +1,14 @@
-use axum::Json;
+use axum::{extract::Query, Json};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-use sched_core::scoring::compute_soft_scores;
-use types::{Assignment, Instance};
+use sched_core::feasibility::coverage;
+use sched_core::scoring::{compute_scores_attributed, compute_soft_scores};
+use sched_core::{CancelToken, Solver};
+use solver_heur::HeurSolver;
+use types::{Assignment, Instance, SolveEnvelope, SolveParams, SolverKind};
+
+use crate::error::ApiError;
 
 #[derive(Deserialize, ToSchema)]
 pub struct ExplainIn {
@@ -11,49 +16,329 @@ pub struct ExplainIn {
     pub assignments: Vec<Assignment>,
 }
 
+#[derive(Deserialize)]
+pub struct ExplainQuery {
+    #[serde(default)]
+    pub attributed: bool,
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct ExplainOut {
     pub objective: f64,
     pub weights: Weights,
     pub counts: Counts,
+    /// Per-assignment share of `objective`, present with `?attributed=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributed: Option<Vec<AttributedAssignment>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AttributedAssignment {
+    #[serde(flatten)]
+    pub assignment: Assignment,
+    pub penalty: f64,
 }
 
 #[derive(Serialize, ToSchema)]
 pub struct Weights {
     pub unpreferred_time: i32,
     pub windows: i32,
+    pub windows_teachers: i32,
+    pub windows_groups: i32,
+    pub load_imbalance: i32,
+    pub compactness: i32,
+    pub soft_availability: i32,
+    pub course_room_consistency: i32,
+    pub teacher_room_spread: i32,
+    pub away_from_home: i32,
+    pub course_adjacency: i32,
+    pub parity_slot_consistency: i32,
+    pub active_days: i32,
+    pub course_building_spread: i32,
+    pub rooms_used: i32,
+    pub building_switch: i32,
+    pub course_spacing: i32,
 }
 
 #[derive(Serialize, ToSchema)]
 pub struct Counts {
     pub unpreferred_meetings: i64,
+    pub group_unpreferred: std::collections::HashMap<String, i64>,
+    pub group_unpreferred_total: i64,
+    pub soft_available_meetings: i64,
     pub windows_total: i64,
     pub windows_teachers: std::collections::HashMap<String, i64>,
     pub windows_groups: std::collections::HashMap<String, i64>,
+    pub teacher_loads: std::collections::HashMap<String, i64>,
+    pub load_imbalance: f64,
+    pub group_day_spans: std::collections::HashMap<String, std::collections::HashMap<String, i64>>,
+    pub spans_total: i64,
+    pub course_room_spread: std::collections::HashMap<String, i64>,
+    pub room_spread_total: i64,
+    pub teacher_room_spread: std::collections::HashMap<String, i64>,
+    pub teacher_room_spread_total: i64,
+    pub away_from_home: std::collections::HashMap<String, i64>,
+    pub away_from_home_total: i64,
+    pub adjacent_pairs_satisfied: i64,
+    pub adjacent_pairs_missed: i64,
+    pub parity_slot_mismatches: i64,
+    pub active_days_teachers: std::collections::HashMap<String, i64>,
+    pub active_days_groups: std::collections::HashMap<String, i64>,
+    pub active_days_total: i64,
+    pub course_building_spread: std::collections::HashMap<String, i64>,
+    pub building_spread_total: i64,
+    pub rooms_used: i64,
+    pub building_switches_teachers: std::collections::HashMap<String, i64>,
+    pub building_switches_groups: std::collections::HashMap<String, i64>,
+    pub building_switch_distance: i64,
+    pub course_spacing: std::collections::HashMap<String, i64>,
+    pub course_spacing_total: i64,
 }
 
 #[utoipa::path(
     post,
     path = "/v1/explain",
     request_body = ExplainIn,
+    params(("attributed" = Option<bool>, Query, description = "Attribute the objective to individual assignments")),
     responses(
     (status = 200, description = "Soft-penalty breakdown for provided schedule", body = ExplainOut)
     )
 )]
-pub async fn explain(Json(input): Json<ExplainIn>) -> Json<ExplainOut> {
-    let s = compute_soft_scores(&input.instance, &input.assignments);
-    let w = &input.instance.policy.soft_weights;
-    Json(ExplainOut {
+pub async fn explain(
+    Query(q): Query<ExplainQuery>,
+    Json(input): Json<ExplainIn>,
+) -> Json<ExplainOut> {
+    let mut out = breakdown(&input.instance, &input.assignments);
+    if q.attributed {
+        out.attributed = Some(
+            compute_scores_attributed(&input.instance, &input.assignments)
+                .into_iter()
+                .map(|(assignment, penalty)| AttributedAssignment {
+                    assignment,
+                    penalty,
+                })
+                .collect(),
+        );
+    }
+    Json(out)
+}
+
+pub fn breakdown(inst: &Instance, assignments: &[Assignment]) -> ExplainOut {
+    let s = compute_soft_scores(inst, assignments);
+    let w = sched_core::presets::effective_weights(&inst.policy);
+    ExplainOut {
         objective: s.objective,
         weights: Weights {
             unpreferred_time: w.unpreferred_time,
             windows: w.windows,
+            windows_teachers: w.teacher_windows(),
+            windows_groups: w.group_windows(),
+            load_imbalance: w.load_imbalance,
+            compactness: w.compactness,
+            soft_availability: w.soft_availability,
+            course_room_consistency: w.course_room_consistency,
+            teacher_room_spread: w.teacher_room_spread,
+            away_from_home: w.away_from_home,
+            course_adjacency: w.course_adjacency,
+            parity_slot_consistency: w.parity_slot_consistency,
+            active_days: w.active_days,
+            course_building_spread: w.course_building_spread,
+            rooms_used: w.rooms_used,
+            building_switch: w.building_switch,
+            course_spacing: w.course_spacing,
         },
         counts: Counts {
             unpreferred_meetings: s.unpreferred_meetings,
+            group_unpreferred: s.group_unpreferred,
+            group_unpreferred_total: s.group_unpreferred_total,
+            soft_available_meetings: s.soft_available_meetings,
             windows_total: s.windows_total,
             windows_teachers: s.windows_teachers,
             windows_groups: s.windows_groups,
+            teacher_loads: s.teacher_loads,
+            load_imbalance: s.load_imbalance,
+            group_day_spans: s.group_day_spans,
+            spans_total: s.spans_total,
+            course_room_spread: s.course_room_spread,
+            room_spread_total: s.room_spread_total,
+            teacher_room_spread: s.teacher_room_spread,
+            teacher_room_spread_total: s.teacher_room_spread_total,
+            away_from_home: s.away_from_home,
+            away_from_home_total: s.away_from_home_total,
+            adjacent_pairs_satisfied: s.adjacent_pairs_satisfied,
+            adjacent_pairs_missed: s.adjacent_pairs_missed,
+            parity_slot_mismatches: s.parity_slot_mismatches,
+            active_days_teachers: s.active_days_teachers,
+            active_days_groups: s.active_days_groups,
+            active_days_total: s.active_days_total,
+            course_building_spread: s.course_building_spread,
+            building_spread_total: s.building_spread_total,
+            rooms_used: s.rooms_used,
+            building_switches_teachers: s.building_switches_teachers,
+            building_switches_groups: s.building_switches_groups,
+            building_switch_distance: s.building_switch_distance,
+            course_spacing: s.course_spacing,
+            course_spacing_total: s.course_spacing_total,
         },
-    })
+        attributed: None,
+    }
+}
+
+/// Wall-clock budget of the heuristic run behind `/v1/explain/gap`.
+const GAP_BUDGET_SECS: u64 = 2;
+
+#[derive(Serialize, ToSchema)]
+pub struct GapOut {
+    /// Objective of the supplied schedule.
+    pub given: f64,
+    /// Objective of the best schedule the bounded heuristic run found,
+    /// starting from the supplied one. `None` unless the run finished
+    /// "solved" with every meeting placed.
+    pub estimated_best: Option<f64>,
+    /// `given - estimated_best`; positive when the supplied schedule can be
+    /// improved. Negative when the heuristic did worse within its budget.
+    pub gap: Option<f64>,
+    /// Status of the heuristic run; "cancelled" when it used the whole
+    /// budget, "partial" when it could not place every meeting.
+    pub solver_status: String,
+    pub budget_sec: u64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/explain/gap",
+    request_body = ExplainIn,
+    responses(
+    (status = 200, description = "Objective of the provided schedule against a quick heuristic estimate", body = GapOut),
+    (status = 400, description = "Invalid instance")
+    )
+)]
+pub async fn gap(Json(input): Json<ExplainIn>) -> Result<Json<GapOut>, ApiError> {
+    sched_core::validate(&input.instance).map_err(|e| ApiError(e.to_string()))?;
+    let given = compute_soft_scores(&input.instance, &input.assignments).objective;
+    let env = SolveEnvelope {
+        instance: input.instance.clone(),
+        params: SolveParams {
+            solver: SolverKind::Heuristic,
+            timeLimitSec: GAP_BUDGET_SECS,
+            seed: 0,
+            repairLocalSearch: false,
+            repairSteps: None,
+            repairMinGain: None,
+            repairMinGainRel: None,
+            relaxPins: false,
+            bestEffort: false,
+            objectiveScale: None,
+            greedyOrder: Default::default(),
+            allowFallback: false,
+            tournamentSize: None,
+            elitism: None,
+            objectiveBudget: None,
+            onlyGroups: Vec::new(),
+            twoPhase: false,
+        },
+        base: input.assignments,
+        pinned: Vec::new(),
+        masks: Vec::new(),
+        partial_pins: Vec::new(),
+        frozen_days: Vec::new(),
+        window_days: Vec::new(),
+        priority: None,
+    };
+    // The GA checks the token between generations; run it on its own task
+    // so the timer can fire.
+    let cancel = CancelToken::new();
+    let timer = {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_secs(GAP_BUDGET_SECS)).await;
+            cancel.cancel();
+        })
+    };
+    let res = tokio::spawn(async move { HeurSolver::new().solve_cancellable(env, cancel).await })
+        .await
+        .map_err(|e| ApiError(e.to_string()))?
+        .map_err(|e| ApiError(e.to_string()))?;
+    timer.abort();
+    let complete = res.status == "solved"
+        && coverage(&input.instance, &res.assignments)
+            .iter()
+            .all(|c| c.placed == c.required);
+    let estimated_best =
+        complete.then(|| compute_soft_scores(&input.instance, &res.assignments).objective);
+    Ok(Json(GapOut {
+        given,
+        estimated_best,
+        gap: estimated_best.map(|best| given - best),
+        solver_status: res.status,
+        budget_sec: GAP_BUDGET_SECS,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{RoomId, TimeslotId};
+
+    #[tokio::test]
+    async fn suboptimal_schedule_reports_a_positive_gap() {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let mut instance = env.instance;
+        instance.policy.soft_weights.unpreferred_time = 20;
+        instance.teachers[0].prefs.avoid_slots =
+            vec![TimeslotId("mon.1".into()), TimeslotId("mon.2".into())];
+        let meet = |ci: usize, ts: &str, room: &str| {
+            let c = &instance.courses[ci];
+            Assignment {
+                courseId: c.id.clone(),
+                timeslot: TimeslotId(ts.into()),
+                roomId: RoomId(room.into()),
+                teacherId: c.teacherId.clone(),
+                meta: None,
+            }
+        };
+        // c_alg sits in both slots its teacher avoids.
+        let assignments = vec![
+            meet(0, "mon.1", "r101"),
+            meet(0, "mon.2", "r101"),
+            meet(1, "mon.1", "r202"),
+            meet(1, "tue.2", "r202"),
+            meet(1, "thu.1", "r202"),
+        ];
+        let Ok(Json(out)) = gap(Json(ExplainIn {
+            instance,
+            assignments,
+        }))
+        .await
+        else {
+            panic!("gap failed");
+        };
+        let (Some(gap), Some(best)) = (out.gap, out.estimated_best) else {
+            panic!("no estimate: {}", out.solver_status);
+        };
+        assert!(gap > 0.0, "gap {gap}");
+        assert_eq!(gap, out.given - best);
+    }
+
+    #[tokio::test]
+    async fn no_estimate_when_the_heuristic_cannot_place_everything() {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let mut instance = env.instance;
+        // Without thu.2 its teacher has only two free two-slot runs for
+        // c_prog's three meetings.
+        instance.teachers[1].available.retain(|t| t.0 != "thu.2");
+        let Ok(Json(out)) = gap(Json(ExplainIn {
+            instance,
+            assignments: Vec::new(),
+        }))
+        .await
+        else {
+            panic!("gap failed");
+        };
+        assert_ne!(out.solver_status, "solved");
+        assert!(out.estimated_best.is_none());
+        assert!(out.gap.is_none());
+    }
 }
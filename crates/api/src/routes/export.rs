@@ -0,0 +1,443 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+use sched_core::{CancelToken, Solver};
+use types::{Assignment, Instance, Parity, SlotTime, SolveEnvelope, TimeslotId};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Ics,
+    Csv,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ExportIn {
+    pub instance: Instance,
+    pub assignments: Vec<Assignment>,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Monday of the exported week, "YYYY-MM-DD". Its ISO week number
+    /// decides whether odd- or even-week courses are included.
+    pub week_start: String,
+    /// Wall-clock times per slot index (the number after the dot in a timeslot id).
+    /// Indices missing here fall back to `instance.slot_times`.
+    #[serde(default)]
+    pub slot_times: BTreeMap<u32, SlotTime>,
+    /// IANA zone the slot times are expressed in. Defaults to UTC.
+    #[serde(default)]
+    pub tz: Option<String>,
+    /// Emit UTC-normalized datetimes instead of zone-local ones.
+    #[serde(default)]
+    pub utc: bool,
+}
+
+struct Event<'a> {
+    a: &'a Assignment,
+    start: DateTime<Tz>,
+    end: DateTime<Tz>,
+}
+
+fn parse_hm(s: &str) -> Result<NaiveTime, ApiError> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| ApiError(format!("invalid time {s}")))
+}
+
+fn localize(tz: Tz, date: NaiveDate, time: NaiveTime) -> Result<DateTime<Tz>, ApiError> {
+    tz.from_local_datetime(&date.and_time(time))
+        .earliest()
+        .ok_or_else(|| ApiError(format!("{date} {time} does not exist in {tz}")))
+}
+
+/// Date and wall-clock start/end of slot `ts`; a sub-slot gets its equal
+/// share of the period's `slot_times` entry.
+fn slot_bounds(input: &ExportIn, ts: &str) -> Result<(NaiveDate, NaiveTime, NaiveTime), ApiError> {
+    let (day, idx, sub) =
+        TimeslotId::parse_str(ts).ok_or_else(|| ApiError(format!("invalid timeslot {ts}")))?;
+    let week_start = NaiveDate::parse_from_str(&input.week_start, "%Y-%m-%d")
+        .map_err(|_| ApiError(format!("invalid week_start {}", input.week_start)))?;
+    let st = input
+        .slot_times
+        .get(&idx)
+        .or_else(|| input.instance.slot_times.get(&idx))
+        .ok_or_else(|| ApiError(format!("no slot_times entry for slot index {idx}")))?;
+    let (start, end) = (parse_hm(&st.start)?, parse_hm(&st.end)?);
+    let (start, end) = if sub == 0 {
+        (start, end)
+    } else {
+        let minutes = |t: NaiveTime| t.num_seconds_from_midnight() / 60;
+        let (s, e) = sched_core::clock::sub_span(
+            (minutes(start), minutes(end)),
+            sub,
+            sched_core::clock::sub_slots(&input.instance, day, idx),
+        );
+        let time = |m: u32| NaiveTime::from_hms_opt(m / 60, m % 60, 0).unwrap_or(start);
+        (time(s), time(e))
+    };
+    Ok((week_start + Duration::days(day as i64), start, end))
+}
+
+fn week_parity(week_start: &str) -> Result<Parity, ApiError> {
+    let date = NaiveDate::parse_from_str(week_start, "%Y-%m-%d")
+        .map_err(|_| ApiError(format!("invalid week_start {week_start}")))?;
+    Ok(if date.iso_week().week() % 2 == 1 {
+        Parity::Odd
+    } else {
+        Parity::Even
+    })
+}
+
+fn build_events(input: &ExportIn, tz: Tz) -> Result<Vec<Event<'_>>, ApiError> {
+    let week = week_parity(&input.week_start)?;
+    let mut events = Vec::with_capacity(input.assignments.len());
+    for a in &input.assignments {
+        let course = input.instance.courses.iter().find(|c| c.id == a.courseId);
+        if course.is_some_and(|c| !c.parity().overlaps(week)) {
+            continue;
+        }
+        let (date, start, mut end) = slot_bounds(input, &a.timeslot.0)?;
+        let mut last_date = date;
+        let dur2 = course.is_some_and(|c| c.duration == 2);
+        if dur2 {
+            let t0 = input
+                .instance
+                .timeslots
+                .iter()
+                .position(|t| *t == a.timeslot)
+                .ok_or_else(|| ApiError(format!("unknown timeslot {}", a.timeslot.0)))?;
+            let last = sched_core::occupancy::meeting_slots(&input.instance, t0, 2)
+                .and_then(|slots| slots.last().copied())
+                .ok_or_else(|| {
+                    ApiError(format!(
+                        "two-slot meeting at {} does not fit the day",
+                        a.timeslot.0
+                    ))
+                })?;
+            let next = &input.instance.timeslots[last];
+            (last_date, _, end) = slot_bounds(input, &next.0)?;
+        }
+        events.push(Event {
+            a,
+            start: localize(tz, date, start)?,
+            end: localize(tz, last_date, end)?,
+        });
+    }
+    Ok(events)
+}
+
+fn ics_stamp(dt: &DateTime<Tz>, utc: bool) -> String {
+    if utc {
+        format!(":{}", dt.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ"))
+    } else {
+        format!(
+            ";TZID={}:{}",
+            dt.timezone().name(),
+            dt.format("%Y%m%dT%H%M%S")
+        )
+    }
+}
+
+fn render_ics(events: &[Event], utc: bool) -> String {
+    let mut out = String::from("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//unischedule//EN\r\n");
+    for e in events {
+        out.push_str("BEGIN:VEVENT\r\n");
+        out.push_str(&format!(
+            "UID:{}-{}-{}@unischedule\r\n",
+            e.a.courseId, e.a.timeslot.0, e.a.roomId
+        ));
+        out.push_str(&format!("DTSTART{}\r\n", ics_stamp(&e.start, utc)));
+        out.push_str(&format!("DTEND{}\r\n", ics_stamp(&e.end, utc)));
+        out.push_str(&format!("SUMMARY:{}\r\n", e.a.courseId));
+        out.push_str(&format!("LOCATION:{}\r\n", e.a.roomId));
+        out.push_str(&format!("DESCRIPTION:teacher {}\r\n", e.a.teacherId));
+        out.push_str("END:VEVENT\r\n");
+    }
+    out.push_str("END:VCALENDAR\r\n");
+    out
+}
+
+fn render_csv(events: &[Event], utc: bool) -> String {
+    let stamp = |dt: &DateTime<Tz>| {
+        if utc {
+            dt.with_timezone(&Utc).to_rfc3339()
+        } else {
+            dt.to_rfc3339()
+        }
+    };
+    let mut out = String::from("course,timeslot,room,teacher,start,end\n");
+    for e in events {
+        out.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            e.a.courseId,
+            e.a.timeslot.0,
+            e.a.roomId,
+            e.a.teacherId,
+            stamp(&e.start),
+            stamp(&e.end)
+        ));
+    }
+    out
+}
+
+pub fn render(input: &ExportIn) -> Result<Response, ApiError> {
+    let tz: Tz = match &input.tz {
+        Some(name) => name
+            .parse()
+            .map_err(|_| ApiError(format!("unknown time zone {name}")))?,
+        None => Tz::UTC,
+    };
+    let events = build_events(input, tz)?;
+    Ok(match input.format {
+        ExportFormat::Ics => (
+            [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+            render_ics(&events, input.utc),
+        )
+            .into_response(),
+        ExportFormat::Csv => (
+            [(header::CONTENT_TYPE, "text/csv; charset=utf-8")],
+            render_csv(&events, input.utc),
+        )
+            .into_response(),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/export",
+    request_body = ExportIn,
+    responses(
+    (status = 200, description = "Schedule as iCalendar or CSV", body = String),
+    (status = 400, description = "Invalid time zone, week start or slot times")
+    )
+)]
+pub async fn export(Json(input): Json<ExportIn>) -> Result<Response, ApiError> {
+    render(&input)
+}
+
+/// Largest instance `/v1/solve/export` will solve inline. It is the only
+/// endpoint that solves synchronously, so its guard lives here.
+const SYNC_MAX_COURSES: usize = 60;
+/// Upper bound on the inline solve time, whatever `timeLimitSec` asks for.
+const SYNC_MAX_SECS: u64 = 30;
+
+#[derive(Deserialize)]
+pub struct FormatQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SolveExportIn {
+    #[serde(flatten)]
+    pub env: SolveEnvelope,
+    /// Monday of the exported week, "YYYY-MM-DD".
+    pub week_start: String,
+    #[serde(default)]
+    pub slot_times: BTreeMap<u32, SlotTime>,
+    #[serde(default)]
+    pub tz: Option<String>,
+    #[serde(default)]
+    pub utc: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/export/lp",
+    request_body = SolveEnvelope,
+    responses(
+    (status = 200, description = "The MILP the solver would build, in CPLEX LP format", body = String),
+    (status = 400, description = "Invalid instance, or the MILP backend is not built")
+    )
+)]
+pub async fn export_lp(Json(env): Json<SolveEnvelope>) -> Result<Response, ApiError> {
+    sched_core::validate(&env.instance).map_err(|e| ApiError(e.to_string()))?;
+    let env = crate::state::backend_envelope(env);
+    let lp = solver_milp::export_lp(&env).map_err(|e| ApiError(e.to_string()))?;
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], lp).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/solve/export",
+    request_body = SolveExportIn,
+    params(("format" = Option<ExportFormat>, Query, description = "ics (default) or csv")),
+    responses(
+    (status = 200, description = "Solved schedule as iCalendar or CSV", body = String),
+    (status = 400, description = "Instance too large, invalid, infeasible or the solve timed out")
+    )
+)]
+pub async fn solve_and_export(
+    State(state): State<AppState>,
+    Query(q): Query<FormatQuery>,
+    Json(input): Json<SolveExportIn>,
+) -> Result<Response, ApiError> {
+    let courses = input.env.instance.courses.len();
+    if courses > SYNC_MAX_COURSES {
+        return Err(ApiError(format!(
+            "instance has {courses} courses; synchronous solve is limited to {SYNC_MAX_COURSES}, use /v1/solve"
+        )));
+    }
+    sched_core::validate(&input.env.instance).map_err(|e| ApiError(e.to_string()))?;
+
+    let secs = input.env.params.timeLimitSec.clamp(1, SYNC_MAX_SECS);
+    let instance = input.env.instance.clone();
+    let mut env = input.env;
+    env.params.timeLimitSec = secs;
+    // Solvers compute without yielding, so run on a blocking thread for the
+    // timeout to be able to fire, and cancel the solve when it does rather
+    // than leave it running with nobody waiting for the result.
+    let cancel = CancelToken::new();
+    let solver = state.solver.clone();
+    let token = cancel.clone();
+    let runtime = tokio::runtime::Handle::current();
+    let task =
+        tokio::task::spawn_blocking(move || runtime.block_on(solver.solve_cancellable(env, token)));
+    let res = match tokio::time::timeout(std::time::Duration::from_secs(secs), task).await {
+        Ok(joined) => joined
+            .map_err(|e| ApiError(e.to_string()))?
+            .map_err(|e| ApiError(e.to_string()))?,
+        Err(_) => {
+            cancel.cancel();
+            return Err(ApiError(format!("solve did not finish within {secs}s")));
+        }
+    };
+    if res.status == "infeasible" {
+        return Err(ApiError("no feasible schedule found".into()));
+    }
+    if res.status == "timeout" {
+        return Err(ApiError(format!("no schedule found within {secs}s")));
+    }
+
+    render(&ExportIn {
+        instance,
+        assignments: res.assignments,
+        format: q.format,
+        week_start: input.week_start,
+        slot_times: input.slot_times,
+        tz: input.tz,
+        utc: input.utc,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{CourseId, RoomId, TeacherId};
+
+    fn export_in(tz: &str, utc: bool) -> ExportIn {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let slot = |start: &str, end: &str| SlotTime {
+            start: start.into(),
+            end: end.into(),
+        };
+        ExportIn {
+            instance: env.instance,
+            assignments: vec![Assignment {
+                courseId: CourseId("c_prog".into()),
+                timeslot: TimeslotId("mon.1".into()),
+                roomId: RoomId("r202".into()),
+                teacherId: TeacherId("t_petrenko".into()),
+                meta: None,
+            }],
+            format: ExportFormat::Ics,
+            week_start: "2024-01-15".into(),
+            slot_times: BTreeMap::from([(1, slot("08:30", "09:50")), (2, slot("10:05", "11:25"))]),
+            tz: Some(tz.into()),
+            utc,
+        }
+    }
+
+    fn ics(input: &ExportIn) -> String {
+        let tz: Tz = input.tz.as_deref().unwrap().parse().unwrap();
+        render_ics(&build_events(input, tz).unwrap(), input.utc)
+    }
+
+    #[test]
+    fn vevent_carries_the_zone_offset() {
+        let local = ics(&export_in("Europe/Kyiv", false));
+        assert!(local.contains("DTSTART;TZID=Europe/Kyiv:20240115T083000\r\n"));
+        // A two-slot meeting ends when the second slot does.
+        assert!(local.contains("DTEND;TZID=Europe/Kyiv:20240115T112500\r\n"));
+
+        // Kyiv is UTC+2 in January.
+        let utc = ics(&export_in("Europe/Kyiv", true));
+        assert!(utc.contains("DTSTART:20240115T063000Z\r\n"));
+        assert!(utc.contains("DTEND:20240115T092500Z\r\n"));
+    }
+
+    #[test]
+    fn unknown_zone_is_rejected() {
+        let err = render(&export_in("Mars/Olympus", false)).unwrap_err();
+        assert!(err.0.contains("unknown time zone"));
+    }
+
+    #[tokio::test]
+    async fn solve_and_export_returns_one_event_per_meeting() {
+        let mut env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        env.params.solver = types::SolverKind::Heuristic;
+        let slot_times = (1..=4)
+            .map(|i| {
+                let t = SlotTime {
+                    start: format!("{:02}:00", 7 + 2 * i),
+                    end: format!("{:02}:30", 8 + 2 * i),
+                };
+                (i, t)
+            })
+            .collect();
+        let resp = solve_and_export(
+            State(AppState::new_default()),
+            Query(FormatQuery {
+                format: ExportFormat::Ics,
+            }),
+            Json(SolveExportIn {
+                env,
+                week_start: "2024-01-15".into(),
+                slot_times,
+                tz: None,
+                utc: false,
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/calendar; charset=utf-8"
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        // c_alg meets twice and c_prog three times a week.
+        assert_eq!(body.matches("BEGIN:VEVENT").count(), 2 + 3);
+    }
+
+    #[test]
+    fn two_slot_meeting_across_days_is_rejected() {
+        let mut input = export_in("Europe/Kyiv", false);
+        input.slot_times.insert(
+            3,
+            SlotTime {
+                start: "11:40".into(),
+                end: "13:00".into(),
+            },
+        );
+        input.assignments[0].timeslot = TimeslotId("mon.3".into());
+        let tz: Tz = "Europe/Kyiv".parse().unwrap();
+        let err = build_events(&input, tz).err().unwrap();
+        assert!(err.0.contains("mon.3"), "{}", err.0);
+    }
+}
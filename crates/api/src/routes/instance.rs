@@ -0,0 +1,45 @@
+use axum::Json;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use types::Instance;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub struct InstanceStats {
+    /// Content hash that ignores entity order; equal instances share it.
+    pub fingerprint: String,
+    pub teachers: usize,
+    pub groups: usize,
+    pub rooms: usize,
+    pub courses: usize,
+    pub timeslots: usize,
+    /// Meetings per week over all courses and sections.
+    pub required_meetings: u32,
+    /// Meeting length in minutes per course, when `slot_times` cover the grid.
+    pub course_minutes: BTreeMap<String, u32>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/instance/stats",
+    request_body = Instance,
+    responses(
+    (status = 200, description = "Fingerprint and entity counts", body = InstanceStats)
+    )
+)]
+pub async fn stats(Json(inst): Json<Instance>) -> Json<InstanceStats> {
+    Json(InstanceStats {
+        fingerprint: sched_core::instance_fingerprint(&inst),
+        teachers: inst.teachers.len(),
+        groups: inst.groups.len(),
+        rooms: inst.rooms.len(),
+        courses: inst.courses.len(),
+        timeslots: inst.timeslots.len(),
+        required_meetings: inst.courses.iter().map(|c| c.required_meetings()).sum(),
+        course_minutes: inst
+            .courses
+            .iter()
+            .filter_map(|c| Some((c.id.0.clone(), sched_core::clock::course_minutes(&inst, c)?)))
+            .collect(),
+    })
+}
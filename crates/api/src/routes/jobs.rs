@@ -1,10 +1,12 @@
+use crate::error::ApiError;
 use crate::state::AppState;
 use axum::{
     extract::{Path, State},
     Json,
 };
-use types::SolveResult;
-use utoipa;
+use serde::Serialize;
+use types::{SoftWeights, SolveResult};
+use utoipa::{self, ToSchema};
 
 #[utoipa::path(
         get,
@@ -38,7 +40,137 @@ pub async fn result(
     let st = state.jobs.get(&id);
     Json(match st {
         Some(jobs::JobStatus::Solved { result }) => serde_json::to_value(result).unwrap(),
+        Some(s @ jobs::JobStatus::Infeasible { .. }) => serde_json::to_value(s).unwrap(),
+        Some(jobs::JobStatus::TimedOut { result }) => serde_json::to_value(result).unwrap(),
         Some(_) => serde_json::json!({"status": "not_ready"}),
         None => serde_json::json!({"status": "not_found"}),
     })
 }
+
+#[utoipa::path(
+        post,
+        path = "/v1/jobs/{id}/cancel",
+        params(("id" = String, Path, description = "Job ID")),
+        responses(
+            (status = 200, description = "Cancellation requested, or not_found for unknown and finished jobs")
+        )
+    )]
+pub async fn cancel(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Json<serde_json::Value> {
+    Json(if state.jobs.cancel(&id) {
+        serde_json::json!({"jobId": id, "status": "cancelling"})
+    } else {
+        serde_json::json!({"status": "not_found"})
+    })
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Retuned {
+    pub jobId: String,
+    pub previousJobId: String,
+    pub status: &'static str,
+}
+
+/// Applies the keys of `patch` over the effective weights of `policy`.
+fn patched_weights(
+    policy: &types::Policy,
+    patch: serde_json::Value,
+) -> Result<SoftWeights, ApiError> {
+    let serde_json::Value::Object(patch) = patch else {
+        return Err(ApiError("weight patch must be a JSON object".into()));
+    };
+    let mut weights = serde_json::to_value(sched_core::presets::effective_weights(policy))
+        .map_err(|e| ApiError(e.to_string()))?;
+    for (key, value) in patch {
+        match weights.get_mut(&key) {
+            Some(slot) => *slot = value,
+            None => return Err(ApiError(format!("unknown soft weight {key}"))),
+        }
+    }
+    serde_json::from_value(weights).map_err(|e| ApiError(format!("invalid weight patch: {e}")))
+}
+
+#[utoipa::path(
+        post,
+        path = "/v1/jobs/{id}/retune",
+        params(("id" = String, Path, description = "Job ID")),
+        request_body(content = serde_json::Value, description = "Subset of SoftWeights fields to override"),
+        responses(
+            (status = 200, description = "Old job cancelled if unfinished, patched copy enqueued", body = Retuned),
+            (status = 400, description = "Unknown job or invalid weight patch")
+        )
+    )]
+pub async fn retune(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(patch): Json<serde_json::Value>,
+) -> Result<Json<Retuned>, ApiError> {
+    let mut env = state
+        .jobs
+        .envelope(&id)
+        .ok_or_else(|| ApiError(format!("unknown job {id}")))?;
+    env.instance.policy.soft_weights = patched_weights(&env.instance.policy, patch)?;
+    state.jobs.cancel(&id);
+    let new_id = state.jobs.enqueue(env);
+    Ok(Json(Retuned {
+        jobId: new_id.0,
+        previousJobId: id,
+        status: "queued",
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::SolveEnvelope;
+
+    fn small() -> SolveEnvelope {
+        let mut env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        env.params.solver = types::SolverKind::Heuristic;
+        env
+    }
+
+    #[tokio::test]
+    async fn retune_enqueues_a_new_job_with_patched_weights() {
+        let state = AppState::new_default();
+        let old = state.jobs.enqueue(small()).0;
+        let Json(retuned) = retune(
+            State(state.clone()),
+            Path(old.clone()),
+            Json(serde_json::json!({"windows": 9})),
+        )
+        .await
+        .unwrap();
+
+        assert_ne!(retuned.jobId, old);
+        assert_eq!(retuned.previousJobId, old);
+        let weights = |id: &str| {
+            state
+                .jobs
+                .envelope(id)
+                .unwrap()
+                .instance
+                .policy
+                .soft_weights
+        };
+        assert_eq!(weights(&retuned.jobId).windows, 9);
+        assert_eq!(
+            weights(&old).windows,
+            small().instance.policy.soft_weights.windows
+        );
+
+        let Err(err) = retune(
+            State(state.clone()),
+            Path(old),
+            Json(serde_json::json!({"no_such_weight": 1})),
+        )
+        .await
+        else {
+            panic!("unknown weight accepted");
+        };
+        assert!(err.0.contains("unknown soft weight no_such_weight"));
+    }
+}
@@ -0,0 +1,94 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use types::{Assignment, DayOfWeek, Instance, LockMask, PartialPin};
+
+use crate::state::resolve_masks;
+
+#[derive(Deserialize, ToSchema)]
+pub struct MaskPreviewIn {
+    pub instance: Instance,
+    #[serde(default)]
+    pub base: Vec<Assignment>,
+    #[serde(default)]
+    pub pinned: Vec<Assignment>,
+    #[serde(default)]
+    pub masks: Vec<LockMask>,
+    #[serde(default)]
+    pub partial_pins: Vec<PartialPin>,
+    #[serde(default)]
+    pub frozen_days: Vec<DayOfWeek>,
+    #[serde(default)]
+    pub window_days: Vec<DayOfWeek>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct MaskPreviewOut {
+    pub pinned: Vec<Assignment>,
+    pub partial_pins: Vec<PartialPin>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/masks/preview",
+    request_body = MaskPreviewIn,
+    responses(
+    (status = 200, description = "Pins and partial pins the solver would see after applying masks", body = MaskPreviewOut)
+    )
+)]
+pub async fn preview(Json(input): Json<MaskPreviewIn>) -> Json<MaskPreviewOut> {
+    let MaskPreviewIn {
+        instance,
+        base,
+        mut pinned,
+        mut masks,
+        mut partial_pins,
+        mut frozen_days,
+        mut window_days,
+    } = input;
+    resolve_masks(
+        &instance,
+        &base,
+        &mut pinned,
+        &mut partial_pins,
+        &mut masks,
+        &mut frozen_days,
+        &mut window_days,
+    );
+    Json(MaskPreviewOut {
+        pinned,
+        partial_pins,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn full_group_mask_pins_that_group_base_meetings() {
+        let env: serde_json::Value =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let meet = |course: &str, ts: &str, room: &str, teacher: &str| serde_json::json!({"courseId": course, "timeslot": ts, "roomId": room, "teacherId": teacher});
+        let input: MaskPreviewIn = serde_json::from_value(serde_json::json!({
+            "instance": env["instance"],
+            "base": [
+                meet("c_alg", "mon.1", "r101", "t_kovalenko"),
+                meet("c_alg", "tue.1", "r101", "t_kovalenko"),
+                meet("c_prog", "mon.2", "r202", "t_petrenko"),
+            ],
+            "masks": [{"groups": ["g1_math_1"], "lock": "full"}],
+        }))
+        .unwrap();
+
+        let Json(out) = preview(Json(input)).await;
+        let pinned: Vec<(&str, &str)> = out
+            .pinned
+            .iter()
+            .map(|a| (a.courseId.0.as_str(), a.timeslot.0.as_str()))
+            .collect();
+        assert_eq!(pinned, vec![("c_alg", "mon.1"), ("c_alg", "tue.1")]);
+        assert!(out.partial_pins.is_empty());
+    }
+}
@@ -0,0 +1,44 @@
+use axum::Json;
+use serde::Deserialize;
+use types::{Instance, Policy};
+use utoipa::ToSchema;
+
+/// A whole instance, or just its policy.
+#[derive(Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum ResolveIn {
+    Instance(Instance),
+    Policy(Policy),
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/policy/resolve",
+    request_body = ResolveIn,
+    responses(
+    (status = 200, description = "Policy with preset weights and equipment implications applied", body = Policy)
+    )
+)]
+pub async fn resolve(Json(input): Json<ResolveIn>) -> Json<Policy> {
+    let policy = match input {
+        ResolveIn::Instance(inst) => inst.policy,
+        ResolveIn::Policy(policy) => policy,
+    };
+    Json(sched_core::presets::resolve_policy(&policy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn preset_only_policy_resolves_to_concrete_weights() {
+        let input: ResolveIn =
+            serde_json::from_value(serde_json::json!({"preset": "teacher_friendly"})).unwrap();
+        let Json(policy) = resolve(Json(input)).await;
+        assert_eq!(policy.preset.as_deref(), Some("teacher_friendly"));
+        assert_eq!(policy.soft_weights.unpreferred_time, 10);
+        assert_eq!(policy.soft_weights.windows, 3);
+        assert_eq!(policy.soft_weights.soft_availability, 8);
+    }
+}
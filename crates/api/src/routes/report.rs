@@ -0,0 +1,349 @@
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use sched_core::availability::slot_allowed;
+use sched_core::feasibility::{feasible_starts, Conflict};
+use sched_core::index::InstanceIndex;
+use sched_core::occupancy::Occupancy;
+use types::{
+    Assignment, CourseId, DayOfWeek, GroupId, Instance, Parity, RoomId, TeacherId, TimeslotId,
+};
+
+use crate::error::ApiError;
+
+#[derive(Deserialize, ToSchema)]
+pub struct AvailabilityIn {
+    pub instance: Instance,
+    pub assignments: Vec<Assignment>,
+    #[serde(default)]
+    pub teachers: Vec<TeacherId>,
+    #[serde(default)]
+    pub groups: Vec<GroupId>,
+}
+
+/// A slot counts as busy when a meeting uses it in either week.
+#[derive(Serialize, ToSchema)]
+pub struct AvailabilityOut {
+    /// Slots each requested teacher is available and not teaching.
+    pub teachers: BTreeMap<String, Vec<TimeslotId>>,
+    /// Slots each requested group has no meeting.
+    pub groups: BTreeMap<String, Vec<TimeslotId>>,
+    /// Slots free for every requested teacher and group, in grid order.
+    pub common: Vec<TimeslotId>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/report/conflict-graph",
+    request_body = Instance,
+    responses(
+    (status = 200, description = "Per course, the courses it can never share a slot with", body = BTreeMap<String, Vec<Conflict>>)
+    )
+)]
+pub async fn conflict_graph(Json(inst): Json<Instance>) -> Json<BTreeMap<String, Vec<Conflict>>> {
+    Json(sched_core::feasibility::conflict_graph(&inst))
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/report/availability",
+    request_body = AvailabilityIn,
+    responses(
+    (status = 200, description = "Free slots per requested teacher and group, and their intersection", body = AvailabilityOut),
+    (status = 400, description = "A requested teacher or group is not in the instance")
+    )
+)]
+pub async fn availability(
+    Json(input): Json<AvailabilityIn>,
+) -> Result<Json<AvailabilityOut>, ApiError> {
+    let inst = &input.instance;
+    let occ = Occupancy::from_assignments(inst, &input.assignments);
+    let free_slots = |free: &dyn Fn(usize) -> bool| -> Vec<usize> {
+        (0..inst.timeslots.len()).filter(|&k| free(k)).collect()
+    };
+
+    let mut free: Vec<(bool, String, Vec<usize>)> = Vec::new();
+    for id in &input.teachers {
+        let t = inst
+            .teachers
+            .iter()
+            .find(|t| t.id == *id)
+            .ok_or_else(|| ApiError(format!("unknown teacher {}", id.0)))?;
+        let slots = free_slots(&|k| {
+            slot_allowed(t, &inst.timeslots[k].0, &inst.policy)
+                && !occ.teacher_busy(&id.0, k, Parity::Every)
+        });
+        free.push((true, id.0.clone(), slots));
+    }
+    for id in &input.groups {
+        if !inst.groups.iter().any(|g| g.id == *id) {
+            return Err(ApiError(format!("unknown group {}", id.0)));
+        }
+        let slots = free_slots(&|k| !occ.group_busy(&id.0, k, Parity::Every));
+        free.push((false, id.0.clone(), slots));
+    }
+
+    let common = free_slots(&|k| free.iter().all(|(_, _, slots)| slots.contains(&k)));
+    let ids = |slots: &[usize]| -> Vec<TimeslotId> {
+        slots.iter().map(|&k| inst.timeslots[k].clone()).collect()
+    };
+    let mut out = AvailabilityOut {
+        teachers: BTreeMap::new(),
+        groups: BTreeMap::new(),
+        common: ids(&common),
+    };
+    for (is_teacher, id, slots) in &free {
+        let map = if *is_teacher {
+            &mut out.teachers
+        } else {
+            &mut out.groups
+        };
+        map.insert(id.clone(), ids(slots));
+    }
+    Ok(Json(out))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct TimetablesIn {
+    pub instance: Instance,
+    pub assignments: Vec<Assignment>,
+    /// Also list the slots each teacher is available and not teaching.
+    #[serde(default)]
+    pub include_free: bool,
+}
+
+/// One line of a teacher's week: a meeting, or with `include_free` a free
+/// slot, which has no course, room or group.
+#[derive(Serialize, ToSchema)]
+pub struct TimetableEntry {
+    pub day: DayOfWeek,
+    /// Period index within the day.
+    pub slot: u32,
+    pub timeslot: TimeslotId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub course: Option<CourseId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub room: Option<RoomId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<GroupId>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/report/teacher-timetables",
+    request_body = TimetablesIn,
+    responses(
+    (status = 200, description = "Per teacher, meetings (and optionally free slots) in day then slot order", body = BTreeMap<String, Vec<TimetableEntry>>),
+    (status = 400, description = "Invalid instance")
+    )
+)]
+pub async fn teacher_timetables(
+    Json(input): Json<TimetablesIn>,
+) -> Result<Json<BTreeMap<String, Vec<TimetableEntry>>>, ApiError> {
+    let inst = &input.instance;
+    sched_core::validate(inst).map_err(|e| ApiError(e.to_string()))?;
+    let entry = |ts: &TimeslotId| -> Option<TimetableEntry> {
+        let (day, slot, _) = ts.parse()?;
+        Some(TimetableEntry {
+            day,
+            slot,
+            timeslot: ts.clone(),
+            course: None,
+            room: None,
+            group: None,
+        })
+    };
+
+    let mut out: BTreeMap<String, Vec<TimetableEntry>> = inst
+        .teachers
+        .iter()
+        .map(|t| (t.id.0.clone(), Vec::new()))
+        .collect();
+    // Meetings on slots outside the grid are left out.
+    for a in &input.assignments {
+        if !inst.timeslots.contains(&a.timeslot) {
+            continue;
+        }
+        let Some(e) = entry(&a.timeslot) else {
+            continue;
+        };
+        let group = inst
+            .courses
+            .iter()
+            .find(|c| c.id == a.courseId)
+            .map(|c| c.groupId.clone());
+        out.entry(a.teacherId.0.clone())
+            .or_default()
+            .push(TimetableEntry {
+                course: Some(a.courseId.clone()),
+                room: Some(a.roomId.clone()),
+                group,
+                ..e
+            });
+    }
+    if input.include_free {
+        let occ = Occupancy::from_assignments(inst, &input.assignments);
+        for t in &inst.teachers {
+            let free = inst.timeslots.iter().enumerate().filter(|&(k, ts)| {
+                slot_allowed(t, &ts.0, &inst.policy) && !occ.teacher_busy(&t.id.0, k, Parity::Every)
+            });
+            let list = out.entry(t.id.0.clone()).or_default();
+            list.extend(free.filter_map(|(_, ts)| entry(ts)));
+        }
+    }
+    for list in out.values_mut() {
+        list.sort_by_cached_key(|e| {
+            let sub = e.timeslot.parse().map_or(0, |(_, _, sub)| sub);
+            (e.day, e.slot, sub)
+        });
+    }
+    Ok(Json(out))
+}
+
+/// How boxed in one course is when every other course is ignored.
+#[derive(Serialize, ToSchema)]
+pub struct Bottleneck {
+    pub course: CourseId,
+    /// Feasible `(start slot, room)` pairs in an empty grid.
+    pub placements: usize,
+    /// Slots the course's teacher is available in.
+    pub teacher_slots: usize,
+    /// At most one feasible placement: loosening anything else will not
+    /// help this course.
+    pub critical: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/report/bottlenecks",
+    request_body = Instance,
+    responses(
+    (status = 200, description = "Courses ranked most constrained first", body = Vec<Bottleneck>)
+    )
+)]
+pub async fn bottlenecks(Json(inst): Json<Instance>) -> Json<Vec<Bottleneck>> {
+    let index = InstanceIndex::new(&inst);
+    let feas = feasible_starts(&index);
+    let mut out: Vec<Bottleneck> = inst
+        .courses
+        .iter()
+        .zip(&feas)
+        .map(|(c, starts)| {
+            let teacher_slots = index
+                .teacher_by_id
+                .get(c.teacherId.0.as_str())
+                .map_or(0, |t| {
+                    index
+                        .times
+                        .iter()
+                        .filter(|ts| slot_allowed(t, ts, &inst.policy))
+                        .count()
+                });
+            Bottleneck {
+                course: c.id.clone(),
+                placements: starts.len(),
+                teacher_slots,
+                critical: starts.len() <= 1,
+            }
+        })
+        .collect();
+    out.sort_by_key(|b| (b.placements, b.teacher_slots));
+    Json(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::SolveEnvelope;
+
+    fn meeting(inst: &Instance, course: usize, ts: &str) -> Assignment {
+        let c = &inst.courses[course];
+        Assignment {
+            courseId: c.id.clone(),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r101".into()),
+            teacherId: c.teacherId.clone(),
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn common_slots_exclude_any_busy_agent() {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let instance = env.instance;
+        // t_kovalenko teaches at mon.2; g2_cs_1's double meeting fills tue.1-2.
+        let assignments = vec![
+            meeting(&instance, 0, "mon.2"),
+            meeting(&instance, 1, "tue.1"),
+        ];
+        let input = AvailabilityIn {
+            teachers: vec![instance.teachers[0].id.clone()],
+            groups: vec![instance.groups[1].id.clone()],
+            instance,
+            assignments,
+        };
+        let Json(out) = availability(Json(input)).await.unwrap();
+
+        let names = |slots: &[TimeslotId]| slots.iter().map(|t| t.0.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            names(&out.teachers["t_kovalenko"]),
+            ["mon.1", "tue.1", "tue.2", "wed.1"]
+        );
+        assert!(!names(&out.groups["g2_cs_1"]).contains(&"tue.2".to_string()));
+        assert_eq!(names(&out.common), ["mon.1", "wed.1"]);
+    }
+
+    #[tokio::test]
+    async fn teacher_timetable_lists_meetings_in_day_then_slot_order() {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let instance = env.instance;
+        let assignments = vec![
+            meeting(&instance, 0, "wed.1"),
+            meeting(&instance, 0, "tue.1"),
+            meeting(&instance, 0, "mon.2"),
+        ];
+        let timetable = |include_free: bool| {
+            teacher_timetables(Json(TimetablesIn {
+                instance: instance.clone(),
+                assignments: assignments.clone(),
+                include_free,
+            }))
+        };
+        let slots = |list: &[TimetableEntry]| -> Vec<String> {
+            list.iter().map(|e| e.timeslot.0.clone()).collect()
+        };
+
+        let Json(out) = timetable(false).await.unwrap();
+        assert_eq!(slots(&out["t_kovalenko"]), ["mon.2", "tue.1", "wed.1"]);
+        assert!(out["t_petrenko"].is_empty());
+
+        // Free slots interleave with the meetings, and carry no course.
+        let Json(out) = timetable(true).await.unwrap();
+        let week = &out["t_kovalenko"];
+        assert_eq!(slots(week), ["mon.1", "mon.2", "tue.1", "tue.2", "wed.1"]);
+        assert!(week[0].course.is_none());
+        assert_eq!(week[1].course.as_ref().unwrap().0, "c_alg");
+    }
+
+    #[tokio::test]
+    async fn single_placement_course_ranks_most_critical() {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        let mut inst = env.instance;
+        // c_prog's double meeting now fits only thu.1-2 in r202.
+        inst.teachers[1].available = vec![TimeslotId("thu.1".into()), TimeslotId("thu.2".into())];
+        inst.courses[1].allowed_rooms = vec![RoomId("r202".into())];
+
+        let Json(out) = bottlenecks(Json(inst)).await;
+        assert_eq!(out[0].course.0, "c_prog");
+        assert_eq!(out[0].placements, 1);
+        assert!(out[0].critical);
+        assert_eq!(out[1].course.0, "c_alg");
+        assert_eq!(out[1].placements, 10);
+        assert!(!out[1].critical);
+    }
+}
@@ -0,0 +1,39 @@
+use axum::Json;
+use schemars::schema::RootSchema;
+use types::{Instance, SolveEnvelope};
+
+#[utoipa::path(
+    get,
+    path = "/v1/schema/envelope",
+    responses((status = 200, description = "JSON Schema of SolveEnvelope"))
+)]
+pub async fn envelope() -> Json<RootSchema> {
+    Json(schemars::schema_for!(SolveEnvelope))
+}
+
+#[utoipa::path(
+    get,
+    path = "/v1/schema/instance",
+    responses((status = 200, description = "JSON Schema of Instance"))
+)]
+pub async fn instance() -> Json<RootSchema> {
+    Json(schemars::schema_for!(Instance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn envelope_schema_lists_partial_pins() {
+        let Json(schema) = envelope().await;
+        let props = &schema.schema.object.as_ref().unwrap().properties;
+        assert!(props.contains_key("partial_pins"));
+        assert!(props.contains_key("instance"));
+
+        let Json(schema) = instance().await;
+        let props = &schema.schema.object.as_ref().unwrap().properties;
+        assert!(props.contains_key("courses"));
+        assert!(!props.contains_key("partial_pins"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::extract::ApiJson;
 use crate::state::AppState;
 use axum::{extract::State, Json};
 use serde::Deserialize;
@@ -20,11 +21,12 @@ pub struct JobCreated {
         post,
         path = "/v1/solve",
         request_body = SolveEnvelope,
+        params(("strict" = Option<bool>, Query, description = "Reject fields the schema does not know")),
         responses((status = 200, description = "Job enqueued", body = JobCreated))
     )]
 pub async fn solve(
     State(state): State<AppState>,
-    Json(env): Json<SolveEnvelope>,
+    ApiJson(env): ApiJson<SolveEnvelope>,
 ) -> Json<JobCreated> {
     let id = state.jobs.enqueue(env);
     Json(JobCreated {
@@ -37,11 +39,12 @@ pub async fn solve(
     post,
     path = "/v1/reoptimize",
     request_body = SolveEnvelope,
+    params(("strict" = Option<bool>, Query, description = "Reject fields the schema does not know")),
     responses((status = 200, description = "Reoptimize job enqueued", body = JobCreated))
 )]
 pub async fn reoptimize(
     State(state): State<AppState>,
-    Json(env): Json<SolveEnvelope>,
+    ApiJson(env): ApiJson<SolveEnvelope>,
 ) -> Json<JobCreated> {
     let id = state.jobs.enqueue(env);
     Json(JobCreated {
@@ -49,3 +52,114 @@ pub async fn reoptimize(
         status: "queued",
     })
 }
+
+#[derive(serde::Serialize, ToSchema)]
+pub struct BatchItem {
+    pub index: usize,
+    pub jobId: Option<String>,
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    /// Redundant pins and partial pins; the solver drops them before use.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/solve/batch",
+    request_body = Vec<SolveEnvelope>,
+    params(("strict" = Option<bool>, Query, description = "Reject fields the schema does not know")),
+    responses((status = 200, description = "One entry per envelope: queued job or validation errors", body = [BatchItem]))
+)]
+pub async fn solve_batch(
+    State(state): State<AppState>,
+    ApiJson(envs): ApiJson<Vec<SolveEnvelope>>,
+) -> Json<Vec<BatchItem>> {
+    let items = envs
+        .into_iter()
+        .enumerate()
+        .map(
+            |(index, mut env)| match sched_core::validate(&env.instance) {
+                Ok(()) => {
+                    let warnings = sched_core::locks::dedup_locks(&mut env);
+                    BatchItem {
+                        index,
+                        jobId: Some(state.jobs.enqueue(env).0),
+                        status: "queued",
+                        errors: vec![],
+                        warnings,
+                    }
+                }
+                Err(sched_core::ValidationError::Msg(msg)) => BatchItem {
+                    index,
+                    jobId: None,
+                    status: "invalid",
+                    errors: msg
+                        .split(';')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect(),
+                    warnings: vec![],
+                },
+            },
+        )
+        .collect();
+    Json(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small() -> SolveEnvelope {
+        let mut env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../../examples/small_instance.json")).unwrap();
+        env.params.solver = types::SolverKind::Heuristic;
+        env
+    }
+
+    #[tokio::test]
+    async fn batch_enqueues_each_valid_envelope_separately() {
+        let state = AppState::new_default();
+        let mut broken = small();
+        broken.instance.courses[0].groupId = types::GroupId("g_missing".into());
+        let Json(items) = solve_batch(
+            State(state.clone()),
+            ApiJson(vec![small(), small(), broken]),
+        )
+        .await;
+
+        assert_eq!(items.len(), 3);
+        let (a, b) = (
+            items[0].jobId.clone().unwrap(),
+            items[1].jobId.clone().unwrap(),
+        );
+        assert_ne!(a, b);
+        assert!(state.jobs.get(&a).is_some());
+        assert!(state.jobs.get(&b).is_some());
+
+        assert_eq!(items[2].index, 2);
+        assert_eq!(items[2].status, "invalid");
+        assert!(items[2].jobId.is_none());
+        assert!(items[2].errors.iter().any(|e| e.contains("g_missing")));
+    }
+
+    #[tokio::test]
+    async fn batch_warns_about_duplicate_pins() {
+        let state = AppState::new_default();
+        let mut env = small();
+        let pin = types::Assignment {
+            courseId: types::CourseId("c_alg".into()),
+            timeslot: types::TimeslotId("mon.1".into()),
+            roomId: types::RoomId("r101".into()),
+            teacherId: types::TeacherId("t_kovalenko".into()),
+            meta: None,
+        };
+        env.pinned = vec![pin.clone(), pin];
+        let Json(items) = solve_batch(State(state), ApiJson(vec![env, small()])).await;
+        assert_eq!(items[0].warnings.len(), 1);
+        assert!(items[0].warnings[0].contains("duplicate pin"));
+        assert!(items[1].warnings.is_empty());
+    }
+}
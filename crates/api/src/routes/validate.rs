@@ -1,30 +1,39 @@
+use crate::extract::ApiJson;
 use axum::{http::StatusCode, Json};
-use sched_core::{validate, ValidationError};
+use sched_core::{capacity_warnings, validate, ValidationError};
 use serde::Serialize;
-use types::Instance;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use types::{Instance, TimeslotId};
 
 #[derive(Serialize, utoipa::ToSchema)]
 pub struct ValidationReport {
     pub ok: bool,
     #[serde(default)]
     pub errors: Vec<String>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 #[utoipa::path(
     post,
     path = "/v1/validate",
     request_body = Instance,
+    params(("strict" = Option<bool>, Query, description = "Reject fields the schema does not know")),
     responses(
     (status = 200, description = "Validation result", body = ValidationReport)
     )
 )]
-pub async fn validate_handler(Json(inst): Json<Instance>) -> (StatusCode, Json<ValidationReport>) {
+pub async fn validate_handler(
+    ApiJson(inst): ApiJson<Instance>,
+) -> (StatusCode, Json<ValidationReport>) {
+    let warnings = capacity_warnings(&inst);
     match validate(&inst) {
         Ok(()) => (
             StatusCode::OK,
             Json(ValidationReport {
                 ok: true,
                 errors: vec![],
+                warnings,
             }),
         ),
         Err(ValidationError::Msg(msg)) => {
@@ -38,8 +47,119 @@ pub async fn validate_handler(Json(inst): Json<Instance>) -> (StatusCode, Json<V
                 Json(ValidationReport {
                     ok: false,
                     errors: errs,
+                    warnings,
                 }),
             )
         }
     }
 }
+
+#[derive(Serialize, utoipa::ToSchema)]
+pub struct TimeslotReport {
+    pub ok: bool,
+    /// Ids not of the form `<mon..sun>.<index>` or, for a period split into
+    /// sub-slots, `<mon..sun>.<index><a..z>` (`mon.1a`, `mon.1b`).
+    pub invalid: Vec<String>,
+    /// Ids listed more than once, each reported once.
+    pub duplicates: Vec<String>,
+    /// Valid distinct slots per day, each sub-slot counted on its own.
+    pub per_day: BTreeMap<String, usize>,
+    /// Missing indices between a day's first and last slot, e.g. `mon.3`
+    /// when `mon.2` and `mon.4` exist, and missing sub-slots before the last
+    /// one listed for a period, e.g. `mon.1a` and `mon.1c` when only
+    /// `mon.1b` and `mon.1d` exist. Two-slot meetings cannot span them.
+    pub gaps: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/timeslots/validate",
+    request_body = Vec<TimeslotId>,
+    responses(
+    (status = 200, description = "Format, duplicate and contiguity checks for a weekly grid", body = TimeslotReport)
+    )
+)]
+pub async fn validate_timeslots(Json(slots): Json<Vec<TimeslotId>>) -> Json<TimeslotReport> {
+    let mut invalid = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut seen = HashSet::new();
+    let mut indices: BTreeMap<String, BTreeSet<(u32, u32)>> = BTreeMap::new();
+    for t in &slots {
+        if !t.is_valid_format() {
+            invalid.push(t.0.clone());
+            continue;
+        }
+        if !seen.insert(&t.0) {
+            if !duplicates.contains(&t.0) {
+                duplicates.push(t.0.clone());
+            }
+            continue;
+        }
+        if let Some((day, idx, sub)) = t.parse() {
+            indices
+                .entry(day.abbrev().to_string())
+                .or_default()
+                .insert((idx, sub));
+        }
+    }
+    let mut gaps = Vec::new();
+    for (day, slots) in &indices {
+        let (Some(&(first, _)), Some(&(last, _))) = (slots.first(), slots.last()) else {
+            continue;
+        };
+        for i in first..=last {
+            let subs: Vec<u32> = slots
+                .range((i, 0)..=(i, u32::MAX))
+                .map(|&(_, sub)| sub)
+                .collect();
+            match subs.last() {
+                None => gaps.push(format!("{day}.{i}")),
+                Some(&top) => gaps.extend(
+                    (1..top)
+                        .filter(|sub| !subs.contains(sub))
+                        .map(|sub| format!("{day}.{i}{}", char::from(b'a' + sub as u8 - 1))),
+                ),
+            }
+        }
+    }
+    Json(TimeslotReport {
+        ok: invalid.is_empty() && duplicates.is_empty() && !slots.is_empty(),
+        invalid,
+        duplicates,
+        per_day: indices
+            .into_iter()
+            .map(|(d, slots)| (d, slots.len()))
+            .collect(),
+        gaps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn malformed_and_duplicate_slots_are_flagged() {
+        let slots = ["mon.1", "mon.2", "monday.3", "mon.2", "mon.4", "tue.1"]
+            .map(|s| TimeslotId(s.into()))
+            .to_vec();
+        let Json(report) = validate_timeslots(Json(slots)).await;
+        assert!(!report.ok);
+        assert_eq!(report.invalid, ["monday.3"]);
+        assert_eq!(report.duplicates, ["mon.2"]);
+        assert_eq!(report.per_day["mon"], 3);
+        assert_eq!(report.per_day["tue"], 1);
+        assert_eq!(report.gaps, ["mon.3"]);
+    }
+
+    #[tokio::test]
+    async fn sub_slots_count_separately_and_report_their_gaps() {
+        let slots = ["mon.1b", "mon.1d", "mon.2", "mon.3a", "mon.3b", "tue.1"]
+            .map(|s| TimeslotId(s.into()))
+            .to_vec();
+        let Json(report) = validate_timeslots(Json(slots)).await;
+        assert!(report.ok);
+        assert_eq!(report.per_day["mon"], 5);
+        assert_eq!(report.gaps, ["mon.1a", "mon.1c"]);
+    }
+}
@@ -1,19 +1,27 @@
+use crate::cache::ResultCache;
 use async_trait::async_trait;
 use jobs::InMemJobs;
-use sched_core::{SolveEnvelope, SolveResult, Solver};
-use solver_heur::HeurSolver;
+use sched_core::index::InstanceIndex;
+use sched_core::{CancelToken, SolveEnvelope, SolveResult, Solver};
+use solver_heur::{HeurSolver, RepairLimits};
 use solver_milp::MilpSolver;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 pub struct AppState {
     pub jobs: Arc<InMemJobs<DispatchSolver>>,
+    /// Same solver as the job store, for endpoints that answer synchronously.
+    pub solver: DispatchSolver,
 }
 
 #[derive(Clone)]
 pub struct DispatchSolver {
     milp: Arc<MilpSolver>,
     heur: Arc<HeurSolver>,
+    /// Results of earlier identical envelopes; `None` disables caching.
+    cache: Option<Arc<ResultCache>>,
 }
 
 impl DispatchSolver {
@@ -21,12 +29,18 @@ impl DispatchSolver {
         Self {
             milp: Arc::new(MilpSolver::new()),
             heur: Arc::new(HeurSolver::new()),
+            cache: None,
         }
     }
-}
 
-fn day_of(ts: &str) -> &str {
-    ts.split('.').next().unwrap_or(ts)
+    /// Keeps up to `capacity` results and answers repeated identical solves
+    /// from them.
+    pub fn with_cache(capacity: usize) -> Self {
+        Self {
+            cache: Some(Arc::new(ResultCache::new(capacity))),
+            ..Self::new()
+        }
+    }
 }
 
 fn mask_matches(m: &types::LockMask, a: &types::Assignment, inst: &types::Instance) -> bool {
@@ -48,16 +62,12 @@ fn mask_matches(m: &types::LockMask, a: &types::Assignment, inst: &types::Instan
         ok &= m.rooms.iter().any(|r| r == &a.roomId);
     }
     if !m.days.is_empty() {
-        let d = match day_of(&a.timeslot.0) {
-            "mon" => types::DayOfWeek::Mon,
-            "tue" => types::DayOfWeek::Tue,
-            "wed" => types::DayOfWeek::Wed,
-            "thu" => types::DayOfWeek::Thu,
-            "fri" => types::DayOfWeek::Fri,
-            "sat" => types::DayOfWeek::Sat,
-            _ => types::DayOfWeek::Sun,
-        };
-        ok &= m.days.iter().any(|x| *x == d);
+        // A slot whose id does not parse is on no day, so a day mask
+        // never matches it.
+        ok &= a
+            .timeslot
+            .parse()
+            .is_some_and(|(d, _, _)| m.days.contains(&d));
     }
     if !m.times.is_empty() {
         ok &= m.times.iter().any(|t| t == &a.timeslot);
@@ -65,6 +75,19 @@ fn mask_matches(m: &types::LockMask, a: &types::Assignment, inst: &types::Instan
     ok
 }
 
+/// Every field of a partial pin, for sorting and deduplicating them.
+fn partial_pin_key(
+    p: &types::PartialPin,
+) -> (&str, Option<&str>, Option<&str>, Option<&str>, Option<u32>) {
+    (
+        &p.courseId.0,
+        p.timeslot.as_ref().map(|x| x.0.as_str()),
+        p.roomId.as_ref().map(|x| x.0.as_str()),
+        p.teacherId.as_ref().map(|x| x.0.as_str()),
+        p.meeting,
+    )
+}
+
 fn partial_pin_matches_mask(
     m: &types::LockMask,
     p: &types::PartialPin,
@@ -91,28 +114,96 @@ fn partial_pin_matches_mask(
             .roomId
             .clone()
             .unwrap_or_else(|| types::RoomId(String::new())),
-        teacherId: course.teacherId.clone(),
+        teacherId: p
+            .teacherId
+            .clone()
+            .unwrap_or_else(|| course.teacherId.clone()),
+        meta: None,
     };
 
     mask_matches(m, &fake, inst)
 }
 
+/// `env` as `dispatch` hands it to a backend: normalized, with redundant
+/// locks dropped, masks resolved and overrides applied.
+pub(crate) fn backend_envelope(mut env: SolveEnvelope) -> SolveEnvelope {
+    sched_core::normalize(&mut env.instance);
+    sched_core::locks::dedup_locks(&mut env);
+    let mut env = apply_masks(env);
+    sched_core::overrides::restrict_to_groups(&mut env);
+    sched_core::overrides::split_teacher_overrides(&mut env);
+    env
+}
+
 fn apply_masks(mut env: types::SolveEnvelope) -> types::SolveEnvelope {
-    if env.masks.is_empty() {
-        return env;
+    resolve_masks(
+        &env.instance,
+        &env.base,
+        &mut env.pinned,
+        &mut env.partial_pins,
+        &mut env.masks,
+        &mut env.frozen_days,
+        &mut env.window_days,
+    );
+    env
+}
+
+/// Turns `masks`, `frozen_days` and `window_days` over `base` into the
+/// `pinned` and `partial_pins` the solver sees. Negated masks drop matching
+/// locks first.
+pub(crate) fn resolve_masks(
+    inst: &types::Instance,
+    base: &[types::Assignment],
+    pinned: &mut Vec<types::Assignment>,
+    partial_pins: &mut Vec<types::PartialPin>,
+    masks: &mut Vec<types::LockMask>,
+    frozen_days: &mut Vec<types::DayOfWeek>,
+    window_days: &mut Vec<types::DayOfWeek>,
+) {
+    if !frozen_days.is_empty() {
+        masks.push(types::LockMask {
+            courses: vec![],
+            groups: vec![],
+            teachers: vec![],
+            rooms: vec![],
+            days: std::mem::take(frozen_days),
+            times: vec![],
+            lock: types::LockMode::Full,
+            negate: false,
+        });
+    }
+    let window = std::mem::take(window_days);
+    let outside: Vec<types::DayOfWeek> = types::DayOfWeek::ALL
+        .into_iter()
+        .filter(|d| !window.contains(d))
+        .collect();
+    // An empty `days` list would match every day, so a window spanning the
+    // whole week adds no mask.
+    if !window.is_empty() && !outside.is_empty() {
+        masks.push(types::LockMask {
+            courses: vec![],
+            groups: vec![],
+            teachers: vec![],
+            rooms: vec![],
+            days: outside,
+            times: vec![],
+            lock: types::LockMode::Full,
+            negate: false,
+        });
+    }
+    if masks.is_empty() {
+        return;
     }
 
     use std::collections::HashSet;
 
-    for m in env.masks.iter().filter(|m| m.negate) {
-        env.pinned.retain(|a| !mask_matches(m, a, &env.instance));
+    for m in masks.iter().filter(|m| m.negate) {
+        pinned.retain(|a| !mask_matches(m, a, inst));
 
-        env.partial_pins
-            .retain(|p| !partial_pin_matches_mask(m, p, &env.instance));
+        partial_pins.retain(|p| !partial_pin_matches_mask(m, p, inst));
     }
 
-    let mut pins_set: HashSet<(String, String, String, String)> = env
-        .pinned
+    let mut pins_set: HashSet<(String, String, String, String)> = pinned
         .iter()
         .map(|a| {
             (
@@ -124,14 +215,14 @@ fn apply_masks(mut env: types::SolveEnvelope) -> types::SolveEnvelope {
         })
         .collect();
 
-    let mut partial: Vec<types::PartialPin> = env.partial_pins.clone();
+    let mut partial: Vec<types::PartialPin> = std::mem::take(partial_pins);
 
-    for m in &env.masks {
+    for m in masks.iter() {
         if m.negate {
             continue;
         }
-        for a in &env.base {
-            if !mask_matches(m, a, &env.instance) {
+        for a in base {
+            if !mask_matches(m, a, inst) {
                 continue;
             }
             match m.lock {
@@ -143,7 +234,7 @@ fn apply_masks(mut env: types::SolveEnvelope) -> types::SolveEnvelope {
                         a.teacherId.0.clone(),
                     );
                     if !pins_set.contains(&key) {
-                        env.pinned.push(a.clone());
+                        pinned.push(a.clone());
                         pins_set.insert(key);
                     }
                 }
@@ -152,6 +243,8 @@ fn apply_masks(mut env: types::SolveEnvelope) -> types::SolveEnvelope {
                         courseId: a.courseId.clone(),
                         timeslot: Some(a.timeslot.clone()),
                         roomId: None,
+                        teacherId: None,
+                        meeting: None,
                     });
                 }
                 types::LockMode::RoomOnly => {
@@ -159,6 +252,8 @@ fn apply_masks(mut env: types::SolveEnvelope) -> types::SolveEnvelope {
                         courseId: a.courseId.clone(),
                         timeslot: None,
                         roomId: Some(a.roomId.clone()),
+                        teacherId: None,
+                        meeting: None,
                     });
                 }
                 types::LockMode::TimeAndRoom => {
@@ -166,84 +261,599 @@ fn apply_masks(mut env: types::SolveEnvelope) -> types::SolveEnvelope {
                         courseId: a.courseId.clone(),
                         timeslot: Some(a.timeslot.clone()),
                         roomId: Some(a.roomId.clone()),
+                        teacherId: None,
+                        meeting: None,
                     });
                 }
             }
         }
     }
 
-    partial.sort_by(|a, b| {
-        (
-            &a.courseId.0,
-            &a.timeslot.as_ref().map(|x| &x.0),
-            &a.roomId.as_ref().map(|x| &x.0),
-        )
-            .cmp(&(
-                &b.courseId.0,
-                &b.timeslot.as_ref().map(|x| &x.0),
-                &b.roomId.as_ref().map(|x| &x.0),
-            ))
-    });
-    partial.dedup_by(|a, b| {
-        a.courseId == b.courseId && a.timeslot == b.timeslot && a.roomId == b.roomId
-    });
+    partial.sort_by(|a, b| partial_pin_key(a).cmp(&partial_pin_key(b)));
+    partial.dedup_by(|a, b| partial_pin_key(a) == partial_pin_key(b));
 
-    env.partial_pins = partial;
-    env
+    *partial_pins = partial;
 }
 
 #[async_trait]
 impl Solver for DispatchSolver {
     async fn solve(&self, env: SolveEnvelope) -> anyhow::Result<SolveResult> {
-        let env = apply_masks(env);
+        self.solve_cancellable(env, CancelToken::new()).await
+    }
+
+    async fn solve_cancellable(
+        &self,
+        mut env: SolveEnvelope,
+        cancel: CancelToken,
+    ) -> anyhow::Result<SolveResult> {
+        sched_core::normalize(&mut env.instance);
+        let redundant_locks = sched_core::locks::dedup_locks(&mut env);
+        let cache_key = sched_core::fingerprint::reproduce_token(&env);
+        if let Some(cache) = &self.cache {
+            if let Some(mut hit) = cache.get(&cache_key) {
+                hit.stats["cache_hit"] = serde_json::json!(true);
+                return Ok(hit);
+            }
+        }
+        let fingerprint = sched_core::instance_fingerprint(&env.instance);
+        let input_meta = input_meta(&env);
+        let budget = env.params.objectiveBudget;
+        let mut env = apply_masks(env);
+        sched_core::overrides::restrict_to_groups(&mut env);
+        let instance = env.instance.clone();
+        let synthetic = sched_core::overrides::split_teacher_overrides(&mut env);
+        let mut res = self.dispatch(env, cancel).await?;
+        if !synthetic.is_empty() {
+            sched_core::overrides::restore_course_ids(&mut res.assignments, &synthetic);
+            res.stats["placement"] =
+                sched_core::feasibility::placement_report(&instance, &res.assignments);
+        }
+        res.stats["instance_fingerprint"] = serde_json::json!(fingerprint);
+        if !redundant_locks.is_empty() {
+            res.stats["redundant_locks"] = serde_json::json!(redundant_locks);
+        }
+        // Repair may have moved the objective since the backend judged it.
+        if let Some(budget) = budget {
+            res.stats["budget_met"] = serde_json::json!(sched_core::budget_met(&res, budget));
+        }
+        let scores = sched_core::scoring::compute_soft_scores(&instance, &res.assignments);
+        res.stats["objective_components"] = serde_json::json!(scores
+            .components
+            .iter()
+            .map(|c| (
+                c.term.to_string(),
+                serde_json::json!({
+                    "count": c.count,
+                    "weight": c.weight,
+                    "contribution": c.contribution,
+                })
+            ))
+            .collect::<serde_json::Map<_, _>>());
+        for a in &mut res.assignments {
+            if let Some(m) = input_meta.get(&(a.courseId.0.clone(), a.timeslot.0.clone())) {
+                a.meta = Some(m.clone());
+            }
+        }
+        if let Some(cache) = &self.cache {
+            res.stats["cache_hit"] = serde_json::json!(false);
+            if cacheable(&res) {
+                cache.put(cache_key, res.clone());
+            }
+        }
+        Ok(res)
+    }
+}
+
+/// Whether solving the same request again would give `res` back. A timeout,
+/// a repair skipped for lack of time, or a greedy fallback after a backend
+/// error depends on timing rather than on the input, so is not cached.
+fn cacheable(res: &SolveResult) -> bool {
+    matches!(res.status.as_str(), "solved" | "infeasible")
+        && res.stats["fallback"] != true
+        && res.stats["repair_skipped"] != true
+}
+
+/// Metadata on `pinned`/`base` input meetings, keyed by course and slot.
+/// Pinned entries win over base ones for the same key.
+fn input_meta(env: &SolveEnvelope) -> HashMap<(String, String), serde_json::Value> {
+    env.base
+        .iter()
+        .chain(env.pinned.iter())
+        .filter_map(|a| {
+            a.meta
+                .as_ref()
+                .map(|m| ((a.courseId.0.clone(), a.timeslot.0.clone()), m.clone()))
+        })
+        .collect()
+}
+
+impl DispatchSolver {
+    async fn dispatch(
+        &self,
+        env: SolveEnvelope,
+        cancel: CancelToken,
+    ) -> anyhow::Result<SolveResult> {
         match env.params.solver {
             types::SolverKind::Milp => {
-                let milp_env = env.clone();
-                let mut res = self.milp.solve(env).await?;
-
-                if res.status == "solved" && milp_env.params.repairLocalSearch {
-                    let before = res.objective;
-
-                    let steps = milp_env
-                        .params
-                        .repairSteps
-                        .map(|x| x as usize)
-                        .unwrap_or_else(|| (res.assignments.len().saturating_mul(5)).max(200));
-
-                    let (imp_assign, imp_obj) = self.heur.improve_from(
-                        &milp_env.instance,
-                        res.assignments.clone(),
-                        &milp_env.pinned,
-                        &milp_env.partial_pins,
-                        milp_env.params.seed,
-                        steps,
-                    );
+                // One index serves the MILP model and the repair after it.
+                let index = InstanceIndex::new(&env.instance);
+                let started = Instant::now();
+                let mut res = self.milp.solve_indexed(&env, &index).await?;
+                res.stats["milp_secs"] = serde_json::json!(started.elapsed().as_secs_f64());
 
-                    res.stats["method"] = serde_json::json!("milp+ga");
-                    res.stats["improved"] = serde_json::json!(false);
-                    res.stats["repair_steps"] = serde_json::json!(steps);
+                // `timeLimitSec` caps MILP and repair together; repair gets
+                // whatever the MILP left. 0 leaves both unlimited.
+                let limit = env.params.timeLimitSec;
+                let deadline = (limit > 0).then(|| started + Duration::from_secs(limit));
+                self.repair(&env, &index, &mut res, deadline, &cancel);
 
-                    if imp_obj < before {
-                        res.stats["before_objective"] = serde_json::json!(before);
-                        res.stats["after_objective"] = serde_json::json!(imp_obj);
-                        res.stats["improved"] = serde_json::json!(true);
-
-                        res.assignments = imp_assign;
-                        res.objective = imp_obj;
-                    }
+                // The MILP itself cannot be interrupted; a cancel that
+                // arrived during it or the repair still ends the job as
+                // cancelled, with the best schedule found.
+                if cancel.is_cancelled() {
+                    res.status = "cancelled".into();
                 }
                 Ok(res)
             }
-            types::SolverKind::Heuristic => self.heur.solve(env).await,
+            types::SolverKind::Heuristic => self.heur.solve_cancellable(env, cancel).await,
         }
     }
+
+    /// Local-search repair of a solved MILP (or greedy fallback) result,
+    /// skipped when `deadline` has already passed.
+    fn repair(
+        &self,
+        env: &SolveEnvelope,
+        index: &InstanceIndex,
+        res: &mut SolveResult,
+        deadline: Option<Instant>,
+        cancel: &CancelToken,
+    ) {
+        let out_of_time = deadline.is_some_and(|d| Instant::now() >= d);
+        if res.status == "solved" && env.params.repairLocalSearch && out_of_time {
+            res.stats["repair_skipped"] = serde_json::json!(true);
+        } else if res.status == "solved" && env.params.repairLocalSearch {
+            let before = res.objective;
+            let repair_started = Instant::now();
+
+            let steps = env
+                .params
+                .repairSteps
+                .map(|x| x as usize)
+                .unwrap_or_else(|| (res.assignments.len().saturating_mul(5)).max(200));
+
+            let (imp_assign, imp_obj) = self.heur.improve_from(
+                index,
+                res.assignments.clone(),
+                &env.pinned,
+                &env.partial_pins,
+                env.params.seed,
+                &RepairLimits {
+                    steps,
+                    deadline,
+                    cancel: cancel.clone(),
+                },
+            );
+            res.stats["repair_secs"] = serde_json::json!(repair_started.elapsed().as_secs_f64());
+
+            let method = if res.stats["fallback"] == true {
+                "greedy+ga"
+            } else {
+                "milp+ga"
+            };
+            res.stats["method"] = serde_json::json!(method);
+            res.stats["improved"] = serde_json::json!(false);
+            res.stats["repair_steps"] = serde_json::json!(steps);
+            let min_gain = repair_min_gain(&env.params, before);
+            res.stats["repair_min_gain"] = serde_json::json!(min_gain);
+
+            if before - imp_obj > min_gain {
+                res.stats["before_objective"] = serde_json::json!(before);
+                res.stats["after_objective"] = serde_json::json!(imp_obj);
+                res.stats["improved"] = serde_json::json!(true);
+                res.stats["proven_optimal"] = serde_json::json!(false);
+
+                res.assignments = imp_assign;
+                res.objective = imp_obj;
+            }
+        }
+    }
+}
+
+/// Cached results kept when `UNISCHEDULE__CACHE__SIZE` is unset.
+const DEFAULT_CACHE_SIZE: usize = 64;
+
+/// Below this gain a repaired schedule is float noise, not an improvement.
+const REPAIR_EPSILON: f64 = 1e-9;
+
+fn repair_min_gain(params: &types::SolveParams, before: f64) -> f64 {
+    let abs = params.repairMinGain.unwrap_or(REPAIR_EPSILON);
+    let rel = params.repairMinGainRel.unwrap_or(0.0) * before.abs();
+    abs.max(rel).max(0.0)
 }
 
 impl AppState {
     pub fn new_default() -> Self {
-        let jobs = InMemJobs::new(DispatchSolver::new());
+        let cache_on = std::env::var("UNISCHEDULE__CACHE__ENABLED").is_ok_and(|v| v == "true");
+        let solver = if cache_on {
+            let size = std::env::var("UNISCHEDULE__CACHE__SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_CACHE_SIZE);
+            DispatchSolver::with_cache(size)
+        } else {
+            DispatchSolver::new()
+        };
+        let jobs = match std::env::var("UNISCHEDULE__JOBS__MAX_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            Some(max) => InMemJobs::with_max_concurrency(solver.clone(), max),
+            None => InMemJobs::new(solver.clone()),
+        };
         Self {
             jobs: Arc::new(jobs),
+            solver,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{Assignment, DayOfWeek, PartialPin, RoomId, SolverKind, TeacherId, TimeslotId};
+
+    fn small() -> SolveEnvelope {
+        serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn greedy_fallback_is_repaired_like_a_milp_result() {
+        let mut env = small();
+        env.params.repairSteps = Some(50);
+        let res = DispatchSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        assert_eq!(res.stats["repair_steps"], 50);
+        // Without the CBC backend the MILP path falls back to greedy.
+        let method = if res.stats["fallback"] == true {
+            assert_eq!(res.stats["fallback_reason"], "feature_disabled");
+            "greedy+ga"
+        } else {
+            "milp+ga"
+        };
+        assert_eq!(res.stats["method"], method);
+    }
+
+    #[tokio::test]
+    async fn sub_threshold_repair_gain_is_discarded() {
+        let mut env = small();
+        env.params.repairSteps = Some(200);
+        // Greedy takes the earliest free slots, which c_alg's teacher avoids.
+        env.instance.policy.soft_weights.unpreferred_time = 20;
+        env.instance.teachers[0].prefs.avoid_slots =
+            vec![TimeslotId("mon.1".into()), TimeslotId("mon.2".into())];
+        let res = DispatchSolver::new().solve(env.clone()).await.unwrap();
+        assert_eq!(res.stats["improved"], true);
+        let before = res.stats["before_objective"].as_f64().unwrap();
+        let gain = before - res.objective;
+
+        // The same repair gain, now exactly at the threshold, is not enough.
+        env.params.repairMinGain = Some(gain);
+        let res = DispatchSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.stats["improved"], false);
+        assert_eq!(res.stats["repair_min_gain"], gain);
+        assert_eq!(res.objective, before);
+        assert!(res.stats.get("after_objective").is_none());
+    }
+
+    fn alg(env: &SolveEnvelope, ts: &str) -> Assignment {
+        let c = &env.instance.courses[0];
+        Assignment {
+            courseId: c.id.clone(),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r101".into()),
+            teacherId: c.teacherId.clone(),
+            meta: None,
         }
     }
+
+    #[tokio::test]
+    async fn frozen_days_keep_their_base_meetings() {
+        let mut env = small();
+        env.params.solver = SolverKind::Heuristic;
+        env.base = vec![alg(&env, "mon.1"), alg(&env, "wed.1")];
+        env.frozen_days = vec![DayOfWeek::Mon, DayOfWeek::Tue];
+
+        let resolved = apply_masks(env.clone());
+        let pinned: Vec<&str> = resolved
+            .pinned
+            .iter()
+            .map(|a| a.timeslot.0.as_str())
+            .collect();
+        assert_eq!(pinned, vec!["mon.1"]);
+
+        let res = DispatchSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        assert!(res
+            .assignments
+            .iter()
+            .any(|a| a.courseId.0 == "c_alg" && a.timeslot.0 == "mon.1" && a.roomId.0 == "r101"));
+    }
+
+    #[tokio::test]
+    async fn entity_order_does_not_change_the_result() {
+        let mut env = small();
+        env.params.solver = SolverKind::Heuristic;
+        let mut shuffled = env.clone();
+        let inst = &mut shuffled.instance;
+        inst.teachers.reverse();
+        inst.groups.reverse();
+        inst.rooms.reverse();
+        inst.courses.reverse();
+        inst.timeslots.reverse();
+
+        let solver = DispatchSolver::new();
+        let a = solver.solve(env).await.unwrap();
+        let b = solver.solve(shuffled).await.unwrap();
+        assert_eq!(a.status, "solved");
+        assert_eq!(
+            serde_json::to_value(&a.assignments).unwrap(),
+            serde_json::to_value(&b.assignments).unwrap()
+        );
+        assert_eq!(a.objective, b.objective);
+    }
+
+    #[tokio::test]
+    async fn substitute_meeting_respects_the_substitute_availability() {
+        let mut env = small();
+        env.params.solver = SolverKind::Heuristic;
+        let mut sub = env.instance.teachers[0].clone();
+        sub.id = TeacherId("t_sub".into());
+        sub.available = vec![TimeslotId("wed.2".into())];
+        env.instance.teachers.push(sub);
+        env.partial_pins = vec![PartialPin {
+            courseId: env.instance.courses[0].id.clone(),
+            timeslot: None,
+            roomId: None,
+            teacherId: Some(TeacherId("t_sub".into())),
+            meeting: None,
+        }];
+
+        let res = DispatchSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        let alg: Vec<&Assignment> = res
+            .assignments
+            .iter()
+            .filter(|a| a.courseId.0 == "c_alg")
+            .collect();
+        assert_eq!(alg.len(), 2);
+        let by_sub: Vec<&&Assignment> = alg.iter().filter(|a| a.teacherId.0 == "t_sub").collect();
+        assert_eq!(by_sub.len(), 1);
+        assert_eq!(by_sub[0].timeslot.0, "wed.2");
+        assert_eq!(res.stats["placement"]["c_alg"]["placed"], 2);
+    }
+
+    #[tokio::test]
+    async fn pinned_metadata_survives_a_reoptimize() {
+        let mut env = small();
+        env.params.solver = SolverKind::Heuristic;
+        env.instance.courses[1].meta = Some(serde_json::json!({"lms": "PROG-1"}));
+        let mut pin = alg(&env, "mon.1");
+        pin.meta = Some(serde_json::json!({"lms": "ALG-7"}));
+        env.pinned = vec![pin];
+
+        let res = DispatchSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        for a in &res.assignments {
+            let expected = match (a.courseId.0.as_str(), a.timeslot.0.as_str()) {
+                ("c_alg", "mon.1") => Some(serde_json::json!({"lms": "ALG-7"})),
+                ("c_alg", _) => None,
+                _ => Some(serde_json::json!({"lms": "PROG-1"})),
+            };
+            assert_eq!(a.meta, expected, "{} at {}", a.courseId.0, a.timeslot.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn objective_components_sum_to_the_objective() {
+        let mut env = small();
+        env.params.solver = SolverKind::Heuristic;
+        let weights = &mut env.instance.policy.soft_weights;
+        weights.unpreferred_time = 3;
+        weights.course_room_consistency = 2;
+        weights.load_imbalance = 1;
+        let res = DispatchSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+
+        let components = res.stats["objective_components"].as_object().unwrap();
+        assert!(components.contains_key("unpreferred_time"));
+        let total: f64 = components
+            .values()
+            .map(|c| c["contribution"].as_f64().unwrap())
+            .sum();
+        assert!(
+            (total - res.objective).abs() < 1e-9,
+            "{total} vs {}",
+            res.objective
+        );
+    }
+
+    #[tokio::test]
+    async fn second_identical_solve_is_a_cache_hit() {
+        let mut env = small();
+        env.params.solver = SolverKind::Heuristic;
+        let solver = DispatchSolver::with_cache(4);
+        let first = solver.solve(env.clone()).await.unwrap();
+        assert_eq!(first.stats["cache_hit"], false);
+
+        let second = solver.solve(env.clone()).await.unwrap();
+        assert_eq!(second.stats["cache_hit"], true);
+        assert_eq!(
+            serde_json::to_value(&second.assignments).unwrap(),
+            serde_json::to_value(&first.assignments).unwrap()
+        );
+
+        env.params.seed += 1;
+        let reseeded = solver.solve(env).await.unwrap();
+        assert_eq!(reseeded.stats["cache_hit"], false);
+    }
+
+    #[tokio::test]
+    async fn scoped_solve_respects_another_group_base_rooms() {
+        let mut env = small();
+        env.params.solver = SolverKind::Heuristic;
+        env.instance.rooms.truncate(1);
+        env.instance.teachers[0]
+            .available
+            .push(TimeslotId("wed.2".into()));
+        let prog = |ts: &str| Assignment {
+            courseId: env.instance.courses[1].id.clone(),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r101".into()),
+            teacherId: env.instance.courses[1].teacherId.clone(),
+            meta: None,
+        };
+        // g2's base holds the only room on Monday, Tuesday and Thursday.
+        env.base = vec![prog("mon.1"), prog("tue.1"), prog("thu.1")];
+        env.params.onlyGroups = vec![env.instance.groups[0].id.clone()];
+
+        let res = DispatchSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        let at = |cid: &str| -> Vec<&str> {
+            let mut ts: Vec<&str> = res
+                .assignments
+                .iter()
+                .filter(|a| a.courseId.0 == cid)
+                .map(|a| a.timeslot.0.as_str())
+                .collect();
+            ts.sort();
+            ts
+        };
+        assert_eq!(at("c_prog"), ["mon.1", "thu.1", "tue.1"]);
+        assert_eq!(at("c_alg"), ["wed.1", "wed.2"]);
+    }
+
+    #[tokio::test]
+    async fn window_days_only_move_in_window_meetings() {
+        let mut env = small();
+        env.params.solver = SolverKind::Heuristic;
+        env.instance.policy.soft_weights.unpreferred_time = 10;
+        env.instance.teachers[0].prefs.avoid_slots = vec![TimeslotId("tue.1".into())];
+        let prog = |ts: &str| Assignment {
+            courseId: env.instance.courses[1].id.clone(),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r202".into()),
+            teacherId: env.instance.courses[1].teacherId.clone(),
+            meta: None,
+        };
+        env.base = vec![
+            alg(&env, "mon.1"),
+            alg(&env, "tue.1"),
+            prog("mon.1"),
+            prog("tue.2"),
+            prog("thu.1"),
+        ];
+        env.window_days = vec![DayOfWeek::Tue];
+
+        let res = DispatchSolver::new().solve(env.clone()).await.unwrap();
+        assert_eq!(res.status, "solved");
+        let held = |a: &Assignment| {
+            res.assignments.iter().any(|b| {
+                b.courseId == a.courseId && b.timeslot == a.timeslot && b.roomId == a.roomId
+            })
+        };
+        for a in env
+            .base
+            .iter()
+            .filter(|a| !a.timeslot.0.starts_with("tue."))
+        {
+            assert!(held(a), "{} moved off {}", a.courseId.0, a.timeslot.0);
+        }
+        // The avoided in-window meeting is free to move and does.
+        assert!(!held(&alg(&env, "tue.1")));
+    }
+
+    #[test]
+    fn day_mask_matches_parsed_days_only() {
+        let env = small();
+        let mask = |days: Vec<DayOfWeek>| types::LockMask {
+            courses: vec![],
+            groups: vec![],
+            teachers: vec![],
+            rooms: vec![],
+            days,
+            times: vec![],
+            lock: types::LockMode::Full,
+            negate: false,
+        };
+        let sun = mask(vec![DayOfWeek::Sun]);
+        let tue = mask(vec![DayOfWeek::Tue]);
+        assert!(mask_matches(&tue, &alg(&env, "tue.2"), &env.instance));
+        assert!(mask_matches(&tue, &alg(&env, "tue.2a"), &env.instance));
+        assert!(!mask_matches(&tue, &alg(&env, "wed.1"), &env.instance));
+        // An unparseable slot used to fall through to Sunday.
+        assert!(!mask_matches(&sun, &alg(&env, "someday.1"), &env.instance));
+        assert!(!mask_matches(&sun, &alg(&env, "sun"), &env.instance));
+    }
+
+    #[tokio::test]
+    async fn spent_budget_skips_repair() {
+        let mut env = small();
+        env.params.repairSteps = Some(50);
+        let solver = DispatchSolver::new();
+        let index = InstanceIndex::new(&env.instance);
+        let solved = solver.milp.solve_indexed(&env, &index).await.unwrap();
+        assert_eq!(solved.status, "solved");
+
+        // Nothing left of the budget once the MILP stage returns.
+        let mut res = solved.clone();
+        solver.repair(
+            &env,
+            &index,
+            &mut res,
+            Some(Instant::now()),
+            &CancelToken::new(),
+        );
+        assert_eq!(res.stats["repair_skipped"], true);
+        assert!(res.stats.get("repair_steps").is_none());
+        assert_eq!(res.objective, solved.objective);
+
+        let mut res = solved.clone();
+        solver.repair(&env, &index, &mut res, None, &CancelToken::new());
+        assert!(res.stats.get("repair_skipped").is_none());
+        assert_eq!(res.stats["repair_steps"], 50);
+    }
+
+    #[test]
+    fn partial_pins_differing_in_teacher_or_meeting_are_kept_apart() {
+        let mut env = small();
+        let pin = |ts: &str, teacher: Option<&str>, meeting: Option<u32>| PartialPin {
+            courseId: env.instance.courses[0].id.clone(),
+            timeslot: Some(TimeslotId(ts.into())),
+            roomId: None,
+            teacherId: teacher.map(|t| TeacherId(t.into())),
+            meeting,
+        };
+        let sub = pin("mon.1", Some("t_sub"), None);
+        env.partial_pins = vec![
+            sub.clone(),
+            pin("mon.1", None, None),
+            sub,
+            pin("tue.1", None, Some(0)),
+            pin("tue.1", None, Some(1)),
+        ];
+        // Any mask makes the partial pins go through the merge.
+        env.frozen_days = vec![DayOfWeek::Fri];
+        let resolved = apply_masks(env);
+        let keys: Vec<_> = resolved.partial_pins.iter().map(partial_pin_key).collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("c_alg", Some("mon.1"), None, None, None),
+                ("c_alg", Some("mon.1"), None, Some("t_sub"), None),
+                ("c_alg", Some("tue.1"), None, None, Some(0)),
+                ("c_alg", Some("tue.1"), None, None, Some(1)),
+            ]
+        );
+    }
 }
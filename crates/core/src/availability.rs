@@ -0,0 +1,68 @@
+use types::{DayOfWeek, EmptyAvailability, Group, Policy, Teacher, TimeslotId};
+
+/// A teacher with neither hard nor soft availability listed can teach
+/// anywhere, unless the policy says such a teacher is never available.
+pub fn fully_available(teacher: &Teacher, policy: &Policy) -> bool {
+    teacher.available.is_empty()
+        && teacher.soft_available.is_empty()
+        && policy.empty_availability == EmptyAvailability::AllSlots
+}
+
+pub fn slot_allowed(teacher: &Teacher, ts: &str, policy: &Policy) -> bool {
+    fully_available(teacher, policy)
+        || teacher.available.iter().any(|x| x.0 == ts)
+        || teacher.soft_available.iter().any(|x| x.0 == ts)
+}
+
+/// The slot is usable only through `soft_available` and should be penalized.
+pub fn slot_is_soft(teacher: &Teacher, ts: &str) -> bool {
+    !teacher.available.iter().any(|x| x.0 == ts) && teacher.soft_available.iter().any(|x| x.0 == ts)
+}
+
+/// Day of a timeslot id; `None` when the id does not parse.
+pub fn day_of_week(ts: &str) -> Option<DayOfWeek> {
+    TimeslotId::parse_str(ts).map(|(day, _, _)| day)
+}
+
+/// The group would rather not meet in this slot: it is listed in
+/// `avoid_slots`, or falls outside a non-empty `preferred_days`.
+pub fn group_avoids(group: &Group, ts: &str) -> bool {
+    if group.prefs.avoid_slots.iter().any(|x| x.0 == ts) {
+        return true;
+    }
+    !group.prefs.preferred_days.is_empty()
+        && !day_of_week(ts).is_some_and(|d| group.prefs.preferred_days.contains(&d))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feasibility::unplaceable_courses;
+    use types::{Instance, SolveEnvelope};
+
+    fn small() -> Instance {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+        env.instance
+    }
+
+    #[test]
+    fn empty_availability_follows_the_policy() {
+        let mut inst = small();
+        inst.teachers[0].available.clear();
+
+        assert_eq!(inst.policy.empty_availability, EmptyAvailability::AllSlots);
+        assert!(slot_allowed(&inst.teachers[0], "fri.4", &inst.policy));
+        assert!(unplaceable_courses(&inst).is_empty());
+
+        inst.policy.empty_availability = EmptyAvailability::NoSlots;
+        assert!(!slot_allowed(&inst.teachers[0], "mon.1", &inst.policy));
+        let stuck = unplaceable_courses(&inst);
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].details["courseId"], "c_alg");
+        assert_eq!(stuck[0].details["reason"], "availability");
+        assert!(crate::capacity_warnings(&inst)
+            .iter()
+            .any(|w| w.contains("teacher t_kovalenko lists no availability")));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::occupancy::meeting_slots;
+use types::{Course, DayOfWeek, Instance};
+
+/// Minutes since midnight for an "HH:MM" string.
+pub fn parse_hm(s: &str) -> Option<u32> {
+    let (h, m) = s.split_once(':')?;
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+/// (start, end) in minutes for grid slot `t`, if `slot_times` covers it. A
+/// sub-slot gets its equal share of the period's time.
+pub fn slot_span(inst: &Instance, t: usize) -> Option<(u32, u32)> {
+    let (day, idx, sub) = inst.timeslots.get(t)?.parse()?;
+    let st = inst.slot_times.get(&idx)?;
+    let span = (parse_hm(&st.start)?, parse_hm(&st.end)?);
+    Some(sub_span(span, sub, sub_slots(inst, day, idx)))
+}
+
+/// How many sub-slots period `idx` of `day` is split into on the grid: the
+/// highest sub-slot listed, 0 for a whole period.
+pub fn sub_slots(inst: &Instance, day: DayOfWeek, idx: u32) -> u32 {
+    inst.timeslots
+        .iter()
+        .filter_map(|t| t.parse())
+        .filter(|&(d, i, _)| d == day && i == idx)
+        .map(|(_, _, sub)| sub)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Part `sub` (1-based) of `subs` equal parts of the `(start, end)` minutes;
+/// the whole span for `sub` 0.
+pub fn sub_span((start, end): (u32, u32), sub: u32, subs: u32) -> (u32, u32) {
+    if sub == 0 || subs == 0 {
+        return (start, end);
+    }
+    let len = end.saturating_sub(start);
+    (start + len * (sub - 1) / subs, start + len * sub / subs)
+}
+
+/// Whether slot `b` starts exactly when slot `a` ends. Slots without known
+/// times are taken to be back-to-back, so instances without `slot_times`
+/// behave as before.
+pub fn contiguous(inst: &Instance, a: usize, b: usize) -> bool {
+    match (slot_span(inst, a), slot_span(inst, b)) {
+        (Some((_, end)), Some((start, _))) => end == start,
+        _ => true,
+    }
+}
+
+/// Wall-clock length of a meeting of `duration` starting at `t0`.
+pub fn meeting_minutes(inst: &Instance, t0: usize, duration: u32) -> Option<u32> {
+    let slots = meeting_slots(inst, t0, duration)?;
+    let (start, _) = slot_span(inst, *slots.first()?)?;
+    let (_, end) = slot_span(inst, *slots.last()?)?;
+    end.checked_sub(start)
+}
+
+/// Meeting length in minutes for `c`, taken from its first valid start.
+/// `None` when `slot_times` does not cover the grid.
+pub fn course_minutes(inst: &Instance, c: &Course) -> Option<u32> {
+    (0..inst.timeslots.len()).find_map(|t| meeting_minutes(inst, t, c.duration))
+}
@@ -0,0 +1,40 @@
+use serde::Serialize;
+use types::Assignment;
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, Default, Serialize, ToSchema)]
+pub struct ScheduleDiff {
+    pub unchanged: usize,
+    pub only_a: Vec<Assignment>,
+    pub only_b: Vec<Assignment>,
+}
+
+/// Multiset difference between two assignment lists. Each assignment in `b`
+/// cancels at most one identical assignment in `a`.
+pub fn diff_schedules(a: &[Assignment], b: &[Assignment]) -> ScheduleDiff {
+    let key = |x: &Assignment| {
+        (
+            x.courseId.0.clone(),
+            x.timeslot.0.clone(),
+            x.roomId.0.clone(),
+            x.teacherId.0.clone(),
+        )
+    };
+    let mut remaining: Vec<Option<&Assignment>> = b.iter().map(Some).collect();
+    let mut diff = ScheduleDiff::default();
+    for x in a {
+        let k = key(x);
+        match remaining
+            .iter_mut()
+            .find(|y| y.is_some_and(|y| key(y) == k))
+        {
+            Some(slot) => {
+                *slot = None;
+                diff.unchanged += 1;
+            }
+            None => diff.only_a.push(x.clone()),
+        }
+    }
+    diff.only_b = remaining.into_iter().flatten().cloned().collect();
+    diff
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use types::{Equip, Policy, Room};
+
+/// Equipment a room effectively provides: its own list plus everything
+/// reachable through `Policy.equip_implies`.
+pub fn effective_equip(room: &Room, policy: &Policy) -> Vec<Equip> {
+    let mut out: Vec<Equip> = room.equip.clone();
+    let mut i = 0;
+    while i < out.len() {
+        if let Some(implied) = policy.equip_implies.get(&out[i]) {
+            for e in implied {
+                if !out.contains(e) {
+                    out.push(e.clone());
+                }
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+/// `Policy.equip_implies` closed under chaining: each key maps to everything
+/// it implies directly or through other implications.
+pub fn implied_closure(policy: &Policy) -> HashMap<Equip, Vec<Equip>> {
+    policy
+        .equip_implies
+        .keys()
+        .map(|e| {
+            let mut out = vec![e.clone()];
+            let mut i = 0;
+            while i < out.len() {
+                for x in policy.equip_implies.get(&out[i]).into_iter().flatten() {
+                    if !out.contains(x) {
+                        out.push(x.clone());
+                    }
+                }
+                i += 1;
+            }
+            out.retain(|x| x != e);
+            (e.clone(), out)
+        })
+        .collect()
+}
+
+pub fn room_has(room: &Room, need: &Equip, policy: &Policy) -> bool {
+    room.equip.contains(need) || effective_equip(room, policy).contains(need)
+}
+
+pub fn room_meets_needs(room: &Room, needs: &[Equip], policy: &Policy) -> bool {
+    if needs.iter().all(|n| room.equip.contains(n)) {
+        return true;
+    }
+    let have = effective_equip(room, policy);
+    needs.iter().all(|n| have.contains(n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn implications_chain_but_do_not_reverse() {
+        let policy: Policy = serde_json::from_value(serde_json::json!({
+            "equip_implies": {
+                "computer_lab": ["projector"],
+                "projector": ["whiteboard"]
+            }
+        }))
+        .unwrap();
+        let room = |equip: Vec<Equip>| Room {
+            equip,
+            ..serde_json::from_value(serde_json::json!({"id": "r", "capacity": 30})).unwrap()
+        };
+        let lab = room(vec![Equip::ComputerLab]);
+        let projector = room(vec![Equip::Projector]);
+
+        assert!(room_meets_needs(
+            &lab,
+            &[Equip::Projector, Equip::Whiteboard],
+            &policy
+        ));
+        assert!(!room_meets_needs(
+            &projector,
+            &[Equip::ComputerLab],
+            &policy
+        ));
+        assert_eq!(
+            implied_closure(&policy)[&Equip::ComputerLab],
+            vec![Equip::Projector, Equip::Whiteboard]
+        );
+    }
+}
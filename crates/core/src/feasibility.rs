@@ -0,0 +1,861 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use types::{
+    Assignment, Course, CourseId, Equip, Instance, Parity, PartialPin, Room, RoomId, Violation,
+};
+
+use utoipa::ToSchema;
+
+use crate::index::InstanceIndex;
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct CourseCoverage {
+    pub courseId: CourseId,
+    pub placed: u32,
+    pub required: u32,
+}
+
+pub fn coverage(inst: &Instance, assignments: &[Assignment]) -> Vec<CourseCoverage> {
+    inst.courses
+        .iter()
+        .map(|c| CourseCoverage {
+            courseId: c.id.clone(),
+            placed: assignments.iter().filter(|a| a.courseId == c.id).count() as u32,
+            required: c.required_meetings(),
+        })
+        .collect()
+}
+
+/// Placed vs required meeting counts per course, keyed by course id.
+pub fn placement_report(inst: &Instance, assignments: &[Assignment]) -> serde_json::Value {
+    let mut report = serde_json::Map::new();
+    for c in coverage(inst, assignments) {
+        report.insert(
+            c.courseId.0,
+            serde_json::json!({"placed": c.placed, "required": c.required}),
+        );
+    }
+    serde_json::Value::Object(report)
+}
+
+/// Ids referenced by `assignments` that do not exist in `inst`, formatted as
+/// `"<kind>:<id>"`.
+pub fn dangling_references(inst: &Instance, assignments: &[Assignment]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    let mut push = |s: String| {
+        if !out.contains(&s) {
+            out.push(s);
+        }
+    };
+    for a in assignments {
+        if !inst.courses.iter().any(|c| c.id == a.courseId) {
+            push(format!("course:{}", a.courseId));
+        }
+        if !inst.timeslots.contains(&a.timeslot) {
+            push(format!("timeslot:{}", a.timeslot.0));
+        }
+        if !inst.rooms.iter().any(|r| r.id == a.roomId) {
+            push(format!("room:{}", a.roomId));
+        }
+        if !inst.teachers.iter().any(|t| t.id == a.teacherId) {
+            push(format!("teacher:{}", a.teacherId));
+        }
+    }
+    out
+}
+
+/// Teachers whose weekly `windows` count exceeds `prefs.max_windows`.
+pub fn window_cap_violations(inst: &Instance, assignments: &[Assignment]) -> Vec<Violation> {
+    let capped: Vec<_> = inst
+        .teachers
+        .iter()
+        .filter_map(|t| Some((t.id.0.as_str(), t.prefs.max_windows?)))
+        .collect();
+    if capped.is_empty() {
+        return Vec::new();
+    }
+    let scores = crate::scoring::compute_soft_scores(inst, assignments);
+    capped
+        .into_iter()
+        .filter_map(|(tid, cap)| {
+            let got = scores.windows_teachers.get(tid).copied().unwrap_or(0);
+            (got > cap as i64).then(|| {
+                violation(
+                    "teacher_windows_cap",
+                    serde_json::json!({"teacher": tid, "windows": got, "max_windows": cap}),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Groups with more meetings than `max_weekly`.
+pub fn weekly_cap_violations(inst: &Instance, assignments: &[Assignment]) -> Vec<Violation> {
+    inst.groups
+        .iter()
+        .filter_map(|g| {
+            let cap = g.max_weekly?;
+            let got = assignments
+                .iter()
+                .filter(|a| {
+                    inst.courses
+                        .iter()
+                        .any(|c| c.id == a.courseId && c.groupId == g.id)
+                })
+                .count() as u32;
+            (got > cap).then(|| {
+                violation(
+                    "group_weekly_cap",
+                    serde_json::json!({"group": g.id.0, "meetings": got, "max_weekly": cap}),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Course indices of each `policy.same_room_groups` set; unknown ids are
+/// dropped.
+pub fn same_room_sets(inst: &Instance) -> Vec<Vec<usize>> {
+    inst.policy
+        .same_room_groups
+        .iter()
+        .map(|set| {
+            set.iter()
+                .filter_map(|id| inst.courses.iter().position(|c| c.id == *id))
+                .collect()
+        })
+        .collect()
+}
+
+/// Room of the first `placed` meeting of a course sharing a
+/// `policy.same_room_groups` set with `course`, which must then meet there
+/// too.
+pub fn linked_room<'a>(
+    inst: &Instance,
+    course: &CourseId,
+    placed: impl IntoIterator<Item = &'a Assignment>,
+) -> Option<&'a RoomId> {
+    let linked: Vec<&CourseId> = inst
+        .policy
+        .same_room_groups
+        .iter()
+        .filter(|set| set.contains(course))
+        .flatten()
+        .collect();
+    if linked.is_empty() {
+        return None;
+    }
+    placed
+        .into_iter()
+        .find(|a| linked.contains(&&a.courseId))
+        .map(|a| &a.roomId)
+}
+
+/// `policy.same_room_groups` sets whose meetings use more than one room.
+pub fn same_room_violations(inst: &Instance, assignments: &[Assignment]) -> Vec<Violation> {
+    inst.policy
+        .same_room_groups
+        .iter()
+        .filter_map(|set| {
+            let rooms: BTreeSet<&str> = assignments
+                .iter()
+                .filter(|a| set.contains(&a.courseId))
+                .map(|a| a.roomId.0.as_str())
+                .collect();
+            (rooms.len() > 1).then(|| {
+                violation(
+                    "same_room",
+                    serde_json::json!({"courses": set, "rooms": rooms}),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Partial pins with a `meeting` index whose course does not have exactly
+/// that many meetings starting before the pinned slot, or none at it.
+pub fn meeting_order_violations(
+    inst: &Instance,
+    assignments: &[Assignment],
+    partial_pins: &[PartialPin],
+) -> Vec<Violation> {
+    let pos = |ts: &str| inst.timeslots.iter().position(|t| t.0 == ts);
+    partial_pins
+        .iter()
+        .filter_map(|p| {
+            let (k, ts) = (p.meeting?, p.timeslot.as_ref()?);
+            let at = pos(&ts.0)?;
+            let starts: Vec<usize> = assignments
+                .iter()
+                .filter(|a| a.courseId == p.courseId)
+                .filter_map(|a| pos(&a.timeslot.0))
+                .collect();
+            let before = starts.iter().filter(|&&t| t < at).count() as u32;
+            (before != k || !starts.contains(&at)).then(|| {
+                violation(
+                    "meeting_order",
+                    serde_json::json!({
+                        "course": p.courseId.0,
+                        "meeting": k,
+                        "timeslot": ts.0,
+                        "meetings_before": before,
+                    }),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Days on which a `contiguous_days` group has a free slot between two of
+/// its meetings.
+pub fn contiguous_day_violations(inst: &Instance, assignments: &[Assignment]) -> Vec<Violation> {
+    let mut groups: Vec<&str> = inst
+        .groups
+        .iter()
+        .filter(|g| g.contiguous_days)
+        .map(|g| g.id.0.as_str())
+        .collect();
+    if groups.is_empty() {
+        return Vec::new();
+    }
+    groups.sort_unstable();
+    let times: Vec<&str> = inst.timeslots.iter().map(|t| t.0.as_str()).collect();
+    let days = crate::scoring::day_slots(&times);
+    let occ = crate::occupancy::Occupancy::from_assignments(inst, assignments);
+    let mut out = Vec::new();
+    for gid in groups {
+        for (day, slots) in &days {
+            let busy: Vec<usize> = slots
+                .iter()
+                .enumerate()
+                .filter(|&(_, &k)| occ.group.contains(&(gid, k)))
+                .map(|(p, _)| p)
+                .collect();
+            if let (Some(&first), Some(&last)) = (busy.first(), busy.last()) {
+                if last - first + 1 > busy.len() {
+                    out.push(violation(
+                        "group_day_gap",
+                        serde_json::json!({"group": gid, "day": day}),
+                    ));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Courses with no (slot, room) start at all, before any other meeting is
+/// placed, as `no_feasible_placement` violations. `reason` is `capacity`
+/// (no allowed room seats the group), `equipment` (none of those has the
+/// needed equipment) or `availability` (a fitting room exists but never
+/// while the teacher is available and the room open).
+pub fn unplaceable_courses(inst: &Instance) -> Vec<Violation> {
+    let times: Vec<&str> = inst.timeslots.iter().map(|t| t.0.as_str()).collect();
+    inst.courses
+        .iter()
+        .filter_map(|c| {
+            let size = inst
+                .groups
+                .iter()
+                .find(|g| g.id == c.groupId)
+                .map_or(0, |g| g.size);
+            let seated: Vec<_> = inst
+                .rooms
+                .iter()
+                .filter(|r| c.allows_room(&r.id) && r.capacity >= size)
+                .collect();
+            let fitting: Vec<_> = seated
+                .iter()
+                .filter(|r| crate::equip::room_meets_needs(r, &c.needs, &inst.policy))
+                .collect();
+            let teacher = inst.teachers.iter().find(|t| t.id == c.teacherId);
+            let startable = || {
+                (0..times.len()).any(|t| {
+                    let Some(slots) = crate::occupancy::meeting_slots(inst, t, c.duration) else {
+                        return false;
+                    };
+                    crate::occupancy::slots_allowed(inst, &slots)
+                        && teacher.is_some_and(|tch| {
+                            slots.iter().all(|&k| {
+                                crate::availability::slot_allowed(tch, times[k], &inst.policy)
+                            })
+                        })
+                        && fitting
+                            .iter()
+                            .any(|r| crate::occupancy::room_open(inst, r, &slots))
+                })
+            };
+            let reason = if seated.is_empty() {
+                "capacity"
+            } else if fitting.is_empty() {
+                "equipment"
+            } else if !startable() {
+                "availability"
+            } else {
+                return None;
+            };
+            Some(violation(
+                "no_feasible_placement",
+                serde_json::json!({"courseId": c.id.0, "reason": reason}),
+            ))
+        })
+        .collect()
+}
+
+/// Per course, every `(start slot, room)` pair it could take in an empty
+/// grid: the meeting fits the day and the allowed slots, the teacher is
+/// available throughout, and the room seats the group, has the equipment
+/// and is open. Other courses are ignored.
+pub fn feasible_starts(index: &InstanceIndex) -> Vec<Vec<(usize, usize)>> {
+    let InstanceIndex {
+        inst,
+        times,
+        group_size,
+        teacher_by_id,
+        ..
+    } = index;
+
+    let buckets = RoomBuckets::new(&inst.rooms);
+    let mut feas: Vec<Vec<(usize, usize)>> = vec![Vec::new(); inst.courses.len()];
+    for (ci, c) in inst.courses.iter().enumerate() {
+        let teacher = match teacher_by_id.get(c.teacherId.0.as_str()) {
+            Some(t) => *t,
+            None => continue,
+        };
+        let gsz = *group_size.get(c.groupId.0.as_str()).unwrap_or(&0);
+        let rooms = buckets.suitable(inst, c, gsz);
+        for t in 0..times.len() {
+            let Some(slots) = crate::occupancy::meeting_slots(inst, t, c.duration) else {
+                continue;
+            };
+            if !crate::occupancy::slots_allowed(inst, &slots) {
+                continue;
+            }
+            if !crate::occupancy::teacher_free(inst, teacher, &slots) {
+                continue;
+            }
+            for &ri in &rooms {
+                if crate::occupancy::room_open(inst, &inst.rooms[ri], &slots) {
+                    feas[ci].push((t, ri));
+                }
+            }
+        }
+    }
+    feas
+}
+
+/// Room indices grouped by equipment set, each group in ascending capacity.
+/// Whether a room meets a course's needs depends on its equipment alone, so
+/// one check per group replaces one per room, and the rooms seating the
+/// group are the tail past a binary search.
+struct RoomBuckets<'a> {
+    buckets: Vec<(&'a Room, Vec<usize>)>,
+}
+
+impl<'a> RoomBuckets<'a> {
+    fn new(rooms: &'a [Room]) -> Self {
+        let mut by_equip: BTreeMap<BTreeSet<&Equip>, Vec<usize>> = BTreeMap::new();
+        for (ri, r) in rooms.iter().enumerate() {
+            by_equip
+                .entry(r.equip.iter().collect())
+                .or_default()
+                .push(ri);
+        }
+        let buckets = by_equip
+            .into_values()
+            .map(|mut members| {
+                members.sort_by_key(|&ri| rooms[ri].capacity);
+                (&rooms[members[0]], members)
+            })
+            .collect();
+        RoomBuckets { buckets }
+    }
+
+    /// Rooms that seat `size`, are allowed for `course` and meet its needs,
+    /// in instance order.
+    fn suitable(&self, inst: &Instance, course: &Course, size: u32) -> Vec<usize> {
+        let mut out: Vec<usize> = self
+            .buckets
+            .iter()
+            .filter(|(rep, _)| crate::equip::room_meets_needs(rep, &course.needs, &inst.policy))
+            .flat_map(|(_, members)| {
+                let from = members.partition_point(|&ri| inst.rooms[ri].capacity < size);
+                &members[from..]
+            })
+            .copied()
+            .filter(|&ri| course.allows_room(&inst.rooms[ri].id))
+            .collect();
+        out.sort_unstable();
+        out
+    }
+}
+
+fn violation(kind: &str, details: serde_json::Value) -> Violation {
+    Violation {
+        r#type: kind.into(),
+        weight: 1,
+        details,
+    }
+}
+
+/// Courses holding a (room, teacher or group, slot) key, with their parity.
+type Holders<'a> = HashMap<(&'a str, usize), Vec<(&'a str, Parity)>>;
+
+/// Records `cid` at `key` and returns a course already there that meets in
+/// one of the same weeks.
+fn claim<'a>(
+    holders: &mut Holders<'a>,
+    key: (&'a str, usize),
+    cid: &'a str,
+    course: &Course,
+) -> Option<&'a str> {
+    let held = holders.entry(key).or_default();
+    let other = held
+        .iter()
+        .find(|(_, p)| p.overlaps(course.parity()))
+        .map(|&(other, _)| other);
+    held.push((cid, course.parity()));
+    other
+}
+
+/// Checks an assignment list against the hard constraints of `inst` and
+/// reports every breach found. An empty result means the schedule is feasible.
+pub fn hard_violations(inst: &Instance, assignments: &[Assignment]) -> Vec<Violation> {
+    let mut out = Vec::new();
+
+    let course_by_id: HashMap<&str, &Course> =
+        inst.courses.iter().map(|c| (c.id.0.as_str(), c)).collect();
+    let ts_index: HashMap<&str, usize> = inst
+        .timeslots
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.0.as_str(), i))
+        .collect();
+    let group_size: HashMap<&str, u32> = inst
+        .groups
+        .iter()
+        .map(|g| (g.id.0.as_str(), g.size))
+        .collect();
+
+    let mut occ_room: Holders = HashMap::new();
+    let mut seats: HashMap<(&str, usize), u32> = HashMap::new();
+    let mut occ_teacher: Holders = HashMap::new();
+    let mut occ_group: Holders = HashMap::new();
+    let mut count_by_course: HashMap<&str, u32> = HashMap::new();
+
+    for a in assignments {
+        let cid = a.courseId.0.as_str();
+        let Some(c) = course_by_id.get(cid) else {
+            out.push(violation(
+                "unknown_course",
+                serde_json::json!({"course": cid}),
+            ));
+            continue;
+        };
+        let Some(&t0) = ts_index.get(a.timeslot.0.as_str()) else {
+            out.push(violation(
+                "unknown_timeslot",
+                serde_json::json!({"course": cid, "timeslot": a.timeslot.0}),
+            ));
+            continue;
+        };
+        let Some(room) = inst.rooms.iter().find(|r| r.id == a.roomId) else {
+            out.push(violation(
+                "unknown_room",
+                serde_json::json!({"course": cid, "room": a.roomId.0}),
+            ));
+            continue;
+        };
+        *count_by_course.entry(cid).or_default() += 1;
+
+        if a.teacherId != c.teacherId {
+            out.push(violation(
+                "teacher_mismatch",
+                serde_json::json!({"course": cid, "expected": c.teacherId.0, "got": a.teacherId.0}),
+            ));
+        }
+
+        let gsz = group_size.get(c.groupId.0.as_str()).copied().unwrap_or(0);
+        if room.capacity < gsz {
+            out.push(violation(
+                "room_capacity",
+                serde_json::json!({"course": cid, "room": room.id.0, "capacity": room.capacity, "group_size": gsz}),
+            ));
+        }
+        if !c.allows_room(&room.id) {
+            out.push(violation(
+                "room_not_allowed",
+                serde_json::json!({"course": cid, "room": room.id.0}),
+            ));
+        }
+        for need in &c.needs {
+            if !crate::equip::room_has(room, need, &inst.policy) {
+                out.push(violation(
+                    "missing_equipment",
+                    serde_json::json!({"course": cid, "room": room.id.0, "equip": need}),
+                ));
+            }
+        }
+
+        let slots = match crate::occupancy::meeting_slots(inst, t0, c.duration) {
+            Some(slots) => slots,
+            None => {
+                match inst.timeslots.get(t0 + 1) {
+                    None => out.push(violation(
+                        "duration_overflow",
+                        serde_json::json!({"course": cid, "timeslot": a.timeslot.0}),
+                    )),
+                    Some(next) => out.push(violation(
+                        "non_adjacent_duration",
+                        serde_json::json!({"course": cid, "timeslot": a.timeslot.0, "next": next.0}),
+                    )),
+                }
+                vec![t0]
+            }
+        };
+
+        let teacher = inst.teachers.iter().find(|t| t.id == a.teacherId);
+        if !crate::occupancy::room_open(inst, room, &slots) {
+            out.push(violation(
+                "room_closed",
+                serde_json::json!({"course": cid, "room": room.id.0, "timeslot": a.timeslot.0}),
+            ));
+        }
+
+        for &k in &slots {
+            let ts = inst.timeslots[k].0.as_str();
+            if !inst.policy.allows_slot(ts) {
+                out.push(violation(
+                    "slot_not_allowed",
+                    serde_json::json!({"course": cid, "timeslot": ts}),
+                ));
+            }
+            if let Some(t) = teacher {
+                if !crate::availability::slot_allowed(t, ts, &inst.policy) {
+                    out.push(violation(
+                        "teacher_unavailable",
+                        serde_json::json!({"course": cid, "teacher": t.id.0, "timeslot": ts}),
+                    ));
+                }
+            }
+            if let Some(cap) = room.student_capacity {
+                let seated = seats.entry((room.id.0.as_str(), k)).or_default();
+                *seated += gsz;
+                occ_room
+                    .entry((room.id.0.as_str(), k))
+                    .or_default()
+                    .push((cid, c.parity()));
+                if *seated > cap {
+                    out.push(violation(
+                        "room_seats_exceeded",
+                        serde_json::json!({"room": room.id.0, "timeslot": ts, "seats": *seated, "student_capacity": cap}),
+                    ));
+                }
+            } else if let Some(other) = claim(&mut occ_room, (room.id.0.as_str(), k), cid, c) {
+                out.push(violation(
+                    "room_clash",
+                    serde_json::json!({"room": room.id.0, "timeslot": ts, "courses": [other, cid]}),
+                ));
+            }
+            if let Some(other) = claim(&mut occ_teacher, (a.teacherId.0.as_str(), k), cid, c) {
+                out.push(violation(
+                    "teacher_clash",
+                    serde_json::json!({"teacher": a.teacherId.0, "timeslot": ts, "courses": [other, cid]}),
+                ));
+            }
+            if let Some(other) = claim(&mut occ_group, (c.groupId.0.as_str(), k), cid, c) {
+                out.push(violation(
+                    "group_clash",
+                    serde_json::json!({"group": c.groupId.0, "timeslot": ts, "courses": [other, cid]}),
+                ));
+            }
+        }
+    }
+
+    for (&(rid, k), holders) in &occ_room {
+        let open_lab = inst
+            .rooms
+            .iter()
+            .any(|r| r.id.0 == rid && r.student_capacity.is_some());
+        if !open_lab {
+            continue;
+        }
+        let exclusive = holders
+            .iter()
+            .find(|(cid, _)| course_by_id.get(cid).is_some_and(|c| c.exclusive_room));
+        if let (Some((cid, _)), true) = (exclusive, holders.len() > 1) {
+            let others: Vec<&str> = holders
+                .iter()
+                .map(|&(c, _)| c)
+                .filter(|c| c != cid)
+                .collect();
+            out.push(violation(
+                "exclusive_room_shared",
+                serde_json::json!({"room": rid, "timeslot": inst.timeslots[k].0, "course": cid, "with": others}),
+            ));
+        }
+    }
+
+    let occ = crate::occupancy::Occupancy::from_assignments(inst, assignments);
+    for (x, y) in &inst.policy.no_overlap {
+        for (k, ts) in inst.timeslots.iter().enumerate() {
+            if occ.course.contains(&(x.0.as_str(), k)) && occ.course.contains(&(y.0.as_str(), k)) {
+                out.push(violation(
+                    "no_overlap",
+                    serde_json::json!({"courses": [x.0, y.0], "timeslot": ts.0}),
+                ));
+            }
+        }
+    }
+
+    let caps = &inst.policy.building_concurrency;
+    let mut per_building: HashMap<(&str, usize), u32> = HashMap::new();
+    for &(rid, k) in occ_room.keys() {
+        let building = inst
+            .rooms
+            .iter()
+            .find(|r| r.id.0 == rid)
+            .and_then(|r| r.building.as_deref());
+        if let Some(b) = building.filter(|b| caps.contains_key(*b)) {
+            *per_building.entry((b, k)).or_default() += 1;
+        }
+    }
+    let mut over: Vec<_> = per_building
+        .into_iter()
+        .filter(|&((b, _), n)| n > caps[b])
+        .collect();
+    over.sort_unstable();
+    for ((b, k), n) in over {
+        out.push(violation(
+            "building_concurrency",
+            serde_json::json!({"building": b, "timeslot": inst.timeslots[k].0, "rooms": n, "cap": caps[b]}),
+        ));
+    }
+
+    for c in &inst.courses {
+        let have = count_by_course.get(c.id.0.as_str()).copied().unwrap_or(0);
+        if have != c.required_meetings() {
+            out.push(violation(
+                "meeting_count",
+                serde_json::json!({"course": c.id.0, "expected": c.required_meetings(), "got": have}),
+            ));
+        }
+    }
+
+    out
+}
+
+/// `hard_violations` plus the rules that span a whole schedule: teacher
+/// window caps, contiguous group days, weekly group caps, same-room sets and
+/// the meeting order of `partial_pins`. Every report of a schedule's
+/// feasibility goes through this.
+pub fn all_hard_violations(
+    inst: &Instance,
+    assignments: &[Assignment],
+    partial_pins: &[PartialPin],
+) -> Vec<Violation> {
+    let mut out = hard_violations(inst, assignments);
+    out.extend(window_cap_violations(inst, assignments));
+    out.extend(contiguous_day_violations(inst, assignments));
+    out.extend(weekly_cap_violations(inst, assignments));
+    out.extend(same_room_violations(inst, assignments));
+    out.extend(meeting_order_violations(inst, assignments, partial_pins));
+    out
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+pub struct Conflict {
+    pub course: CourseId,
+    /// Why the two courses cannot meet in the same slot: `"teacher"`,
+    /// `"group"`, `"no_overlap"` or `"room"` (both fit in one room only,
+    /// the same one).
+    pub reasons: Vec<&'static str>,
+}
+
+/// Per course, the courses it can never share a slot with. Courses of
+/// opposite week parity never clash on teacher, group or room.
+pub fn conflict_graph(inst: &Instance) -> BTreeMap<String, Vec<Conflict>> {
+    let only_room: Vec<Option<&str>> = inst
+        .courses
+        .iter()
+        .map(|c| {
+            let size = inst
+                .groups
+                .iter()
+                .find(|g| g.id == c.groupId)
+                .map_or(0, |g| g.size);
+            let mut fits = inst.rooms.iter().filter(|r| {
+                r.capacity >= size
+                    && c.allows_room(&r.id)
+                    && crate::equip::room_meets_needs(r, &c.needs, &inst.policy)
+            });
+            match (fits.next(), fits.next()) {
+                (Some(r), None) if r.student_capacity.is_none() => Some(r.id.0.as_str()),
+                _ => None,
+            }
+        })
+        .collect();
+    let mut graph: BTreeMap<String, Vec<Conflict>> = inst
+        .courses
+        .iter()
+        .map(|c| (c.id.0.clone(), Vec::new()))
+        .collect();
+    for (i, a) in inst.courses.iter().enumerate() {
+        for (j, b) in inst.courses.iter().enumerate().skip(i + 1) {
+            let mut reasons = Vec::new();
+            let same_weeks = a.parity().overlaps(b.parity());
+            if same_weeks && a.teacherId == b.teacherId {
+                reasons.push("teacher");
+            }
+            if same_weeks && a.groupId == b.groupId {
+                reasons.push("group");
+            }
+            if inst
+                .policy
+                .no_overlap
+                .iter()
+                .any(|(x, y)| (*x == a.id && *y == b.id) || (*x == b.id && *y == a.id))
+            {
+                reasons.push("no_overlap");
+            }
+            if same_weeks && only_room[i].is_some() && only_room[i] == only_room[j] {
+                reasons.push("room");
+            }
+            if reasons.is_empty() {
+                continue;
+            }
+            for (from, to) in [(a, b), (b, a)] {
+                if let Some(list) = graph.get_mut(&from.id.0) {
+                    list.push(Conflict {
+                        course: to.id.clone(),
+                        reasons: reasons.clone(),
+                    });
+                }
+            }
+        }
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{SolveEnvelope, TeacherId, TimeslotId};
+
+    fn small() -> Instance {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+        env.instance
+    }
+
+    fn alg(ts: &str) -> Assignment {
+        Assignment {
+            courseId: CourseId("c_alg".into()),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r101".into()),
+            teacherId: TeacherId("t_kovalenko".into()),
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn contiguous_group_rejects_a_scattered_day() {
+        let mut inst = small();
+        let scattered = [alg("mon.1"), alg("mon.3")];
+        assert!(contiguous_day_violations(&inst, &scattered).is_empty());
+
+        inst.groups[0].contiguous_days = true;
+        let v = contiguous_day_violations(&inst, &scattered);
+        assert_eq!(v.len(), 1);
+        assert_eq!(v[0].r#type, "group_day_gap");
+        assert_eq!(v[0].details["day"], "mon");
+        assert!(contiguous_day_violations(&inst, &[alg("mon.1"), alg("mon.2")]).is_empty());
+        assert!(contiguous_day_violations(&inst, &[alg("mon.1"), alg("tue.3")]).is_empty());
+    }
+
+    #[test]
+    fn courses_sharing_a_teacher_are_adjacent() {
+        let mut inst = small();
+        let shares_teacher = |g: &BTreeMap<String, Vec<Conflict>>, from: &str, to: &str| {
+            g[from]
+                .iter()
+                .any(|c| c.course.0 == to && c.reasons.contains(&"teacher"))
+        };
+        let graph = conflict_graph(&inst);
+        assert!(!shares_teacher(&graph, "c_alg", "c_prog"));
+
+        inst.courses[1].teacherId = TeacherId("t_kovalenko".into());
+        let graph = conflict_graph(&inst);
+        assert!(shares_teacher(&graph, "c_alg", "c_prog"));
+        assert!(shares_teacher(&graph, "c_prog", "c_alg"));
+    }
+
+    /// Bucket lookup against the per-room scan it replaced, on a many-room
+    /// instance.
+    #[test]
+    fn room_buckets_match_a_full_scan() {
+        let mut inst = small();
+        let profiles = [
+            vec![],
+            vec![Equip::Projector],
+            vec![Equip::Whiteboard, Equip::Projector],
+            vec![Equip::ComputerLab],
+        ];
+        let template = inst.rooms[0].clone();
+        inst.rooms = (0..400)
+            .map(|i| Room {
+                id: RoomId(format!("r{i}")),
+                capacity: 20 + (i * 7 % 40),
+                equip: profiles[i as usize % profiles.len()].clone(),
+                ..template.clone()
+            })
+            .collect();
+        inst.courses[0].needs = vec![Equip::Projector];
+        inst.courses[1].needs = vec![Equip::ComputerLab];
+        let mut whitelisted = inst.courses[0].clone();
+        whitelisted.id = CourseId("c_alg_small_rooms".into());
+        whitelisted.allowed_rooms = (0..40).map(|i| RoomId(format!("r{i}"))).collect();
+        inst.courses.push(whitelisted);
+        let size = |c: &Course| inst.groups.iter().find(|g| g.id == c.groupId).unwrap().size;
+
+        let scan = |c: &Course| -> Vec<usize> {
+            (0..inst.rooms.len())
+                .filter(|&ri| {
+                    let r = &inst.rooms[ri];
+                    r.capacity >= size(c)
+                        && c.allows_room(&r.id)
+                        && crate::equip::room_meets_needs(r, &c.needs, &inst.policy)
+                })
+                .collect()
+        };
+        let buckets = RoomBuckets::new(&inst.rooms);
+        assert_eq!(buckets.buckets.len(), profiles.len());
+        for c in &inst.courses {
+            let found = buckets.suitable(&inst, c, size(c));
+            assert!(!found.is_empty(), "{}", c.id.0);
+            assert_eq!(found, scan(c), "{}", c.id.0);
+        }
+    }
+
+    #[test]
+    fn long_meetings_that_cannot_run_two_slots_are_reported() {
+        let inst = small();
+        let prog = |ts: &str| Assignment {
+            courseId: CourseId("c_prog".into()),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r202".into()),
+            teacherId: TeacherId("t_petrenko".into()),
+            meta: None,
+        };
+        let kinds = |a: Assignment| -> Vec<String> {
+            hard_violations(&inst, &[a])
+                .into_iter()
+                .map(|v| v.r#type)
+                .filter(|t| t.contains("duration"))
+                .collect()
+        };
+        assert!(kinds(prog("tue.1")).is_empty());
+        assert_eq!(kinds(prog("tue.3")), ["non_adjacent_duration"]);
+        assert_eq!(kinds(prog("fri.4")), ["duration_overflow"]);
+    }
+}
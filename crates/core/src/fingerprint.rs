@@ -0,0 +1,31 @@
+use types::SolveEnvelope;
+
+/// 64-bit FNV-1a. Stable across builds and platforms, unlike `DefaultHasher`.
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for &b in bytes {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    h
+}
+
+/// Hash of a value's canonical JSON form. Going through `serde_json::Value`
+/// sorts object keys, so `HashMap` iteration order does not leak in.
+pub fn canonical_hash<T: serde::Serialize>(value: &T) -> u64 {
+    let canonical = serde_json::to_value(value)
+        .map(|v| v.to_string())
+        .unwrap_or_default();
+    fnv1a64(canonical.as_bytes())
+}
+
+/// Token identifying the exact inputs of a solve: instance, params, pins,
+/// masks and base schedule. The queue `priority` only decides when a job
+/// runs, so it is left out.
+pub fn reproduce_token(env: &SolveEnvelope) -> String {
+    let mut value = serde_json::to_value(env).unwrap_or_default();
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("priority");
+    }
+    format!("{:016x}", canonical_hash(&value))
+}
@@ -0,0 +1,49 @@
+use std::collections::{BTreeMap, HashMap};
+use types::{Instance, Teacher};
+
+use crate::scoring::day_slots;
+
+/// Lookup tables over an instance that every solver stage needs: positions
+/// of slots, rooms and courses by id, the slots of each day in period order,
+/// group sizes and teachers by id. Built once per instance so the MILP model
+/// and the heuristic repair that follows it can share them.
+pub struct InstanceIndex<'a> {
+    pub inst: &'a Instance,
+    pub times: Vec<&'a str>,
+    pub day_slots: BTreeMap<&'a str, Vec<usize>>,
+    pub group_size: HashMap<&'a str, u32>,
+    pub teacher_by_id: HashMap<&'a str, &'a Teacher>,
+    pub idx_ts: HashMap<&'a str, usize>,
+    pub idx_room: HashMap<&'a str, usize>,
+    pub idx_course: HashMap<&'a str, usize>,
+}
+
+impl<'a> InstanceIndex<'a> {
+    pub fn new(inst: &'a Instance) -> Self {
+        let times: Vec<&str> = inst.timeslots.iter().map(|t| t.0.as_str()).collect();
+        InstanceIndex {
+            inst,
+            day_slots: day_slots(&times),
+            group_size: inst
+                .groups
+                .iter()
+                .map(|g| (g.id.0.as_str(), g.size))
+                .collect(),
+            teacher_by_id: inst.teachers.iter().map(|t| (t.id.0.as_str(), t)).collect(),
+            idx_ts: times.iter().enumerate().map(|(i, &t)| (t, i)).collect(),
+            idx_room: inst
+                .rooms
+                .iter()
+                .enumerate()
+                .map(|(i, r)| (r.id.0.as_str(), i))
+                .collect(),
+            idx_course: inst
+                .courses
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (c.id.0.as_str(), i))
+                .collect(),
+            times,
+        }
+    }
+}
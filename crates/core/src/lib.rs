@@ -1,3 +1,15 @@
+pub mod availability;
+pub mod clock;
+pub mod diff;
+pub mod equip;
+pub mod feasibility;
+pub mod fingerprint;
+pub mod index;
+pub mod locks;
+pub mod occupancy;
+pub mod overrides;
+pub mod presets;
+pub mod rng;
 pub mod scoring;
 
 use async_trait::async_trait;
@@ -20,12 +32,44 @@ pub fn validate(inst: &Instance) -> Result<(), ValidationError> {
     if inst.timeslots.is_empty() {
         errors.push("timeslots is empty".into());
     }
+    // One message per empty section instead of one per course referencing
+    // it; the per-course checks below skip sections reported here.
+    let has_courses = !inst.courses.is_empty();
+    let no_teachers = has_courses && inst.teachers.is_empty();
+    let no_groups = has_courses && inst.groups.is_empty();
+    let no_rooms = has_courses && inst.rooms.is_empty();
+    for (name, empty) in [
+        ("teachers", no_teachers),
+        ("groups", no_groups),
+        ("rooms", no_rooms),
+    ] {
+        if empty {
+            errors.push(format!(
+                "{name} is empty but there are {} courses to schedule",
+                inst.courses.len()
+            ));
+        }
+    }
     for t in &inst.timeslots {
         if !t.is_valid_format() {
             errors.push(format!("timeslot has invalid format: {}", t.0));
         }
     }
 
+    for (idx, st) in &inst.slot_times {
+        match (clock::parse_hm(&st.start), clock::parse_hm(&st.end)) {
+            (Some(s), Some(e)) if s < e => {}
+            (Some(_), Some(_)) => errors.push(format!(
+                "slot_times {idx} ends before it starts ({}-{})",
+                st.start, st.end
+            )),
+            _ => errors.push(format!(
+                "slot_times {idx} has invalid time ({}-{}); expected HH:MM",
+                st.start, st.end
+            )),
+        }
+    }
+
     fn chk_unique<I: ToString>(name: &str, ids: impl Iterator<Item = I>, errors: &mut Vec<String>) {
         use std::collections::HashSet;
         let mut seen = HashSet::new();
@@ -36,6 +80,51 @@ pub fn validate(inst: &Instance) -> Result<(), ValidationError> {
             }
         }
     }
+    if let Some(name) = &inst.policy.preset {
+        if presets::preset_weights(name).is_none() {
+            errors.push(format!(
+                "unknown policy preset {name} (expected one of: {})",
+                presets::PRESET_NAMES.join(", ")
+            ));
+        }
+    }
+    let w = &inst.policy.soft_weights;
+    for (name, value) in [
+        ("unpreferred_time", w.unpreferred_time),
+        ("windows", w.windows),
+        ("windows_teachers", w.windows_teachers.unwrap_or(0)),
+        ("windows_groups", w.windows_groups.unwrap_or(0)),
+        ("building_switch", w.building_switch),
+        ("load_imbalance", w.load_imbalance),
+        ("compactness", w.compactness),
+        ("soft_availability", w.soft_availability),
+        ("course_room_consistency", w.course_room_consistency),
+        ("teacher_room_spread", w.teacher_room_spread),
+        ("away_from_home", w.away_from_home),
+        ("course_adjacency", w.course_adjacency),
+        ("parity_slot_consistency", w.parity_slot_consistency),
+        ("active_days", w.active_days),
+        ("course_building_spread", w.course_building_spread),
+        ("rooms_used", w.rooms_used),
+        ("course_spacing", w.course_spacing),
+    ] {
+        if value < 0 {
+            errors.push(format!(
+                "soft weight {name} is negative ({value}); weights must be >= 0"
+            ));
+        }
+    }
+
+    for (a, to) in &inst.policy.building_distance {
+        for (b, d) in to {
+            if *d < 0 {
+                errors.push(format!(
+                    "building_distance {a} to {b} is negative ({d}); distances must be >= 0"
+                ));
+            }
+        }
+    }
+
     chk_unique(
         "teacher",
         inst.teachers.iter().map(|x| &x.id.0),
@@ -52,7 +141,7 @@ pub fn validate(inst: &Instance) -> Result<(), ValidationError> {
     let times: HashSet<_> = inst.timeslots.iter().map(|t| &t.0).collect();
 
     for t in &inst.teachers {
-        for slot in &t.available {
+        for slot in t.available.iter().chain(&t.soft_available) {
             if !times.contains(&slot.0) {
                 errors.push(format!(
                     "teacher {} has unavailable slot {}",
@@ -62,14 +151,77 @@ pub fn validate(inst: &Instance) -> Result<(), ValidationError> {
         }
     }
 
+    for set in &inst.policy.same_room_groups {
+        let courses: Vec<&Course> = set
+            .iter()
+            .filter_map(|id| {
+                let c = inst.courses.iter().find(|c| c.id == *id);
+                if c.is_none() {
+                    errors.push(format!(
+                        "same_room_groups references missing course {}",
+                        id.0
+                    ));
+                }
+                c
+            })
+            .collect();
+        let fits = |r: &Room, c: &Course| {
+            let size = inst
+                .groups
+                .iter()
+                .find(|g| g.id == c.groupId)
+                .map_or(0, |g| g.size);
+            c.allows_room(&r.id)
+                && r.capacity >= size
+                && equip::room_meets_needs(r, &c.needs, &inst.policy)
+        };
+        if !courses.is_empty() && !rooms.iter().any(|r| courses.iter().all(|c| fits(r, c))) {
+            let ids: Vec<&str> = courses.iter().map(|c| c.id.0.as_str()).collect();
+            errors.push(format!(
+                "same_room_groups [{}] has no room suitable for every course",
+                ids.join(", ")
+            ));
+        }
+    }
+
+    for slot in inst.policy.allowed_slots.iter().flatten() {
+        if !times.contains(&slot.0) {
+            errors.push(format!("allowed_slots has unknown slot {}", slot.0));
+        }
+    }
+
+    for (name, pairs) in [
+        ("no_overlap", &inst.policy.no_overlap),
+        ("prefer_adjacent", &inst.policy.prefer_adjacent),
+    ] {
+        for (a, b) in pairs {
+            for id in [a, b] {
+                if !inst.courses.iter().any(|c| c.id == *id) {
+                    errors.push(format!("{name} references missing course {}", id.0));
+                }
+            }
+        }
+    }
+
+    for r in rooms {
+        if r.student_capacity == Some(0) {
+            errors.push(format!("room {} has student_capacity=0", r.id.0));
+        }
+        if let (Some(e), Some(l)) = (r.earliest, r.latest) {
+            if e > l {
+                errors.push(format!("room {} has earliest {e} after latest {l}", r.id.0));
+            }
+        }
+    }
+
     for c in &inst.courses {
-        if !teachers.contains(&c.teacherId.0) {
+        if !no_teachers && !teachers.contains(&c.teacherId.0) {
             errors.push(format!(
                 "course {} references missing teacher {}",
                 c.id.0, c.teacherId.0
             ));
         }
-        if !groups.contains(&c.groupId.0) {
+        if !no_groups && !groups.contains(&c.groupId.0) {
             errors.push(format!(
                 "course {} references missing group {}",
                 c.id.0, c.groupId.0
@@ -78,14 +230,36 @@ pub fn validate(inst: &Instance) -> Result<(), ValidationError> {
         if c.countPerWeek == 0 {
             errors.push(format!("course {} has countPerWeek=0", c.id.0));
         }
+        if c.sections == Some(0) {
+            errors.push(format!("course {} has sections=0", c.id.0));
+        }
         if !(c.duration == 1 || c.duration == 2) {
             errors.push(format!(
                 "course {} has invalid duration {}",
                 c.id.0, c.duration
             ));
+        } else if c.duration == 2
+            && !inst.slot_times.is_empty()
+            && !(0..inst.timeslots.len()).any(|t| occupancy::meeting_slots(inst, t, 2).is_some())
+        {
+            errors.push(format!(
+                "course {} has duration 2 but no two grid slots are back-to-back in slot_times",
+                c.id.0
+            ));
+        }
+        if no_rooms {
+            continue;
+        }
+        for r in &c.allowed_rooms {
+            if !rooms.iter().any(|x| x.id == *r) {
+                errors.push(format!("course {} allows missing room {}", c.id.0, r.0));
+            }
         }
         let mut any_room_ok = false;
         'rooms: for r in rooms {
+            if !c.allows_room(&r.id) {
+                continue;
+            }
             if r.capacity
                 < inst
                     .groups
@@ -96,10 +270,8 @@ pub fn validate(inst: &Instance) -> Result<(), ValidationError> {
             {
                 continue;
             }
-            for need in &c.needs {
-                if !r.equip.contains(need) {
-                    continue 'rooms;
-                }
+            if !equip::room_meets_needs(r, &c.needs, &inst.policy) {
+                continue 'rooms;
             }
             any_room_ok = true;
             break;
@@ -112,6 +284,24 @@ pub fn validate(inst: &Instance) -> Result<(), ValidationError> {
         }
     }
 
+    for g in &inst.groups {
+        let Some(cap) = g.max_weekly else {
+            continue;
+        };
+        let demand: u32 = inst
+            .courses
+            .iter()
+            .filter(|c| c.groupId == g.id)
+            .map(Course::required_meetings)
+            .sum();
+        if demand > cap {
+            errors.push(format!(
+                "group {} needs {demand} meetings a week but max_weekly is {cap}",
+                g.id.0
+            ));
+        }
+    }
+
     if errors.is_empty() {
         Ok(())
     } else {
@@ -119,7 +309,336 @@ pub fn validate(inst: &Instance) -> Result<(), ValidationError> {
     }
 }
 
+pub fn capacity_warnings(inst: &Instance) -> Vec<String> {
+    let mut warnings: Vec<String> = Vec::new();
+    let n_times = inst.timeslots.len() as u64;
+
+    let demand: u64 = inst
+        .courses
+        .iter()
+        .map(|c| c.required_meetings() as u64 * c.duration as u64)
+        .sum();
+    let supply = inst.rooms.len() as u64 * n_times;
+    if demand > supply {
+        warnings.push(format!(
+            "instance is over-subscribed: {demand} meeting-slots demanded, {supply} room-slots available"
+        ));
+    }
+
+    if inst.policy.empty_availability == types::EmptyAvailability::NoSlots {
+        for t in &inst.teachers {
+            let teaches = inst.courses.iter().any(|c| c.teacherId == t.id);
+            if teaches && t.available.is_empty() && t.soft_available.is_empty() {
+                warnings.push(format!(
+                    "teacher {} lists no availability and policy empty_availability is no_slots",
+                    t.id.0
+                ));
+            }
+        }
+    }
+
+    let mut demand_by_equip: Vec<(&types::Equip, u64)> = Vec::new();
+    for c in &inst.courses {
+        for need in &c.needs {
+            let slots = c.required_meetings() as u64 * c.duration as u64;
+            match demand_by_equip.iter_mut().find(|(e, _)| *e == need) {
+                Some((_, d)) => *d += slots,
+                None => demand_by_equip.push((need, slots)),
+            }
+        }
+    }
+    for (equip, demand) in demand_by_equip {
+        let rooms = inst
+            .rooms
+            .iter()
+            .filter(|r| equip::room_has(r, equip, &inst.policy))
+            .count() as u64;
+        let supply = rooms * n_times;
+        if demand > supply {
+            warnings.push(format!(
+                "equipment {} is over-subscribed: {demand} meeting-slots demanded, {supply} room-slots available",
+                equip_name(equip)
+            ));
+        }
+    }
+
+    for c in inst.courses.iter().filter(|c| c.exclusive_room) {
+        let rooms: Vec<_> = inst
+            .rooms
+            .iter()
+            .filter(|r| c.allows_room(&r.id) && equip::room_meets_needs(r, &c.needs, &inst.policy))
+            .collect();
+        if !rooms.is_empty() && rooms.iter().all(|r| r.student_capacity.is_some()) {
+            warnings.push(format!(
+                "course {} is exclusive_room but can only use open-lab rooms; its meetings take them whole",
+                c.id.0
+            ));
+        }
+    }
+
+    warnings
+}
+
+fn equip_name(e: &types::Equip) -> String {
+    serde_json::to_value(e)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{e:?}"))
+}
+
+/// Puts `inst` in a canonical order so solves do not depend on how a client
+/// listed its entities: teachers, groups, rooms and courses by id, timeslots
+/// by weekday then period (unrecognized ids last, by name).
+pub fn normalize(inst: &mut Instance) {
+    inst.teachers.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+    inst.groups.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+    inst.rooms.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+    inst.courses.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+    inst.timeslots.sort_by_cached_key(|t| {
+        let (day, idx, sub) = t
+            .parse()
+            .map_or((7, u32::MAX, 0), |(day, idx, sub)| (day as u8, idx, sub));
+        (day, idx, sub, t.0.clone())
+    });
+}
+
+/// Stable content hash of `inst` after [`normalize`], so two instances that
+/// list the same entities in a different order share a fingerprint. Lists
+/// that are sets (availability, equipment, allowed rooms, course pairs that
+/// must not overlap, ...) are sorted too; `objective_order` and the
+/// direction of each `prefer_adjacent` pair matter, so are kept as given.
+pub fn instance_fingerprint(inst: &Instance) -> String {
+    let mut inst = inst.clone();
+    normalize(&mut inst);
+    sort_sets(&mut inst);
+    format!("{:016x}", fingerprint::canonical_hash(&inst))
+}
+
+/// Sorts the set-like lists inside `inst` for [`instance_fingerprint`].
+fn sort_sets(inst: &mut Instance) {
+    let by_id = |a: &types::TimeslotId, b: &types::TimeslotId| a.0.cmp(&b.0);
+    for t in &mut inst.teachers {
+        t.available.sort_by(by_id);
+        t.soft_available.sort_by(by_id);
+        t.prefs.avoid_slots.sort_by(by_id);
+        t.prefs.preferred_days.sort();
+    }
+    for g in &mut inst.groups {
+        g.prefs.avoid_slots.sort_by(by_id);
+        g.prefs.preferred_days.sort();
+    }
+    for r in &mut inst.rooms {
+        r.equip.sort();
+    }
+    for c in &mut inst.courses {
+        c.needs.sort();
+        c.allowed_rooms.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+    let policy = &mut inst.policy;
+    for implied in policy.equip_implies.values_mut() {
+        implied.sort();
+    }
+    for (a, b) in &mut policy.no_overlap {
+        if b.0 < a.0 {
+            std::mem::swap(a, b);
+        }
+    }
+    policy
+        .no_overlap
+        .sort_by(|x, y| (&x.0 .0, &x.1 .0).cmp(&(&y.0 .0, &y.1 .0)));
+    policy
+        .prefer_adjacent
+        .sort_by(|x, y| (&x.0 .0, &x.1 .0).cmp(&(&y.0 .0, &y.1 .0)));
+    if let Some(slots) = &mut policy.allowed_slots {
+        slots.sort_by(by_id);
+    }
+    for set in &mut policy.same_room_groups {
+        set.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+    policy
+        .same_room_groups
+        .sort_by(|x, y| x.iter().map(|c| &c.0).cmp(y.iter().map(|c| &c.0)));
+}
+
+/// Whether `res` is a schedule within `SolveParams.objectiveBudget`.
+pub fn budget_met(res: &SolveResult, budget: f64) -> bool {
+    matches!(res.status.as_str(), "solved" | "partial") && res.objective <= budget
+}
+
+/// Result for an instance with nothing to schedule or nowhere to schedule
+/// it, so solvers can skip their search: `"solved"` with no assignments when
+/// no course needs a meeting, `"infeasible"` when meetings are needed but
+/// there are no timeslots. `None` for any other instance.
+pub fn degenerate_result(inst: &Instance, method: &str) -> Option<SolveResult> {
+    let needed: u32 = inst.courses.iter().map(|c| c.required_meetings()).sum();
+    let (status, note) = if needed == 0 {
+        ("solved", "no meetings to schedule")
+    } else if inst.timeslots.is_empty() {
+        ("infeasible", "no timeslots")
+    } else {
+        return None;
+    };
+    Some(SolveResult {
+        status: status.into(),
+        objective: 0.0,
+        assignments: vec![],
+        violations: vec![],
+        stats: serde_json::json!({"method": method, "note": note}),
+    })
+}
+
+/// Cooperative cancellation flag shared between a job and its solver.
+/// Solvers that honor it return their best schedule so far with status
+/// `"cancelled"`.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
 #[async_trait]
 pub trait Solver: Send + Sync + 'static {
     async fn solve(&self, env: SolveEnvelope) -> anyhow::Result<SolveResult>;
+
+    /// Like `solve`, stopping early once `cancel` trips. Solvers without a
+    /// search loop to interrupt run to completion.
+    async fn solve_cancellable(
+        &self,
+        env: SolveEnvelope,
+        cancel: CancelToken,
+    ) -> anyhow::Result<SolveResult> {
+        let _ = cancel;
+        self.solve(env).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small() -> Instance {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+        env.instance
+    }
+
+    #[test]
+    fn capacity_warnings_flag_oversubscribed_rooms_and_equipment() {
+        let mut inst = small();
+        assert!(capacity_warnings(&inst).is_empty());
+
+        inst.timeslots.truncate(3);
+        inst.courses[1].needs = vec![types::Equip::ComputerLab];
+        let warnings = capacity_warnings(&inst);
+        assert!(warnings
+            .iter()
+            .any(|w| w.starts_with("instance is over-subscribed: 8 meeting-slots demanded, 6")));
+        assert!(warnings
+            .iter()
+            .any(|w| w.starts_with("equipment computer_lab is over-subscribed")));
+    }
+
+    #[test]
+    fn negative_soft_weights_are_rejected() {
+        let mut inst = small();
+        assert!(validate(&inst).is_ok());
+
+        inst.policy.soft_weights.windows = -2;
+        inst.policy.soft_weights.windows_groups = Some(-1);
+        let err = validate(&inst).unwrap_err().to_string();
+        assert!(err.contains("soft weight windows is negative (-2)"));
+        assert!(err.contains("soft weight windows_groups is negative (-1)"));
+    }
+
+    #[test]
+    fn fingerprint_ignores_order_but_not_capacity() {
+        let inst = small();
+        let mut shuffled = inst.clone();
+        shuffled.teachers.reverse();
+        shuffled.groups.reverse();
+        shuffled.rooms.reverse();
+        shuffled.courses.reverse();
+        shuffled.teachers[0].available.reverse();
+        assert_eq!(instance_fingerprint(&inst), instance_fingerprint(&shuffled));
+
+        let mut bigger = inst.clone();
+        bigger.rooms[0].capacity += 1;
+        assert_ne!(instance_fingerprint(&inst), instance_fingerprint(&bigger));
+    }
+
+    #[test]
+    fn double_slot_course_may_not_straddle_a_clock_gap() {
+        let mut inst = small();
+        let times = |pairs: &[(u32, &str, &str)]| {
+            pairs
+                .iter()
+                .map(|&(i, start, end)| {
+                    let (start, end) = (start.into(), end.into());
+                    (i, types::SlotTime { start, end })
+                })
+                .collect()
+        };
+        // Lunch sits between periods 2 and 3.
+        inst.slot_times = times(&[
+            (1, "08:30", "09:50"),
+            (2, "09:50", "11:10"),
+            (3, "12:00", "13:20"),
+        ]);
+        assert!(validate(&inst).is_ok());
+        assert!(occupancy::meeting_slots(&inst, 0, 2).is_some());
+        assert_eq!(occupancy::meeting_slots(&inst, 1, 2), None);
+        assert_eq!(clock::course_minutes(&inst, &inst.courses[1]), Some(160));
+
+        // With a gap after every period, c_prog has nowhere to meet.
+        inst.slot_times = times(&[
+            (1, "08:30", "09:50"),
+            (2, "10:00", "11:20"),
+            (3, "12:00", "13:20"),
+        ]);
+        let err = validate(&inst).unwrap_err().to_string();
+        assert!(err.contains("course c_prog has duration 2 but no two grid slots are back-to-back"));
+    }
+
+    #[test]
+    fn weekly_cap_below_demand_fails_validation() {
+        let mut inst = small();
+        inst.groups[0].max_weekly = Some(2);
+        assert!(validate(&inst).is_ok());
+
+        // g1's only course needs two meetings a week.
+        inst.groups[0].max_weekly = Some(1);
+        let err = validate(&inst).unwrap_err().to_string();
+        assert!(err.contains("group g1_math_1 needs 2 meetings a week but max_weekly is 1"));
+    }
+
+    #[test]
+    fn empty_rooms_are_reported_once() {
+        let mut inst = small();
+        inst.rooms.clear();
+        assert_eq!(
+            validate(&inst).unwrap_err().to_string(),
+            "invalid instance: rooms is empty but there are 2 courses to schedule"
+        );
+    }
+
+    #[test]
+    fn empty_teachers_are_reported_once() {
+        let mut inst = small();
+        inst.teachers.clear();
+        assert_eq!(
+            validate(&inst).unwrap_err().to_string(),
+            "invalid instance: teachers is empty but there are 2 courses to schedule"
+        );
+    }
 }
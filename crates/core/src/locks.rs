@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+use types::{Assignment, PartialPin, SolveEnvelope};
+
+/// Drops locks that add nothing: repeated pins, repeated partial pins with a
+/// timeslot (a course meets at most once per slot), partial pins already
+/// satisfied by a pin of the same course, and partial pins on a course
+/// whose pins already cover every required meeting. Each pin satisfies at
+/// most one partial pin. Returns one message per dropped lock.
+pub fn dedup_locks(env: &mut SolveEnvelope) -> Vec<String> {
+    let mut notes = Vec::new();
+
+    let mut seen = HashSet::new();
+    env.pinned.retain(|a| {
+        let fresh = seen.insert((a.courseId.clone(), a.timeslot.clone(), a.roomId.clone()));
+        if !fresh {
+            notes.push(format!(
+                "duplicate pin of course {} at {} in room {}",
+                a.courseId.0, a.timeslot.0, a.roomId.0
+            ));
+        }
+        fresh
+    });
+
+    let mut seen = HashSet::new();
+    let mut used = vec![false; env.pinned.len()];
+    let pinned = &env.pinned;
+    let courses = &env.instance.courses;
+    env.partial_pins.retain(|p| {
+        let pins_of_course = pinned.iter().filter(|a| a.courseId == p.courseId).count();
+        let fully_pinned = courses
+            .iter()
+            .find(|c| c.id == p.courseId)
+            .is_some_and(|c| pins_of_course as u32 >= c.required_meetings());
+        let note = if fully_pinned {
+            Some(format!(
+                "partial pin on course {} is redundant: the course is fully pinned",
+                p.courseId.0
+            ))
+        } else if let Some(i) = pinned
+            .iter()
+            .enumerate()
+            .position(|(i, a)| !used[i] && subsumes(a, p))
+        {
+            used[i] = true;
+            Some(format!(
+                "partial pin on course {} is already satisfied by its pin at {}",
+                p.courseId.0, pinned[i].timeslot.0
+            ))
+        } else if p.timeslot.is_some()
+            && !seen.insert((
+                p.courseId.clone(),
+                p.timeslot.clone(),
+                p.roomId.clone(),
+                p.teacherId.clone(),
+                p.meeting,
+            ))
+        {
+            Some(format!("duplicate partial pin on course {}", p.courseId.0))
+        } else {
+            None
+        };
+        if let Some(note) = &note {
+            notes.push(note.clone());
+        }
+        note.is_none()
+    });
+
+    notes
+}
+
+/// Whether the pinned meeting `a` meets every requirement of `p`. A pin
+/// with a `meeting` index also constrains the course's other meetings, so
+/// it is never subsumed.
+fn subsumes(a: &Assignment, p: &PartialPin) -> bool {
+    a.courseId == p.courseId
+        && p.meeting.is_none()
+        && p.timeslot.as_ref().is_none_or(|t| *t == a.timeslot)
+        && p.roomId.as_ref().is_none_or(|r| *r == a.roomId)
+        && p.teacherId.as_ref().is_none_or(|t| *t == a.teacherId)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{CourseId, RoomId, TeacherId, TimeslotId};
+
+    fn alg(ts: &str) -> Assignment {
+        Assignment {
+            courseId: CourseId("c_alg".into()),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r101".into()),
+            teacherId: TeacherId("t_kovalenko".into()),
+            meta: None,
+        }
+    }
+
+    fn partial(ts: &str) -> PartialPin {
+        PartialPin {
+            courseId: CourseId("c_alg".into()),
+            timeslot: Some(TimeslotId(ts.into())),
+            roomId: None,
+            teacherId: None,
+            meeting: None,
+        }
+    }
+
+    #[test]
+    fn partial_pin_on_a_fully_pinned_course_is_dropped() {
+        let mut env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+        env.pinned = vec![alg("mon.1"), alg("tue.1")];
+        env.partial_pins = vec![partial("mon.1")];
+
+        let notes = dedup_locks(&mut env);
+        assert_eq!(env.pinned.len(), 2);
+        assert!(env.partial_pins.is_empty());
+        assert_eq!(
+            notes,
+            ["partial pin on course c_alg is redundant: the course is fully pinned"]
+        );
+
+        // With one meeting left open, the matching pin is what makes it redundant.
+        env.pinned.pop();
+        env.partial_pins = vec![partial("mon.1"), partial("wed.1")];
+        let notes = dedup_locks(&mut env);
+        assert_eq!(env.partial_pins.len(), 1);
+        assert_eq!(
+            notes,
+            ["partial pin on course c_alg is already satisfied by its pin at mon.1"]
+        );
+    }
+}
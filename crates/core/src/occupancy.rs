@@ -0,0 +1,530 @@
+use std::collections::{HashMap, HashSet};
+use types::{Assignment, Course, Instance, Parity, Room, Teacher};
+
+/// Whether the slot at `(index, sub)` comes right after `(i0, s0)` on the
+/// same day: the next sub-slot of the period, or the start of the next
+/// period (whole or sub-slot `a`). A grid must list every sub-slot of a
+/// split period for this to hold.
+pub(crate) fn directly_follows((i0, s0): (u32, u32), (i1, s1): (u32, u32)) -> bool {
+    (i1 == i0 && s0 > 0 && s1 == s0 + 1) || (i1 == i0 + 1 && s1 <= 1)
+}
+
+/// Timeslot indices covered by a meeting of `duration` starting at `t0`.
+/// A two-slot meeting needs the next grid slot to be the following period of
+/// the same day, and when `slot_times` are known the first must end as the
+/// second starts; `None` means the meeting cannot start at `t0`.
+pub fn meeting_slots(inst: &Instance, t0: usize, duration: u32) -> Option<Vec<usize>> {
+    if t0 >= inst.timeslots.len() {
+        return None;
+    }
+    if duration != 2 {
+        return Some(vec![t0]);
+    }
+    let next = inst.timeslots.get(t0 + 1)?;
+    match (inst.timeslots[t0].parse(), next.parse()) {
+        (Some((d0, i0, s0)), Some((d1, i1, s1)))
+            if d0 == d1
+                && directly_follows((i0, s0), (i1, s1))
+                && crate::clock::contiguous(inst, t0, t0 + 1) =>
+        {
+            Some(vec![t0, t0 + 1])
+        }
+        _ => None,
+    }
+}
+
+/// Whether the policy's `allowed_slots` lists every slot of a meeting.
+pub fn slots_allowed(inst: &Instance, slots: &[usize]) -> bool {
+    slots.iter().all(|&k| {
+        inst.timeslots
+            .get(k)
+            .is_none_or(|t| inst.policy.allows_slot(&t.0))
+    })
+}
+
+/// Whether `teacher` is available, hard or soft, in every slot of a
+/// meeting (`availability::slot_allowed`).
+pub fn teacher_free(inst: &Instance, teacher: &Teacher, slots: &[usize]) -> bool {
+    slots.iter().all(|&k| {
+        inst.timeslots
+            .get(k)
+            .is_some_and(|t| crate::availability::slot_allowed(teacher, &t.0, &inst.policy))
+    })
+}
+
+/// Whether `room` is open in every slot of a meeting (`Room::open_at`).
+pub fn room_open(inst: &Instance, room: &Room, slots: &[usize]) -> bool {
+    slots.iter().all(|&k| {
+        inst.timeslots
+            .get(k)
+            .and_then(|t| t.parse())
+            .is_none_or(|(_, idx, _)| room.open_at(idx))
+    })
+}
+
+/// Bit set of the weeks a course with this parity meets in.
+fn weeks(parity: Parity) -> u8 {
+    match parity {
+        Parity::Odd => 1,
+        Parity::Even => 2,
+        Parity::Every => 3,
+    }
+}
+
+/// (room, slot), (teacher, slot) and (group, slot) occupancy keyed by id,
+/// plus per-(building, slot) room counts for capped buildings, seat totals
+/// for open-lab rooms (`student_capacity`), open-lab (room, slot) pairs held
+/// by an `exclusive_room` course and (course, slot) marks for
+/// `policy.no_overlap`. The `room`, `teacher` and `group` sets hold keys
+/// busy in any week; clashes are checked per week so odd- and even-week
+/// courses can share a slot. Seats, building caps and `no_overlap` ignore
+/// parity.
+#[derive(Clone, Debug, Default)]
+pub struct Occupancy<'a> {
+    pub room: HashSet<(&'a str, usize)>,
+    pub teacher: HashSet<(&'a str, usize)>,
+    pub group: HashSet<(&'a str, usize)>,
+    pub building: HashMap<(&'a str, usize), u32>,
+    /// Students seated per (open-lab room, slot).
+    pub seats: HashMap<(&'a str, usize), u32>,
+    pub course: HashSet<(&'a str, usize)>,
+    /// Open-lab (room, slot) pairs an `exclusive_room` course holds alone.
+    pub held: HashSet<(&'a str, usize)>,
+    exclusive: HashSet<&'a str>,
+    capped_building: HashMap<&'a str, (&'a str, u32)>,
+    shared_room: HashMap<&'a str, u32>,
+    group_size: HashMap<&'a str, u32>,
+    sharers: HashMap<(&'a str, usize), u32>,
+    no_overlap: HashMap<&'a str, Vec<&'a str>>,
+    room_weeks: HashMap<(&'a str, usize), u8>,
+    teacher_weeks: HashMap<(&'a str, usize), u8>,
+    group_weeks: HashMap<(&'a str, usize), u8>,
+}
+
+impl<'a> Occupancy<'a> {
+    /// Empty occupancy that enforces `policy.building_concurrency` and
+    /// open-lab seat limits.
+    pub fn new(inst: &'a Instance) -> Self {
+        let caps = &inst.policy.building_concurrency;
+        let capped_building = inst
+            .rooms
+            .iter()
+            .filter_map(|r| {
+                let b = r.building.as_deref()?;
+                let (b, &cap) = caps.get_key_value(b)?;
+                Some((r.id.0.as_str(), (b.as_str(), cap)))
+            })
+            .collect();
+        let shared_room = inst
+            .rooms
+            .iter()
+            .filter_map(|r| Some((r.id.0.as_str(), r.student_capacity?)))
+            .collect();
+        let group_size = inst
+            .groups
+            .iter()
+            .map(|g| (g.id.0.as_str(), g.size))
+            .collect();
+        let mut no_overlap: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (a, b) in &inst.policy.no_overlap {
+            no_overlap
+                .entry(a.0.as_str())
+                .or_default()
+                .push(b.0.as_str());
+            no_overlap
+                .entry(b.0.as_str())
+                .or_default()
+                .push(a.0.as_str());
+        }
+        let exclusive = inst
+            .courses
+            .iter()
+            .filter(|c| c.exclusive_room)
+            .map(|c| c.id.0.as_str())
+            .collect();
+        Self {
+            exclusive,
+            capped_building,
+            shared_room,
+            group_size,
+            no_overlap,
+            ..Self::default()
+        }
+    }
+
+    /// Keys borrow from `inst`, so the assignments may be dropped or edited
+    /// afterwards. Assignments with an unknown course or timeslot are skipped,
+    /// an unknown room or teacher only leaves that dimension unmarked. A
+    /// two-slot meeting that cannot fit (grid end or day boundary) occupies
+    /// its start slot only.
+    pub fn from_assignments<'b, I>(inst: &'a Instance, assignments: I) -> Self
+    where
+        I: IntoIterator<Item = &'b Assignment>,
+    {
+        let course_by_id: HashMap<&str, &'a Course> =
+            inst.courses.iter().map(|c| (c.id.0.as_str(), c)).collect();
+        let room_ids: HashMap<&str, &'a str> = inst
+            .rooms
+            .iter()
+            .map(|r| (r.id.0.as_str(), r.id.0.as_str()))
+            .collect();
+        let teacher_ids: HashMap<&str, &'a str> = inst
+            .teachers
+            .iter()
+            .map(|t| (t.id.0.as_str(), t.id.0.as_str()))
+            .collect();
+        let ts_index: HashMap<&str, usize> = inst
+            .timeslots
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.0.as_str(), i))
+            .collect();
+
+        let mut occ = Self::new(inst);
+        for a in assignments {
+            let (Some(&c), Some(&t0)) = (
+                course_by_id.get(a.courseId.0.as_str()),
+                ts_index.get(a.timeslot.0.as_str()),
+            ) else {
+                continue;
+            };
+            let slots = meeting_slots(inst, t0, c.duration).unwrap_or_else(|| vec![t0]);
+            let room = room_ids.get(a.roomId.0.as_str());
+            let teacher = teacher_ids.get(a.teacherId.0.as_str());
+            let group = c.groupId.0.as_str();
+            let w = weeks(c.parity());
+            for &k in &slots {
+                if let Some(&r) = room {
+                    occ.mark_room(r, group, k, w);
+                    if occ.holds(c.id.0.as_str(), r) {
+                        occ.held.insert((r, k));
+                    }
+                }
+                if let Some(&t) = teacher {
+                    mark(&mut occ.teacher, &mut occ.teacher_weeks, t, k, w);
+                }
+                mark(&mut occ.group, &mut occ.group_weeks, group, k, w);
+                if occ.no_overlap.contains_key(c.id.0.as_str()) {
+                    occ.course.insert((c.id.0.as_str(), k));
+                }
+            }
+        }
+        occ
+    }
+
+    pub fn is_free(
+        &self,
+        room: &str,
+        teacher: &str,
+        group: &str,
+        slots: &[usize],
+        parity: Parity,
+    ) -> bool {
+        let cap = self.capped_building.get(room);
+        let shared = self.shared_room.get(room);
+        let size = self.group_size.get(group).copied().unwrap_or(0);
+        slots.iter().all(|&k| {
+            let room_ok = match shared {
+                Some(&seats) => self.seats.get(&(room, k)).copied().unwrap_or(0) + size <= seats,
+                None => !self.room_busy(room, k, parity),
+            };
+            let opens_room = !self.room.contains(&(room, k));
+            room_ok
+                && !self.teacher_busy(teacher, k, parity)
+                && !self.group_busy(group, k, parity)
+                && (!opens_room
+                    || cap.is_none_or(|&(b, cap)| {
+                        self.building.get(&(b, k)).copied().unwrap_or(0) < cap
+                    }))
+        })
+    }
+
+    /// Whether `room` is taken at `k` in a week a `parity` course meets.
+    pub fn room_busy(&self, room: &str, k: usize, parity: Parity) -> bool {
+        busy(&self.room_weeks, room, k, parity)
+    }
+
+    pub fn teacher_busy(&self, teacher: &str, k: usize, parity: Parity) -> bool {
+        busy(&self.teacher_weeks, teacher, k, parity)
+    }
+
+    pub fn group_busy(&self, group: &str, k: usize, parity: Parity) -> bool {
+        busy(&self.group_weeks, group, k, parity)
+    }
+
+    pub fn insert(
+        &mut self,
+        room: &'a str,
+        teacher: &'a str,
+        group: &'a str,
+        slots: &[usize],
+        parity: Parity,
+    ) {
+        let w = weeks(parity);
+        for &k in slots {
+            self.mark_room(room, group, k, w);
+            mark(&mut self.teacher, &mut self.teacher_weeks, teacher, k, w);
+            mark(&mut self.group, &mut self.group_weeks, group, k, w);
+        }
+    }
+
+    pub fn remove(
+        &mut self,
+        room: &'a str,
+        teacher: &'a str,
+        group: &'a str,
+        slots: &[usize],
+        parity: Parity,
+    ) {
+        let w = weeks(parity);
+        for &k in slots {
+            if self.unmark_room(room, group, k, w) {
+                if let Some(&(b, _)) = self.capped_building.get(room) {
+                    if let Some(n) = self.building.get_mut(&(b, k)) {
+                        *n = n.saturating_sub(1);
+                    }
+                }
+            }
+            unmark(&mut self.teacher, &mut self.teacher_weeks, teacher, k, w);
+            unmark(&mut self.group, &mut self.group_weeks, group, k, w);
+        }
+    }
+
+    /// Whether `course` may meet in `room` at `slots` without meeting a
+    /// `no_overlap` partner there, and without sharing an open-lab room with
+    /// an `exclusive_room` course (or, being one, with anyone).
+    pub fn course_free(&self, course: &str, room: &str, slots: &[usize]) -> bool {
+        let room_ok = if !self.shared_room.contains_key(room) {
+            true
+        } else if self.exclusive.contains(course) {
+            slots
+                .iter()
+                .all(|&k| !self.sharers.contains_key(&(room, k)))
+        } else {
+            slots.iter().all(|&k| !self.held.contains(&(room, k)))
+        };
+        let Some(partners) = self.no_overlap.get(course) else {
+            return room_ok;
+        };
+        room_ok
+            && partners
+                .iter()
+                .all(|&p| slots.iter().all(|&k| !self.course.contains(&(p, k))))
+    }
+
+    /// Only courses listed in `no_overlap`, and `exclusive_room` courses in
+    /// open-lab rooms, are tracked.
+    pub fn insert_course(&mut self, course: &'a str, room: &'a str, slots: &[usize]) {
+        if self.no_overlap.contains_key(course) {
+            self.course.extend(slots.iter().map(|&k| (course, k)));
+        }
+        if self.holds(course, room) {
+            self.held.extend(slots.iter().map(|&k| (room, k)));
+        }
+    }
+
+    pub fn remove_course(&mut self, course: &'a str, room: &'a str, slots: &[usize]) {
+        let holds = self.holds(course, room);
+        for &k in slots {
+            self.course.remove(&(course, k));
+            if holds {
+                self.held.remove(&(room, k));
+            }
+        }
+    }
+
+    /// Whether a meeting of `course` in `room` holds the room alone.
+    fn holds(&self, course: &str, room: &str) -> bool {
+        self.exclusive.contains(course) && self.shared_room.contains_key(room)
+    }
+
+    /// Returns whether the room became free at `k`.
+    fn unmark_room(&mut self, room: &'a str, group: &str, k: usize, w: u8) -> bool {
+        if !self.shared_room.contains_key(room) {
+            return unmark(&mut self.room, &mut self.room_weeks, room, k, w);
+        }
+        let Some(n) = self.sharers.get_mut(&(room, k)) else {
+            return false;
+        };
+        *n -= 1;
+        let size = self.group_size.get(group).copied().unwrap_or(0);
+        if let Some(s) = self.seats.get_mut(&(room, k)) {
+            *s = s.saturating_sub(size);
+        }
+        if *n > 0 {
+            return false;
+        }
+        self.sharers.remove(&(room, k));
+        self.seats.remove(&(room, k));
+        self.room_weeks.remove(&(room, k));
+        self.room.remove(&(room, k))
+    }
+
+    fn mark_room(&mut self, room: &'a str, group: &str, k: usize, w: u8) {
+        if self.shared_room.contains_key(room) {
+            let size = self.group_size.get(group).copied().unwrap_or(0);
+            *self.sharers.entry((room, k)).or_default() += 1;
+            *self.seats.entry((room, k)).or_default() += size;
+        }
+        if mark(&mut self.room, &mut self.room_weeks, room, k, w) {
+            if let Some(&(b, _)) = self.capped_building.get(room) {
+                *self.building.entry((b, k)).or_default() += 1;
+            }
+        }
+    }
+}
+
+fn busy(by_week: &HashMap<(&str, usize), u8>, id: &str, k: usize, parity: Parity) -> bool {
+    by_week
+        .get(&(id, k))
+        .is_some_and(|&w| w & weeks(parity) != 0)
+}
+
+/// Returns whether the key was free in every week before.
+fn mark<'a>(
+    set: &mut HashSet<(&'a str, usize)>,
+    by_week: &mut HashMap<(&'a str, usize), u8>,
+    id: &'a str,
+    k: usize,
+    w: u8,
+) -> bool {
+    *by_week.entry((id, k)).or_default() |= w;
+    set.insert((id, k))
+}
+
+/// Returns whether the key is now free in every week.
+fn unmark<'a>(
+    set: &mut HashSet<(&'a str, usize)>,
+    by_week: &mut HashMap<(&'a str, usize), u8>,
+    id: &'a str,
+    k: usize,
+    w: u8,
+) -> bool {
+    let Some(left) = by_week.get_mut(&(id, k)) else {
+        return false;
+    };
+    *left &= !w;
+    if *left != 0 {
+        return false;
+    }
+    by_week.remove(&(id, k));
+    set.remove(&(id, k))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{CourseId, RoomId, SolveEnvelope, TeacherId, TimeslotId};
+
+    fn small() -> Instance {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+        env.instance
+    }
+
+    fn prog(ts: &str) -> Assignment {
+        Assignment {
+            courseId: CourseId("c_prog".into()),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r202".into()),
+            teacherId: TeacherId("t_petrenko".into()),
+            meta: None,
+        }
+    }
+
+    fn sorted(set: &HashSet<(&str, usize)>, id: &str) -> Vec<usize> {
+        let mut ks: Vec<usize> = set
+            .iter()
+            .filter(|(i, _)| *i == id)
+            .map(|&(_, k)| k)
+            .collect();
+        ks.sort();
+        ks
+    }
+
+    #[test]
+    fn two_slot_meetings_cover_both_slots_on_their_own_day() {
+        let inst = small();
+        // mon.1 (0) and tue.2 (4) start two-slot meetings; mon.3 (2) ends its
+        // day, so a meeting there can only hold its start slot.
+        let plan = [prog("mon.1"), prog("tue.2"), prog("mon.3")];
+        let occ = Occupancy::from_assignments(&inst, &plan);
+        assert_eq!(sorted(&occ.room, "r202"), vec![0, 1, 2, 4, 5]);
+        assert_eq!(sorted(&occ.teacher, "t_petrenko"), vec![0, 1, 2, 4, 5]);
+        assert_eq!(sorted(&occ.group, "g2_cs_1"), vec![0, 1, 2, 4, 5]);
+        assert!(!occ.room.contains(&("r202", 3)));
+        assert!(occ.is_free("r202", "t_petrenko", "g2_cs_1", &[3], Parity::Every));
+        assert!(!occ.is_free("r101", "t_kovalenko", "g2_cs_1", &[1], Parity::Every));
+    }
+
+    #[test]
+    fn meetings_never_run_past_the_day_or_the_grid() {
+        let inst = small();
+        assert_eq!(meeting_slots(&inst, 1, 2), Some(vec![1, 2]));
+        assert_eq!(meeting_slots(&inst, 2, 2), None);
+        assert_eq!(meeting_slots(&inst, 11, 2), None);
+        assert_eq!(meeting_slots(&inst, 11, 1), Some(vec![11]));
+        assert_eq!(meeting_slots(&inst, 12, 1), None);
+    }
+
+    #[test]
+    fn open_lab_seats_groups_until_the_student_capacity_runs_out() {
+        let mut inst = small();
+        inst.rooms[1].student_capacity = Some(64);
+        let mut third = inst.groups[0].clone();
+        third.id = types::GroupId("g3_phys_1".into());
+        third.size = 5;
+        inst.groups.push(third);
+
+        let mut occ = Occupancy::new(&inst);
+        occ.insert("r202", "t_kovalenko", "g1_math_1", &[0], Parity::Every);
+        assert!(occ.is_free("r202", "t_petrenko", "g2_cs_1", &[0], Parity::Every));
+        occ.insert("r202", "t_petrenko", "g2_cs_1", &[0], Parity::Every);
+        assert_eq!(occ.seats[&("r202", 0)], 28 + 32);
+        // 28 + 32 + 5 = 65 seats in a room for 64.
+        assert!(!occ.is_free("r202", "t_x", "g3_phys_1", &[0], Parity::Every));
+        assert!(occ.is_free("r202", "t_x", "g3_phys_1", &[1], Parity::Every));
+
+        occ.remove("r202", "t_petrenko", "g2_cs_1", &[0], Parity::Every);
+        assert!(occ.is_free("r202", "t_x", "g3_phys_1", &[0], Parity::Every));
+    }
+
+    #[test]
+    fn exclusive_course_holds_an_open_lab_alone() {
+        let mut inst = small();
+        inst.rooms[1].student_capacity = Some(100);
+        let occ = Occupancy::from_assignments(&inst, &[prog("mon.1")]);
+        assert!(occ.is_free("r202", "t_kovalenko", "g1_math_1", &[0], Parity::Every));
+        assert!(occ.course_free("c_alg", "r202", &[0]));
+
+        inst.courses[1].exclusive_room = true;
+        let occ = Occupancy::from_assignments(&inst, &[prog("mon.1")]);
+        assert!(!occ.course_free("c_alg", "r202", &[0]));
+        assert!(!occ.course_free("c_alg", "r202", &[1]));
+        assert!(occ.course_free("c_alg", "r202", &[2]));
+        assert!(occ.course_free("c_alg", "r101", &[0]));
+
+        // Nor may the exclusive course join a lab someone is already in.
+        let alg = Assignment {
+            courseId: CourseId("c_alg".into()),
+            roomId: RoomId("r202".into()),
+            teacherId: TeacherId("t_kovalenko".into()),
+            ..prog("mon.1")
+        };
+        let occ = Occupancy::from_assignments(&inst, &[alg]);
+        assert!(!occ.course_free("c_prog", "r202", &[0, 1]));
+        assert!(occ.course_free("c_prog", "r202", &[3, 4]));
+    }
+
+    #[test]
+    fn teacher_must_be_free_in_both_slots_of_a_long_meeting() {
+        let inst = small();
+        let kovalenko = &inst.teachers[0];
+        assert_eq!(kovalenko.id.0, "t_kovalenko");
+        let at = |t: usize, d: u32| meeting_slots(&inst, t, d).unwrap();
+        assert!(teacher_free(&inst, kovalenko, &at(0, 2)));
+        assert!(!teacher_free(&inst, kovalenko, &at(1, 2)));
+        assert!(teacher_free(&inst, kovalenko, &at(6, 1)));
+        assert!(!teacher_free(&inst, kovalenko, &at(6, 2)));
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use types::{Assignment, CourseId, GroupId, SolveEnvelope};
+
+/// Turns every partial pin carrying a `teacherId` into its own one-meeting
+/// course taught by that teacher, taken out of the original course's demand.
+/// Solvers then place and check the substitute like any other course. Returns
+/// the synthetic course ids mapped back to the originals for
+/// [`restore_course_ids`].
+pub fn split_teacher_overrides(env: &mut SolveEnvelope) -> HashMap<String, CourseId> {
+    let mut synthetic = HashMap::new();
+    for (k, pin) in env.partial_pins.iter_mut().enumerate() {
+        let Some(teacher) = pin.teacherId.take() else {
+            continue;
+        };
+        let courses = &mut env.instance.courses;
+        let Some(ci) = courses.iter().position(|c| c.id == pin.courseId) else {
+            continue;
+        };
+        let required = courses[ci].required_meetings();
+        if required == 0 {
+            continue;
+        }
+        let original = &mut courses[ci];
+        original.countPerWeek = required - 1;
+        original.sections = None;
+
+        let mut sub = original.clone();
+        sub.id = CourseId(format!("{}~sub{}", original.id.0, k));
+        sub.teacherId = teacher;
+        sub.countPerWeek = 1;
+        synthetic.insert(sub.id.0.clone(), pin.courseId.clone());
+        pin.courseId = sub.id.clone();
+
+        // The substitute meeting keeps the course's no-overlap partners.
+        let no_overlap = &mut env.instance.policy.no_overlap;
+        let inherited: Vec<_> = no_overlap
+            .iter()
+            .filter_map(|(a, b)| {
+                if *a == original.id {
+                    Some((sub.id.clone(), b.clone()))
+                } else if *b == original.id {
+                    Some((a.clone(), sub.id.clone()))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        no_overlap.extend(inherited);
+        courses.push(sub);
+    }
+    synthetic
+}
+
+/// Maps assignments of synthetic override courses back to their course.
+pub fn restore_course_ids(assignments: &mut [Assignment], synthetic: &HashMap<String, CourseId>) {
+    for a in assignments {
+        if let Some(orig) = synthetic.get(&a.courseId.0) {
+            a.courseId = orig.clone();
+        }
+    }
+}
+
+/// Scoped solve for `SolveParams.onlyGroups`: courses of other groups keep
+/// exactly their `base` and `pinned` meetings, which become pins, so the
+/// solver schedules only the listed groups around fixed occupancy of shared
+/// teachers and rooms. Partial pins of other groups are dropped. No-op when
+/// `onlyGroups` is empty.
+pub fn restrict_to_groups(env: &mut SolveEnvelope) {
+    if env.params.onlyGroups.is_empty() {
+        return;
+    }
+    let group_of: HashMap<CourseId, GroupId> = env
+        .instance
+        .courses
+        .iter()
+        .map(|c| (c.id.clone(), c.groupId.clone()))
+        .collect();
+    let in_scope = |course: &CourseId| {
+        group_of
+            .get(course)
+            .is_some_and(|g| env.params.onlyGroups.contains(g))
+    };
+
+    let (kept, fixed): (Vec<Assignment>, Vec<Assignment>) = std::mem::take(&mut env.base)
+        .into_iter()
+        .partition(|a| in_scope(&a.courseId));
+    env.base = kept;
+    for a in fixed {
+        let already = env
+            .pinned
+            .iter()
+            .any(|p| p.courseId == a.courseId && p.timeslot == a.timeslot);
+        if !already {
+            env.pinned.push(a);
+        }
+    }
+    env.partial_pins.retain(|p| in_scope(&p.courseId));
+
+    let mut pins_of: HashMap<&CourseId, u32> = HashMap::new();
+    for a in &env.pinned {
+        *pins_of.entry(&a.courseId).or_default() += 1;
+    }
+    for c in &mut env.instance.courses {
+        if !env.params.onlyGroups.contains(&c.groupId) {
+            c.countPerWeek = pins_of.get(&c.id).copied().unwrap_or(0);
+            c.sections = None;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Named soft-weight presets selectable through `Policy.preset`.
+//!
+//! | preset             | unpreferred_time | windows | building_switch | load_imbalance | compactness | soft_availability |
+//! |--------------------|------------------|---------|-----------------|----------------|-------------|-------------------|
+//! | `teacher_friendly` | 10               | 3       | 1               | 2              | 0           | 8                 |
+//! | `compact`          | 2                | 10      | 2               | 0              | 5           | 2                 |
+//! | `balanced`         | 5                | 5       | 2               | 1              | 1           | 4                 |
+//!
+//! Weights not listed are zero. A preset only applies when every explicit
+//! weight in `soft_weights` is zero; any non-zero explicit weight means the
+//! caller's weights are used as-is.
+
+use types::{Policy, SoftWeights};
+
+pub const PRESET_NAMES: &[&str] = &["teacher_friendly", "compact", "balanced"];
+
+pub fn preset_weights(name: &str) -> Option<SoftWeights> {
+    let w = match name {
+        "teacher_friendly" => SoftWeights {
+            unpreferred_time: 10,
+            windows: 3,
+            building_switch: 1,
+            load_imbalance: 2,
+            compactness: 0,
+            soft_availability: 8,
+            ..Default::default()
+        },
+        "compact" => SoftWeights {
+            unpreferred_time: 2,
+            windows: 10,
+            building_switch: 2,
+            load_imbalance: 0,
+            compactness: 5,
+            soft_availability: 2,
+            ..Default::default()
+        },
+        "balanced" => SoftWeights {
+            unpreferred_time: 5,
+            windows: 5,
+            building_switch: 2,
+            load_imbalance: 1,
+            compactness: 1,
+            soft_availability: 4,
+            ..Default::default()
+        },
+        _ => return None,
+    };
+    Some(w)
+}
+
+/// Weights the solvers and scoring should actually use for `policy`.
+pub fn effective_weights(policy: &Policy) -> SoftWeights {
+    if policy.soft_weights == SoftWeights::default() {
+        if let Some(w) = policy.preset.as_deref().and_then(preset_weights) {
+            return w;
+        }
+    }
+    policy.soft_weights.clone()
+}
+
+/// `policy` as the solvers see it: preset weights filled into
+/// `soft_weights` and `equip_implies` closed under chaining. `preset` is
+/// kept so callers can tell where the weights came from.
+pub fn resolve_policy(policy: &Policy) -> Policy {
+    Policy {
+        soft_weights: effective_weights(policy),
+        equip_implies: crate::equip::implied_closure(policy),
+        ..policy.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(v: serde_json::Value) -> Policy {
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn preset_applies_only_without_explicit_weights() {
+        let compact = policy(serde_json::json!({"preset": "compact"}));
+        assert_eq!(
+            effective_weights(&compact),
+            preset_weights("compact").unwrap()
+        );
+
+        let explicit = policy(serde_json::json!({
+            "preset": "compact",
+            "soft_weights": {"windows": 1}
+        }));
+        assert_eq!(effective_weights(&explicit).windows, 1);
+        assert_eq!(effective_weights(&explicit).compactness, 0);
+    }
+
+    #[test]
+    fn every_listed_preset_resolves() {
+        for name in PRESET_NAMES {
+            assert!(preset_weights(name).is_some(), "{name}");
+        }
+        assert!(preset_weights("fastest").is_none());
+    }
+}
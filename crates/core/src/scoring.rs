@@ -1,15 +1,79 @@
-use std::collections::{HashMap, HashSet};
-use types::{Assignment, Course, Instance, Room, Teacher};
+use crate::availability::{group_avoids, slot_allowed, slot_is_soft};
+use crate::occupancy::{meeting_slots, Occupancy};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use types::{Assignment, Course, DayOfWeek, Group, Instance, Parity, Room, Teacher, TimeslotId};
 
 #[derive(Clone, Debug, Default)]
 pub struct Scores {
     pub unpreferred_meetings: i64,
+    pub group_unpreferred: HashMap<String, i64>,
+    pub group_unpreferred_total: i64,
+    pub soft_available_meetings: i64,
     pub windows_teachers: HashMap<String, i64>,
     pub windows_groups: HashMap<String, i64>,
     pub windows_total: i64,
+    pub teacher_loads: HashMap<String, i64>,
+    pub load_imbalance: f64,
+    pub group_day_spans: HashMap<String, HashMap<String, i64>>,
+    pub spans_total: i64,
+    /// Distinct rooms beyond the first, per course that uses more than one.
+    pub course_room_spread: HashMap<String, i64>,
+    pub room_spread_total: i64,
+    /// Distinct rooms beyond the first, per teacher that uses more than one.
+    pub teacher_room_spread: HashMap<String, i64>,
+    pub teacher_room_spread_total: i64,
+    /// Meetings held outside the group's home building, per group.
+    pub away_from_home: HashMap<String, i64>,
+    pub away_from_home_total: i64,
+    /// `policy.prefer_adjacent` pairs met back-to-back at least once, and
+    /// the ones that are not; only missed pairs are penalized.
+    pub adjacent_pairs_satisfied: i64,
+    pub adjacent_pairs_missed: i64,
+    /// Meetings of odd/even course pairs (see [`parity_pairs`]) with no
+    /// counterpart starting in the same slot.
+    pub parity_slot_mismatches: i64,
+    /// Distinct days with at least one meeting, per teacher and per group.
+    pub active_days_teachers: HashMap<String, i64>,
+    pub active_days_groups: HashMap<String, i64>,
+    pub active_days_total: i64,
+    /// Distinct buildings beyond the first, per course that uses more than one.
+    pub course_building_spread: HashMap<String, i64>,
+    pub building_spread_total: i64,
+    /// Back-to-back moves between buildings, per teacher and per group, and
+    /// their summed `Policy.building_distance`.
+    pub building_switches_teachers: HashMap<String, i64>,
+    pub building_switches_groups: HashMap<String, i64>,
+    pub building_switch_distance: i64,
+    /// Distinct rooms with at least one meeting.
+    pub rooms_used: i64,
+    /// Days short of [`ideal_day_gap`], summed over pairs of distinct days a
+    /// course meets on, per course with any.
+    pub course_spacing: HashMap<String, i64>,
+    pub course_spacing_total: i64,
+    /// Weighted share of each soft term; contributions sum to `objective`.
+    pub components: Vec<ObjectiveComponent>,
     pub objective: f64,
 }
 
+#[derive(Clone, Debug)]
+pub struct ObjectiveComponent {
+    pub term: &'static str,
+    pub count: f64,
+    pub weight: f64,
+    pub contribution: f64,
+}
+
+impl ObjectiveComponent {
+    fn new(term: &'static str, count: f64, weight: f64) -> Self {
+        Self {
+            term,
+            count,
+            weight,
+            contribution: weight * count,
+        }
+    }
+}
+
 pub fn compute_soft_scores(inst: &Instance, assignments: &[Assignment]) -> Scores {
     let times: Vec<&str> = inst.timeslots.iter().map(|t| t.0.as_str()).collect();
 
@@ -20,29 +84,10 @@ pub fn compute_soft_scores(inst: &Instance, assignments: &[Assignment]) -> Score
 
     let teachers_by_id: HashMap<&str, &Teacher> =
         inst.teachers.iter().map(|t| (t.id.0.as_str(), t)).collect();
+    let groups_by_id: HashMap<&str, &Group> =
+        inst.groups.iter().map(|g| (g.id.0.as_str(), g)).collect();
 
-    let mut day_of: Vec<&str> = Vec::with_capacity(times.len());
-    let mut day_index: Vec<u32> = Vec::with_capacity(times.len());
-    for &ts in &times {
-        let mut parts = ts.split('.');
-        let d = parts.next().unwrap_or("");
-        let idx = parts
-            .next()
-            .and_then(|x| x.parse::<u32>().ok())
-            .unwrap_or(0);
-        day_of.push(d);
-        day_index.push(idx);
-    }
-    let mut day_slots: HashMap<&str, Vec<usize>> = HashMap::new();
-    for k in 0..times.len() {
-        day_slots.entry(day_of[k]).or_default().push(k);
-    }
-    for v in day_slots.values_mut() {
-        v.sort_by_key(|&k| day_index[k]);
-    }
-
-    let mut occ_teacher: HashMap<(&str, usize), bool> = HashMap::new();
-    let mut occ_group: HashMap<(&str, usize), bool> = HashMap::new();
+    let day_slots = day_slots(&times);
 
     let mut avoid_by_teacher: HashMap<&str, HashSet<&str>> = HashMap::new();
     for t in &inst.teachers {
@@ -53,6 +98,13 @@ pub fn compute_soft_scores(inst: &Instance, assignments: &[Assignment]) -> Score
     }
 
     let mut unpref = 0i64;
+    let mut soft_avail = 0i64;
+    let mut group_unpreferred: HashMap<String, i64> = HashMap::new();
+    let mut away: HashMap<String, i64> = HashMap::new();
+    let rooms_by_id: HashMap<&str, &Room> =
+        inst.rooms.iter().map(|r| (r.id.0.as_str(), r)).collect();
+    let mut teacher_loads: HashMap<String, i64> =
+        inst.teachers.iter().map(|t| (t.id.0.clone(), 0)).collect();
 
     let mut ts_index: HashMap<&str, usize> = HashMap::new();
     for (i, &ts) in times.iter().enumerate() {
@@ -64,32 +116,40 @@ pub fn compute_soft_scores(inst: &Instance, assignments: &[Assignment]) -> Score
             Some(c) => *c,
             None => continue,
         };
-        let tid = c.teacherId.0.as_str();
-        let gid = c.groupId.0.as_str();
+        let tid = a.teacherId.0.as_str();
         let t0 = match ts_index.get(a.timeslot.0.as_str()) {
             Some(&i) => i,
             None => continue,
         };
-        let dur2 = c.duration == 2;
+        let slots = meeting_slots(inst, t0, c.duration).unwrap_or_else(|| vec![t0]);
+        *teacher_loads.entry(tid.to_string()).or_default() += 1;
 
-        if let Some(avoid) = avoid_by_teacher.get(&tid) {
-            let mut penalize = avoid.contains(times[t0]);
-            if dur2 && t0 + 1 < times.len() {
-                penalize = penalize || avoid.contains(times[t0 + 1]);
+        if let Some(teacher) = teachers_by_id.get(tid) {
+            if slots.iter().any(|&k| slot_is_soft(teacher, times[k])) {
+                soft_avail += 1;
             }
-            if penalize {
+        }
+
+        if let Some(avoid) = avoid_by_teacher.get(&tid) {
+            if slots.iter().any(|&k| avoid.contains(times[k])) {
                 unpref += 1;
             }
         }
 
-        occ_teacher.insert((tid, t0), true);
-        occ_group.insert((gid, t0), true);
-        if dur2 && t0 + 1 < times.len() {
-            occ_teacher.insert((tid, t0 + 1), true);
-            occ_group.insert((gid, t0 + 1), true);
+        if let Some(group) = groups_by_id.get(c.groupId.0.as_str()) {
+            if slots.iter().any(|&k| group_avoids(group, times[k])) {
+                *group_unpreferred.entry(c.groupId.0.clone()).or_default() += 1;
+            }
+            if let Some(room) = rooms_by_id.get(a.roomId.0.as_str()) {
+                if away_from_home(group, room) {
+                    *away.entry(c.groupId.0.clone()).or_default() += 1;
+                }
+            }
         }
     }
 
+    let occ = Occupancy::from_assignments(inst, assignments);
+
     let mut windows_teachers: HashMap<String, i64> = HashMap::new();
     let mut windows_groups: HashMap<String, i64> = HashMap::new();
 
@@ -98,19 +158,25 @@ pub fn compute_soft_scores(inst: &Instance, assignments: &[Assignment]) -> Score
     let mut group_ids: Vec<&str> = inst.groups.iter().map(|g| g.id.0.as_str()).collect();
     group_ids.sort_unstable();
 
-    let mut agent_windows = |is_teacher: bool, id: &str| -> i64 {
+    let agent_windows = |is_teacher: bool, id: &str| -> i64 {
         let mut total = 0i64;
         for (_day, slots) in &day_slots {
             if slots.len() < 2 {}
+            let slots = if is_teacher {
+                window_slots(inst, id, slots, |k| occ.teacher.contains(&(id, k)))
+            } else {
+                slots.clone()
+            };
+            let slots = &slots;
             let mut sum_o = 0i64;
             let mut sum_adj = 0i64;
             for &k in slots {
-                let occ = if is_teacher {
-                    *occ_teacher.get(&(id, k)).unwrap_or(&false)
+                let busy = if is_teacher {
+                    occ.teacher.contains(&(id, k))
                 } else {
-                    *occ_group.get(&(id, k)).unwrap_or(&false)
+                    occ.group.contains(&(id, k))
                 };
-                if occ {
+                if busy {
                     sum_o += 1;
                 }
             }
@@ -118,14 +184,14 @@ pub fn compute_soft_scores(inst: &Instance, assignments: &[Assignment]) -> Score
                 let k = w[0];
                 let k1 = w[1];
                 let occ_k = if is_teacher {
-                    *occ_teacher.get(&(id, k)).unwrap_or(&false)
+                    occ.teacher.contains(&(id, k))
                 } else {
-                    *occ_group.get(&(id, k)).unwrap_or(&false)
+                    occ.group.contains(&(id, k))
                 };
                 let occ_k1 = if is_teacher {
-                    *occ_teacher.get(&(id, k1)).unwrap_or(&false)
+                    occ.teacher.contains(&(id, k1))
                 } else {
-                    *occ_group.get(&(id, k1)).unwrap_or(&false)
+                    occ.group.contains(&(id, k1))
                 };
                 if occ_k && occ_k1 {
                     sum_adj += 1;
@@ -152,15 +218,1096 @@ pub fn compute_soft_scores(inst: &Instance, assignments: &[Assignment]) -> Score
     let windows_total: i64 =
         windows_teachers.values().sum::<i64>() + windows_groups.values().sum::<i64>();
 
-    let w_unpref = inst.policy.soft_weights.unpreferred_time as f64;
-    let w_windows = inst.policy.soft_weights.windows as f64;
-    let objective = w_unpref * (unpref as f64) + w_windows * (windows_total as f64);
+    let load_imbalance = load_deviation(&teacher_loads);
+
+    let active_days = |busy: &dyn Fn(usize) -> bool| -> i64 {
+        day_slots
+            .values()
+            .filter(|slots| slots.iter().any(|&k| busy(k)))
+            .count() as i64
+    };
+    let mut active_days_teachers: HashMap<String, i64> = HashMap::new();
+    for &tid in &teacher_ids {
+        let n = active_days(&|k| occ.teacher.contains(&(tid, k)));
+        if n != 0 {
+            active_days_teachers.insert(tid.to_string(), n);
+        }
+    }
+    let mut active_days_groups: HashMap<String, i64> = HashMap::new();
+    for &gid in &group_ids {
+        let n = active_days(&|k| occ.group.contains(&(gid, k)));
+        if n != 0 {
+            active_days_groups.insert(gid.to_string(), n);
+        }
+    }
+    let active_days_total: i64 =
+        active_days_teachers.values().sum::<i64>() + active_days_groups.values().sum::<i64>();
+
+    let mut group_day_spans: HashMap<String, HashMap<String, i64>> = HashMap::new();
+    for &gid in &group_ids {
+        for (&day, slots) in &day_slots {
+            let occupied: Vec<usize> = slots
+                .iter()
+                .enumerate()
+                .filter(|&(_, &k)| occ.group.contains(&(gid, k)))
+                .map(|(p, _)| p)
+                .collect();
+            if let (Some(&first), Some(&last)) = (occupied.first(), occupied.last()) {
+                if last > first {
+                    group_day_spans
+                        .entry(gid.to_string())
+                        .or_default()
+                        .insert(day.to_string(), (last - first) as i64);
+                }
+            }
+        }
+    }
+    let spans_total: i64 = group_day_spans.values().flat_map(|d| d.values()).sum();
+
+    let mut rooms_of_course: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for a in assignments {
+        if course_by_id.contains_key(a.courseId.0.as_str()) {
+            rooms_of_course
+                .entry(a.courseId.0.as_str())
+                .or_default()
+                .insert(a.roomId.0.as_str());
+        }
+    }
+    let course_room_spread: HashMap<String, i64> = rooms_of_course
+        .into_iter()
+        .filter(|(_, rooms)| rooms.len() > 1)
+        .map(|(cid, rooms)| (cid.to_string(), rooms.len() as i64 - 1))
+        .collect();
+    let room_spread_total: i64 = course_room_spread.values().sum();
+
+    let mut rooms_of_teacher: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for a in assignments {
+        if course_by_id.contains_key(a.courseId.0.as_str()) {
+            rooms_of_teacher
+                .entry(a.teacherId.0.as_str())
+                .or_default()
+                .insert(a.roomId.0.as_str());
+        }
+    }
+    let teacher_room_spread: HashMap<String, i64> = rooms_of_teacher
+        .into_iter()
+        .filter(|(_, rooms)| rooms.len() > 1)
+        .map(|(tid, rooms)| (tid.to_string(), rooms.len() as i64 - 1))
+        .collect();
+    let teacher_room_spread_total: i64 = teacher_room_spread.values().sum();
+
+    let mut buildings_of_course: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for a in assignments {
+        let building = rooms_by_id
+            .get(a.roomId.0.as_str())
+            .and_then(|r| r.building.as_deref());
+        if let (true, Some(b)) = (course_by_id.contains_key(a.courseId.0.as_str()), building) {
+            buildings_of_course
+                .entry(a.courseId.0.as_str())
+                .or_default()
+                .insert(b);
+        }
+    }
+    let course_building_spread: HashMap<String, i64> = buildings_of_course
+        .into_iter()
+        .filter(|(_, buildings)| buildings.len() > 1)
+        .map(|(cid, buildings)| (cid.to_string(), buildings.len() as i64 - 1))
+        .collect();
+    let building_spread_total: i64 = course_building_spread.values().sum();
+
+    let mut building_switches_teachers: HashMap<String, i64> = HashMap::new();
+    let mut building_switches_groups: HashMap<String, i64> = HashMap::new();
+    let mut building_switch_distance = 0i64;
+    for s in building_switches(inst, assignments, &day_slots) {
+        let per = if s.teacher {
+            &mut building_switches_teachers
+        } else {
+            &mut building_switches_groups
+        };
+        *per.entry(s.id.to_string()).or_default() += 1;
+        building_switch_distance += s.distance;
+    }
+
+    let rooms_used = assignments
+        .iter()
+        .filter(|a| course_by_id.contains_key(a.courseId.0.as_str()))
+        .map(|a| a.roomId.0.as_str())
+        .collect::<HashSet<_>>()
+        .len() as i64;
+
+    let mut course_spacing: HashMap<String, i64> = HashMap::new();
+    for p in close_meeting_days(inst, assignments, day_slots.len()) {
+        *course_spacing.entry(p.course.to_string()).or_default() += p.short as i64;
+    }
+    let course_spacing_total: i64 = course_spacing.values().sum();
+
+    let weights = crate::presets::effective_weights(&inst.policy);
+    let w_unpref = weights.unpreferred_time as f64;
+    let w_windows_teachers = weights.teacher_windows() as f64;
+    let w_windows_groups = weights.group_windows() as f64;
+    let w_load = weights.load_imbalance as f64;
+    let w_compact = weights.compactness as f64;
+    let w_soft = weights.soft_availability as f64;
+    let w_room = weights.course_room_consistency as f64;
+    let w_teacher_room = weights.teacher_room_spread as f64;
+    let w_away = weights.away_from_home as f64;
+    let away_from_home_total: i64 = away.values().sum();
+    let w_adjacent = weights.course_adjacency as f64;
+    let missed = missed_adjacent_pairs(inst, assignments, &day_slots);
+    let adjacent_pairs_missed = missed.len() as i64;
+    let adjacent_pairs_satisfied = inst.policy.prefer_adjacent.len() as i64 - adjacent_pairs_missed;
+    let w_parity = weights.parity_slot_consistency as f64;
+    let parity_slot_mismatches: i64 = unmatched_parity_meetings(inst, assignments)
+        .iter()
+        .map(|&(_, n)| n)
+        .sum();
+    let w_active = weights.active_days as f64;
+    let w_building = weights.course_building_spread as f64;
+    let w_rooms_used = weights.rooms_used as f64;
+    let w_switch = weights.building_switch as f64;
+    let w_spacing = weights.course_spacing as f64;
+    let group_unpreferred_total: i64 = group_unpreferred.values().sum();
+    let components = vec![
+        ObjectiveComponent::new(
+            "unpreferred_time",
+            (unpref + group_unpreferred_total) as f64,
+            w_unpref,
+        ),
+        ObjectiveComponent::new(
+            "windows_teachers",
+            windows_teachers.values().sum::<i64>() as f64,
+            w_windows_teachers,
+        ),
+        ObjectiveComponent::new(
+            "windows_groups",
+            windows_groups.values().sum::<i64>() as f64,
+            w_windows_groups,
+        ),
+        ObjectiveComponent::new("load_imbalance", load_imbalance, w_load),
+        ObjectiveComponent::new("compactness", spans_total as f64, w_compact),
+        ObjectiveComponent::new("soft_availability", soft_avail as f64, w_soft),
+        ObjectiveComponent::new("course_room_consistency", room_spread_total as f64, w_room),
+        ObjectiveComponent::new(
+            "teacher_room_spread",
+            teacher_room_spread_total as f64,
+            w_teacher_room,
+        ),
+        ObjectiveComponent::new("away_from_home", away_from_home_total as f64, w_away),
+        ObjectiveComponent::new("course_adjacency", adjacent_pairs_missed as f64, w_adjacent),
+        ObjectiveComponent::new(
+            "parity_slot_consistency",
+            parity_slot_mismatches as f64,
+            w_parity,
+        ),
+        ObjectiveComponent::new("active_days", active_days_total as f64, w_active),
+        ObjectiveComponent::new(
+            "course_building_spread",
+            building_spread_total as f64,
+            w_building,
+        ),
+        ObjectiveComponent::new("rooms_used", rooms_used as f64, w_rooms_used),
+        ObjectiveComponent::new("building_switch", building_switch_distance as f64, w_switch),
+        ObjectiveComponent::new("course_spacing", course_spacing_total as f64, w_spacing),
+    ];
+    let objective = components.iter().map(|c| c.contribution).sum();
 
     Scores {
         unpreferred_meetings: unpref,
+        group_unpreferred,
+        group_unpreferred_total,
+        soft_available_meetings: soft_avail,
         windows_teachers,
         windows_groups,
         windows_total,
+        teacher_loads,
+        load_imbalance,
+        group_day_spans,
+        spans_total,
+        course_room_spread,
+        room_spread_total,
+        teacher_room_spread,
+        teacher_room_spread_total,
+        away_from_home: away,
+        away_from_home_total,
+        adjacent_pairs_satisfied,
+        adjacent_pairs_missed,
+        parity_slot_mismatches,
+        active_days_teachers,
+        active_days_groups,
+        active_days_total,
+        course_building_spread,
+        building_spread_total,
+        building_switches_teachers,
+        building_switches_groups,
+        building_switch_distance,
+        rooms_used,
+        course_spacing,
+        course_spacing_total,
+        components,
         objective,
     }
 }
+
+/// Indices into `policy.prefer_adjacent` of pairs whose second course never
+/// starts in the slot right after a meeting of the first one ends.
+fn missed_adjacent_pairs(
+    inst: &Instance,
+    assignments: &[Assignment],
+    day_slots: &BTreeMap<&str, Vec<usize>>,
+) -> Vec<usize> {
+    let pairs = &inst.policy.prefer_adjacent;
+    if pairs.is_empty() {
+        return Vec::new();
+    }
+    let next: HashMap<usize, usize> = day_slots
+        .values()
+        .flat_map(|slots| slots.windows(2).map(|w| (w[0], w[1])))
+        .collect();
+    let ts_index: HashMap<&str, usize> = inst
+        .timeslots
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.0.as_str(), i))
+        .collect();
+    let mut ends: HashMap<&str, HashSet<usize>> = HashMap::new();
+    let mut starts: HashMap<&str, HashSet<usize>> = HashMap::new();
+    for a in assignments {
+        let (Some(c), Some(&t0)) = (
+            inst.courses.iter().find(|c| c.id == a.courseId),
+            ts_index.get(a.timeslot.0.as_str()),
+        ) else {
+            continue;
+        };
+        let slots = meeting_slots(inst, t0, c.duration).unwrap_or_else(|| vec![t0]);
+        let cid = c.id.0.as_str();
+        starts.entry(cid).or_default().insert(t0);
+        ends.entry(cid)
+            .or_default()
+            .insert(*slots.last().unwrap_or(&t0));
+    }
+    pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, (a, b))| {
+            let (Some(ends), Some(starts)) = (ends.get(a.0.as_str()), starts.get(b.0.as_str()))
+            else {
+                return true;
+            };
+            !ends
+                .iter()
+                .any(|e| next.get(e).is_some_and(|k| starts.contains(k)))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Days apart a course's meetings should fall: the grid's days shared
+/// evenly among its meetings, rounded up, so over Mon–Fri a twice-weekly
+/// course wants three (Mon/Thu) and a thrice-weekly one two.
+pub fn ideal_day_gap(grid_days: usize, meetings: u32) -> u32 {
+    (grid_days as u32).div_ceil(meetings.max(1))
+}
+
+/// Two distinct days course `course` meets on, `short` days closer than
+/// [`ideal_day_gap`]; `later` is its first meeting on the later day.
+struct CloseDays<'a> {
+    course: &'a str,
+    later: usize,
+    short: u32,
+}
+
+/// Every pair of days some multi-meeting course meets on closer than its
+/// ideal gap. Meetings sharing a day count as one day.
+fn close_meeting_days<'a>(
+    inst: &'a Instance,
+    assignments: &[Assignment],
+    grid_days: usize,
+) -> Vec<CloseDays<'a>> {
+    let mut out = Vec::new();
+    for c in inst.courses.iter().filter(|c| c.required_meetings() > 1) {
+        let ideal = ideal_day_gap(grid_days, c.required_meetings());
+        let mut first_on: BTreeMap<DayOfWeek, usize> = BTreeMap::new();
+        for (i, a) in assignments.iter().enumerate() {
+            if a.courseId != c.id {
+                continue;
+            }
+            if let Some((day, _, _)) = a.timeslot.parse() {
+                first_on.entry(day).or_insert(i);
+            }
+        }
+        let days: Vec<(DayOfWeek, usize)> = first_on.into_iter().collect();
+        for (x, &(d0, _)) in days.iter().enumerate() {
+            for &(d1, later) in &days[x + 1..] {
+                let gap = d1 as u32 - d0 as u32;
+                if gap < ideal {
+                    out.push(CloseDays {
+                        course: c.id.0.as_str(),
+                        later,
+                        short: ideal - gap,
+                    });
+                }
+            }
+        }
+    }
+    out
+}
+
+/// A teacher's or group's move between buildings from one slot to the next.
+struct BuildingSwitch<'a> {
+    teacher: bool,
+    id: &'a str,
+    /// Index of the assignment moved into.
+    into: usize,
+    distance: i64,
+}
+
+/// Back-to-back slots of one teacher or group whose meetings are in rooms
+/// of different buildings. Rooms without a building never switch.
+fn building_switches<'a>(
+    inst: &'a Instance,
+    assignments: &'a [Assignment],
+    day_slots: &BTreeMap<&str, Vec<usize>>,
+) -> Vec<BuildingSwitch<'a>> {
+    let ts_index: HashMap<&str, usize> = inst
+        .timeslots
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.0.as_str(), i))
+        .collect();
+    let mut teacher_at: HashMap<(&str, usize), (usize, &str)> = HashMap::new();
+    let mut group_at: HashMap<(&str, usize), (usize, &str)> = HashMap::new();
+    for (i, a) in assignments.iter().enumerate() {
+        let (Some(c), Some(&t0), Some(b)) = (
+            inst.courses.iter().find(|c| c.id == a.courseId),
+            ts_index.get(a.timeslot.0.as_str()),
+            inst.rooms
+                .iter()
+                .find(|r| r.id == a.roomId)
+                .and_then(|r| r.building.as_deref()),
+        ) else {
+            continue;
+        };
+        for k in meeting_slots(inst, t0, c.duration).unwrap_or_else(|| vec![t0]) {
+            teacher_at.insert((a.teacherId.0.as_str(), k), (i, b));
+            group_at.insert((c.groupId.0.as_str(), k), (i, b));
+        }
+    }
+    let mut out = Vec::new();
+    for (teacher, at) in [(true, &teacher_at), (false, &group_at)] {
+        let mut ids: Vec<&str> = at.keys().map(|&(id, _)| id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        for id in ids {
+            for w in day_slots.values().flat_map(|slots| slots.windows(2)) {
+                let (Some(&(_, from)), Some(&(into, to))) =
+                    (at.get(&(id, w[0])), at.get(&(id, w[1])))
+                else {
+                    continue;
+                };
+                if from != to {
+                    out.push(BuildingSwitch {
+                        teacher,
+                        id,
+                        into,
+                        distance: inst.policy.building_distance(from, to) as i64,
+                    });
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Odd/even course pairs sharing group, teacher and duration: the two halves
+/// of one biweekly course, expected to meet in the same slots.
+pub fn parity_pairs(inst: &Instance) -> Vec<(usize, usize)> {
+    let of = |parity: Parity| {
+        inst.courses
+            .iter()
+            .enumerate()
+            .filter(move |(_, c)| c.parity() == parity)
+    };
+    of(Parity::Odd)
+        .flat_map(|(a, ca)| {
+            of(Parity::Even)
+                .filter(move |(_, cb)| {
+                    ca.groupId == cb.groupId
+                        && ca.teacherId == cb.teacherId
+                        && ca.duration == cb.duration
+                })
+                .map(move |(b, _)| (a, b))
+        })
+        .collect()
+}
+
+/// Per slot and parity pair, the meetings of one course left over after
+/// matching them with the other's: `(index of the first leftover
+/// assignment, leftover count)`.
+fn unmatched_parity_meetings(inst: &Instance, assignments: &[Assignment]) -> Vec<(usize, i64)> {
+    let pairs = parity_pairs(inst);
+    if pairs.is_empty() {
+        return Vec::new();
+    }
+    let course_index: HashMap<&str, usize> = inst
+        .courses
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.id.0.as_str(), i))
+        .collect();
+    let ts_index: HashMap<&str, usize> = inst
+        .timeslots
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.0.as_str(), i))
+        .collect();
+    let mut starts: HashMap<usize, BTreeMap<usize, Vec<usize>>> = HashMap::new();
+    for (i, a) in assignments.iter().enumerate() {
+        let (Some(&ci), Some(&t)) = (
+            course_index.get(a.courseId.0.as_str()),
+            ts_index.get(a.timeslot.0.as_str()),
+        ) else {
+            continue;
+        };
+        starts.entry(ci).or_default().entry(t).or_default().push(i);
+    }
+    let none = BTreeMap::new();
+    let mut out = Vec::new();
+    for (a, b) in pairs {
+        let sa = starts.get(&a).unwrap_or(&none);
+        let sb = starts.get(&b).unwrap_or(&none);
+        let slots: BTreeSet<usize> = sa.keys().chain(sb.keys()).copied().collect();
+        for t in slots {
+            let na = sa.get(&t).map_or(&[][..], Vec::as_slice);
+            let nb = sb.get(&t).map_or(&[][..], Vec::as_slice);
+            let (more, less) = if na.len() > nb.len() {
+                (na, nb)
+            } else {
+                (nb, na)
+            };
+            if more.len() > less.len() {
+                out.push((more[less.len()], (more.len() - less.len()) as i64));
+            }
+        }
+    }
+    out
+}
+
+/// Whether a meeting of `group` in `room` is outside the group's home
+/// building. Groups without a home and rooms without a building are neutral.
+pub fn away_from_home(group: &Group, room: &Room) -> bool {
+    match (&group.home_building, &room.building) {
+        (Some(home), Some(b)) => home != b,
+        _ => false,
+    }
+}
+
+/// The slots of one day (`slots`) that count towards `teacher`'s windows:
+/// all of them, or with `Policy.windows_within_availability` those the
+/// teacher is available in or `busy` at.
+pub fn window_slots(
+    inst: &Instance,
+    teacher: &str,
+    slots: &[usize],
+    busy: impl Fn(usize) -> bool,
+) -> Vec<usize> {
+    let t = inst
+        .policy
+        .windows_within_availability
+        .then(|| inst.teachers.iter().find(|t| t.id.0 == teacher))
+        .flatten();
+    let Some(t) = t else {
+        return slots.to_vec();
+    };
+    slots
+        .iter()
+        .copied()
+        .filter(|&k| busy(k) || slot_allowed(t, &inst.timeslots[k].0, &inst.policy))
+        .collect()
+}
+
+/// Slot indices per day, ordered by period and sub-slot within the day;
+/// days iterate in a fixed order so float sums over them are reproducible.
+pub(crate) fn day_slots<'a>(times: &[&'a str]) -> BTreeMap<&'a str, Vec<usize>> {
+    let mut day_of: Vec<&str> = Vec::with_capacity(times.len());
+    let mut day_index: Vec<(u32, u32)> = Vec::with_capacity(times.len());
+    for &ts in times {
+        let parsed = TimeslotId::parse_str(ts);
+        day_of.push(parsed.map_or(ts, |(day, _, _)| day.abbrev()));
+        day_index.push(parsed.map_or((0, 0), |(_, idx, sub)| (idx, sub)));
+    }
+    let mut day_slots: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for k in 0..times.len() {
+        day_slots.entry(day_of[k]).or_default().push(k);
+    }
+    for v in day_slots.values_mut() {
+        v.sort_by_key(|&k| day_index[k]);
+    }
+    day_slots
+}
+
+/// Splits the weighted objective of `compute_soft_scores` over the meetings
+/// responsible for it: an avoided or soft-available slot is charged to its
+/// meeting, each teacher/group block of a day (the windows term) to the
+/// meeting that opens it, a group's daily span to the day's last meeting and
+/// a teacher's load deviation evenly over their meetings, and each extra
+/// room of a course or teacher, and each extra building of a course, to the
+/// first such meeting held there, each used room to its first meeting, a
+/// building switch to the meeting moved into, a missed `prefer_adjacent` pair to its
+/// leading course's first meeting, unmatched odd/even meetings to the
+/// leftover meetings, each active day of a teacher or group to its first
+/// meeting that day and a course's too-close pair of days to its first
+/// meeting on the later one. Deviation of teachers with no meetings belongs to
+/// nobody and is left out.
+pub fn compute_scores_attributed(
+    inst: &Instance,
+    assignments: &[Assignment],
+) -> Vec<(Assignment, f64)> {
+    let times: Vec<&str> = inst.timeslots.iter().map(|t| t.0.as_str()).collect();
+    let day_slots = day_slots(&times);
+    let weights = crate::presets::effective_weights(&inst.policy);
+    let w_unpref = weights.unpreferred_time as f64;
+    let w_windows_teachers = weights.teacher_windows() as f64;
+    let w_windows_groups = weights.group_windows() as f64;
+    let w_load = weights.load_imbalance as f64;
+    let w_compact = weights.compactness as f64;
+    let w_soft = weights.soft_availability as f64;
+    let w_room = weights.course_room_consistency as f64;
+    let w_teacher_room = weights.teacher_room_spread as f64;
+    let w_away = weights.away_from_home as f64;
+    let w_adjacent = weights.course_adjacency as f64;
+    let w_parity = weights.parity_slot_consistency as f64;
+    let w_active = weights.active_days as f64;
+    let w_building = weights.course_building_spread as f64;
+    let w_rooms_used = weights.rooms_used as f64;
+    let w_switch = weights.building_switch as f64;
+    let w_spacing = weights.course_spacing as f64;
+
+    let course_by_id: HashMap<&str, &Course> =
+        inst.courses.iter().map(|c| (c.id.0.as_str(), c)).collect();
+    let teachers_by_id: HashMap<&str, &Teacher> =
+        inst.teachers.iter().map(|t| (t.id.0.as_str(), t)).collect();
+    let groups_by_id: HashMap<&str, &Group> =
+        inst.groups.iter().map(|g| (g.id.0.as_str(), g)).collect();
+    let ts_index: HashMap<&str, usize> = times.iter().enumerate().map(|(i, &t)| (t, i)).collect();
+
+    let mut share = vec![0.0f64; assignments.len()];
+    let mut teacher_at: HashMap<(&str, usize), usize> = HashMap::new();
+    let mut group_at: HashMap<(&str, usize), usize> = HashMap::new();
+    let mut meetings_of: HashMap<&str, Vec<usize>> = HashMap::new();
+
+    for (i, a) in assignments.iter().enumerate() {
+        let (Some(c), Some(&t0)) = (
+            course_by_id.get(a.courseId.0.as_str()),
+            ts_index.get(a.timeslot.0.as_str()),
+        ) else {
+            continue;
+        };
+        let slots = meeting_slots(inst, t0, c.duration).unwrap_or_else(|| vec![t0]);
+        let tid = a.teacherId.0.as_str();
+        meetings_of.entry(tid).or_default().push(i);
+
+        if let Some(t) = teachers_by_id.get(tid) {
+            if slots
+                .iter()
+                .any(|&k| t.prefs.avoid_slots.iter().any(|x| x.0 == times[k]))
+            {
+                share[i] += w_unpref;
+            }
+            if slots.iter().any(|&k| slot_is_soft(t, times[k])) {
+                share[i] += w_soft;
+            }
+        }
+        if let Some(g) = groups_by_id.get(c.groupId.0.as_str()) {
+            if slots.iter().any(|&k| group_avoids(g, times[k])) {
+                share[i] += w_unpref;
+            }
+            if let Some(room) = inst.rooms.iter().find(|r| r.id == a.roomId) {
+                if away_from_home(g, room) {
+                    share[i] += w_away;
+                }
+            }
+        }
+        for &k in &slots {
+            teacher_at.insert((a.teacherId.0.as_str(), k), i);
+            group_at.insert((c.groupId.0.as_str(), k), i);
+        }
+    }
+
+    let mut charge_blocks =
+        |at: &HashMap<(&str, usize), usize>, id: &str, w_windows: f64, is_teacher: bool| {
+            for slots in day_slots.values() {
+                if let Some(&i) = slots.iter().find_map(|&k| at.get(&(id, k))) {
+                    share[i] += w_active;
+                }
+                let window_slots = if is_teacher {
+                    window_slots(inst, id, slots, |k| at.contains_key(&(id, k)))
+                } else {
+                    slots.clone()
+                };
+                let mut prev = false;
+                for &k in &window_slots {
+                    match at.get(&(id, k)) {
+                        Some(&i) => {
+                            if !prev {
+                                share[i] += w_windows;
+                            }
+                            prev = true;
+                        }
+                        None => prev = false,
+                    }
+                }
+            }
+        };
+    for t in &inst.teachers {
+        charge_blocks(&teacher_at, t.id.0.as_str(), w_windows_teachers, true);
+    }
+    for g in &inst.groups {
+        charge_blocks(&group_at, g.id.0.as_str(), w_windows_groups, false);
+    }
+
+    for g in &inst.groups {
+        let gid = g.id.0.as_str();
+        for slots in day_slots.values() {
+            let occupied: Vec<usize> = slots
+                .iter()
+                .enumerate()
+                .filter(|&(_, &k)| group_at.contains_key(&(gid, k)))
+                .map(|(p, _)| p)
+                .collect();
+            if let (Some(&first), Some(&last)) = (occupied.first(), occupied.last()) {
+                if last > first {
+                    share[group_at[&(gid, slots[last])]] += w_compact * (last - first) as f64;
+                }
+            }
+        }
+    }
+
+    if !inst.teachers.is_empty() {
+        let total: usize = inst
+            .teachers
+            .iter()
+            .map(|t| meetings_of.get(t.id.0.as_str()).map_or(0, Vec::len))
+            .sum();
+        let mean = total as f64 / inst.teachers.len() as f64;
+        for t in &inst.teachers {
+            let Some(mine) = meetings_of.get(t.id.0.as_str()) else {
+                continue;
+            };
+            let dev = (mine.len() as f64 - mean).abs();
+            for &i in mine {
+                share[i] += w_load * dev / mine.len() as f64;
+            }
+        }
+    }
+
+    let mut seen_rooms: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut seen_teacher_rooms: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut seen_buildings: HashMap<&str, HashSet<&str>> = HashMap::new();
+    let mut rooms_in_use: HashSet<&str> = HashSet::new();
+    for (i, a) in assignments.iter().enumerate() {
+        if !course_by_id.contains_key(a.courseId.0.as_str()) {
+            continue;
+        }
+        let rooms = seen_rooms.entry(a.courseId.0.as_str()).or_default();
+        if rooms.insert(a.roomId.0.as_str()) && rooms.len() > 1 {
+            share[i] += w_room;
+        }
+        if rooms_in_use.insert(a.roomId.0.as_str()) {
+            share[i] += w_rooms_used;
+        }
+        let rooms = seen_teacher_rooms
+            .entry(a.teacherId.0.as_str())
+            .or_default();
+        if rooms.insert(a.roomId.0.as_str()) && rooms.len() > 1 {
+            share[i] += w_teacher_room;
+        }
+        let building = inst
+            .rooms
+            .iter()
+            .find(|r| r.id == a.roomId)
+            .and_then(|r| r.building.as_deref());
+        if let Some(b) = building {
+            let buildings = seen_buildings.entry(a.courseId.0.as_str()).or_default();
+            if buildings.insert(b) && buildings.len() > 1 {
+                share[i] += w_building;
+            }
+        }
+    }
+
+    for s in building_switches(inst, assignments, &day_slots) {
+        share[s.into] += w_switch * s.distance as f64;
+    }
+
+    for p in close_meeting_days(inst, assignments, day_slots.len()) {
+        share[p.later] += w_spacing * p.short as f64;
+    }
+
+    // A missed pair is charged to the first meeting of its leading course.
+    for p in missed_adjacent_pairs(inst, assignments, &day_slots) {
+        let lead = &inst.policy.prefer_adjacent[p].0;
+        if let Some(i) = assignments.iter().position(|a| a.courseId == *lead) {
+            share[i] += w_adjacent;
+        }
+    }
+
+    for (i, n) in unmatched_parity_meetings(inst, assignments) {
+        share[i] += w_parity * n as f64;
+    }
+
+    assignments.iter().cloned().zip(share).collect()
+}
+
+/// Sum of absolute deviations of per-teacher meeting counts from their mean.
+pub fn load_deviation(loads: &HashMap<String, i64>) -> f64 {
+    if loads.is_empty() {
+        return 0.0;
+    }
+    let mean = loads.values().sum::<i64>() as f64 / loads.len() as f64;
+    // Sum in a fixed order; HashMap order would make the last bits vary.
+    let mut sorted: Vec<i64> = loads.values().copied().collect();
+    sorted.sort_unstable();
+    sorted.iter().map(|&l| (l as f64 - mean).abs()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::{RoomId, SoftWeights, SolveEnvelope};
+
+    fn small() -> Instance {
+        let env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+        env.instance
+    }
+
+    fn meet(inst: &Instance, course: &str, ts: &str, room: &str) -> Assignment {
+        let c = inst.courses.iter().find(|c| c.id.0 == course).unwrap();
+        Assignment {
+            courseId: c.id.clone(),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId(room.into()),
+            teacherId: c.teacherId.clone(),
+            meta: None,
+        }
+    }
+
+    fn component(s: &Scores, term: &str) -> f64 {
+        s.components
+            .iter()
+            .find(|c| c.term == term)
+            .map_or(0.0, |c| c.contribution)
+    }
+
+    #[test]
+    fn load_imbalance_is_deviation_from_mean_load() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            load_imbalance: 3,
+            ..Default::default()
+        };
+        let plan = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "tue.1", "r101"),
+        ];
+        let s = compute_soft_scores(&inst, &plan);
+        assert_eq!(s.teacher_loads["t_kovalenko"], 2);
+        assert_eq!(s.teacher_loads["t_petrenko"], 0);
+        assert_eq!(s.load_imbalance, 2.0);
+        assert_eq!(component(&s, "load_imbalance"), 6.0);
+    }
+
+    #[test]
+    fn compactness_sums_first_to_last_distance_per_group_day() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            compactness: 4,
+            ..Default::default()
+        };
+        let plan = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "mon.3", "r101"),
+            meet(&inst, "c_alg", "tue.2", "r101"),
+        ];
+        let s = compute_soft_scores(&inst, &plan);
+        assert_eq!(s.group_day_spans["g1_math_1"]["mon"], 2);
+        assert!(!s.group_day_spans["g1_math_1"].contains_key("tue"));
+        assert_eq!(s.spans_total, 2);
+        assert_eq!(component(&s, "compactness"), 8.0);
+    }
+
+    #[test]
+    fn group_avoided_slot_counts_as_unpreferred_time() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            unpreferred_time: 4,
+            ..Default::default()
+        };
+        inst.groups[0].prefs.avoid_slots = vec![TimeslotId("mon.1".into())];
+        let plan = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "mon.2", "r101"),
+        ];
+        let s = compute_soft_scores(&inst, &plan);
+        assert_eq!(s.group_unpreferred["g1_math_1"], 1);
+        assert_eq!(s.group_unpreferred_total, 1);
+        assert!(!s.group_unpreferred.contains_key("g2_cs_1"));
+        assert_eq!(component(&s, "unpreferred_time"), 4.0);
+    }
+
+    #[test]
+    fn avoided_slot_penalty_is_attributed_to_its_meeting() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            unpreferred_time: 3,
+            ..Default::default()
+        };
+        inst.teachers[0].prefs.avoid_slots = vec![TimeslotId("tue.1".into())];
+        let plan = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "tue.1", "r101"),
+        ];
+        let attributed = compute_scores_attributed(&inst, &plan);
+        assert_eq!(attributed.len(), 2);
+        assert_eq!(attributed[0].0.timeslot.0, "mon.1");
+        assert_eq!(attributed[0].1, 0.0);
+        assert_eq!(attributed[1].0.timeslot.0, "tue.1");
+        assert_eq!(attributed[1].1, 3.0);
+    }
+
+    #[test]
+    fn twice_weekly_course_prefers_a_single_room() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            course_room_consistency: 5,
+            ..Default::default()
+        };
+        let same = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "tue.1", "r101"),
+        ];
+        let split = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "tue.1", "r202"),
+        ];
+        let s = compute_soft_scores(&inst, &same);
+        let t = compute_soft_scores(&inst, &split);
+        assert_eq!(s.course_room_spread.get("c_alg").copied().unwrap_or(0), 0);
+        assert_eq!(t.course_room_spread["c_alg"], 1);
+        assert_eq!(component(&t, "course_room_consistency"), 5.0);
+        assert!(s.objective < t.objective);
+    }
+
+    #[test]
+    fn teacher_room_spread_rewards_single_room_teachers() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            teacher_room_spread: 2,
+            ..Default::default()
+        };
+        let plan = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "tue.1", "r202"),
+            meet(&inst, "c_prog", "mon.1", "r202"),
+            meet(&inst, "c_prog", "tue.2", "r202"),
+        ];
+        let s = compute_soft_scores(&inst, &plan);
+        assert_eq!(s.teacher_room_spread["t_kovalenko"], 1);
+        assert_eq!(
+            s.teacher_room_spread
+                .get("t_petrenko")
+                .copied()
+                .unwrap_or(0),
+            0
+        );
+        assert_eq!(s.teacher_room_spread_total, 1);
+        assert_eq!(component(&s, "teacher_room_spread"), 2.0);
+    }
+
+    #[test]
+    fn objective_is_bit_identical_across_runs() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            unpreferred_time: 3,
+            windows: 5,
+            load_imbalance: 7,
+            compactness: 1,
+            ..Default::default()
+        };
+        let plan = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "tue.2", "r101"),
+            meet(&inst, "c_prog", "mon.2", "r202"),
+            meet(&inst, "c_prog", "tue.1", "r202"),
+            meet(&inst, "c_prog", "thu.1", "r202"),
+        ];
+        // Every run hashes with fresh random keys, so any order that leaked
+        // from a HashMap into the sums would show up here.
+        let first = compute_soft_scores(&inst, &plan).objective.to_bits();
+        for _ in 0..20 {
+            assert_eq!(compute_soft_scores(&inst, &plan).objective.to_bits(), first);
+        }
+    }
+
+    #[test]
+    fn lecture_then_lab_back_to_back_is_rewarded() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            course_adjacency: 4,
+            ..Default::default()
+        };
+        let lecture = inst.courses[0].id.clone();
+        let lab = inst.courses[1].id.clone();
+        inst.policy.prefer_adjacent = vec![(lecture, lab)];
+        let back_to_back = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_prog", "mon.2", "r202"),
+        ];
+        let apart = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_prog", "tue.1", "r202"),
+        ];
+        let s = compute_soft_scores(&inst, &back_to_back);
+        let t = compute_soft_scores(&inst, &apart);
+        assert_eq!(
+            (s.adjacent_pairs_satisfied, s.adjacent_pairs_missed),
+            (1, 0)
+        );
+        assert_eq!(
+            (t.adjacent_pairs_satisfied, t.adjacent_pairs_missed),
+            (0, 1)
+        );
+        assert_eq!(component(&s, "course_adjacency"), 0.0);
+        assert_eq!(component(&t, "course_adjacency"), 4.0);
+        assert!(s.objective < t.objective);
+    }
+
+    #[test]
+    fn matching_slots_across_parities_are_rewarded() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            parity_slot_consistency: 3,
+            ..Default::default()
+        };
+        inst.courses[0].week_parity = Some(Parity::Odd);
+        let mut even = inst.courses[0].clone();
+        even.id = types::CourseId("c_alg_even".into());
+        even.week_parity = Some(Parity::Even);
+        inst.courses.push(even);
+
+        let matched = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg_even", "mon.1", "r101"),
+        ];
+        let shifted = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg_even", "tue.1", "r101"),
+        ];
+        let s = compute_soft_scores(&inst, &matched);
+        let t = compute_soft_scores(&inst, &shifted);
+        assert_eq!(s.parity_slot_mismatches, 0);
+        assert_eq!(t.parity_slot_mismatches, 2);
+        assert_eq!(component(&t, "parity_slot_consistency"), 6.0);
+        assert!(s.objective < t.objective);
+    }
+
+    #[test]
+    fn course_in_two_buildings_pays_its_building_spread() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            course_building_spread: 4,
+            ..Default::default()
+        };
+        inst.rooms[0].building = Some("main".into());
+        inst.rooms[1].building = Some("annex".into());
+        let one = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "tue.1", "r101"),
+        ];
+        let two = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "tue.1", "r202"),
+        ];
+        let s = compute_soft_scores(&inst, &one);
+        let t = compute_soft_scores(&inst, &two);
+        assert!(s.course_building_spread.is_empty());
+        assert_eq!(t.course_building_spread["c_alg"], 1);
+        assert_eq!(component(&t, "course_building_spread"), 4.0);
+        assert!(s.objective < t.objective);
+    }
+
+    #[test]
+    fn long_building_switch_costs_more_than_a_short_one() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            building_switch: 3,
+            ..Default::default()
+        };
+        let mut far = inst.rooms[0].clone();
+        far.id = RoomId("r303".into());
+        far.building = Some("far".into());
+        inst.rooms.push(far);
+        inst.rooms[0].building = Some("main".into());
+        inst.rooms[1].building = Some("annex".into());
+        inst.policy.building_distance =
+            HashMap::from([("main".into(), HashMap::from([("far".into(), 5)]))]);
+        let to = |room: &str| {
+            compute_soft_scores(
+                &inst,
+                &[
+                    meet(&inst, "c_alg", "mon.1", "r101"),
+                    meet(&inst, "c_alg", "mon.2", room),
+                ],
+            )
+        };
+        // Teacher and group both make the move, each paying the distance.
+        let (short, long) = (to("r202"), to("r303"));
+        assert_eq!(short.building_switch_distance, 2);
+        assert_eq!(long.building_switch_distance, 10);
+        assert_eq!(component(&short, "building_switch"), 6.0);
+        assert_eq!(component(&long, "building_switch"), 30.0);
+        assert_eq!(to("r101").building_switch_distance, 0);
+    }
+
+    #[test]
+    fn unavailable_gap_is_not_a_window_when_counting_within_availability() {
+        let mut inst = small();
+        inst.teachers[0].available = vec![TimeslotId("mon.1".into()), TimeslotId("mon.3".into())];
+        let plan = [
+            meet(&inst, "c_alg", "mon.1", "r101"),
+            meet(&inst, "c_alg", "mon.3", "r101"),
+        ];
+        // Over the whole day, the unavailable mon.2 splits two blocks.
+        let s = compute_soft_scores(&inst, &plan);
+        assert_eq!(s.windows_teachers["t_kovalenko"], 2);
+
+        inst.policy.windows_within_availability = true;
+        let s = compute_soft_scores(&inst, &plan);
+        assert_eq!(s.windows_teachers["t_kovalenko"], 1);
+        // Groups still count over the whole day.
+        assert_eq!(s.windows_groups["g1_math_1"], 2);
+    }
+
+    #[test]
+    fn monday_thursday_beats_monday_tuesday() {
+        let mut inst = small();
+        inst.policy.soft_weights = SoftWeights {
+            course_spacing: 2,
+            ..Default::default()
+        };
+        let on = |second: &str| {
+            compute_soft_scores(
+                &inst,
+                &[
+                    meet(&inst, "c_alg", "mon.1", "r101"),
+                    meet(&inst, "c_alg", second, "r101"),
+                ],
+            )
+        };
+        // Over Mon-Fri a twice-weekly course wants its days three apart.
+        let (tue, thu) = (on("tue.1"), on("thu.1"));
+        assert_eq!(tue.course_spacing["c_alg"], 2);
+        assert_eq!(component(&tue, "course_spacing"), 4.0);
+        assert!(thu.course_spacing.is_empty());
+        assert!(thu.objective < tue.objective);
+    }
+}
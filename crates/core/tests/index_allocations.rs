@@ -0,0 +1,77 @@
+//! Allocation benchmark for sharing one `InstanceIndex` across solver
+//! stages. Lives in its own test binary so the counting allocator does not
+//! replace the allocator of the library unit tests.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use sched_core::index::InstanceIndex;
+use sched_core::SolveEnvelope;
+use types::Instance;
+
+/// Counts allocations made on the current thread, so the measurement is not
+/// skewed by tests running alongside it.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCS: Cell<u64> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocs_during(f: impl FnOnce()) -> u64 {
+    let before = ALLOCS.with(Cell::get);
+    f();
+    ALLOCS.with(Cell::get) - before
+}
+
+/// The lookups a solver stage makes per course: its position, its group's
+/// size, its teacher and the slots of every day.
+fn stage(index: &InstanceIndex, inst: &Instance) -> usize {
+    let mut hits = 0;
+    for c in &inst.courses {
+        hits += index.idx_course[c.id.0.as_str()];
+        hits += index.group_size[c.groupId.0.as_str()] as usize;
+        hits += index.teacher_by_id[c.teacherId.0.as_str()].id.0.len();
+        hits += index.day_slots.values().map(Vec::len).sum::<usize>();
+    }
+    hits
+}
+
+#[test]
+fn shared_index_allocates_less_than_one_per_stage() {
+    let env: SolveEnvelope =
+        serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+    let inst = &env.instance;
+    let stages = 3;
+
+    let per_stage = allocs_during(|| {
+        for _ in 0..stages {
+            std::hint::black_box(stage(&InstanceIndex::new(inst), inst));
+        }
+    });
+    let mut lookups = 0;
+    let shared = allocs_during(|| {
+        let index = InstanceIndex::new(inst);
+        lookups = allocs_during(|| {
+            for _ in 0..stages {
+                std::hint::black_box(stage(&index, inst));
+            }
+        });
+    });
+
+    assert_eq!(lookups, 0);
+    assert!(shared < per_stage);
+}
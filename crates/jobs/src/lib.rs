@@ -1,7 +1,11 @@
-use parking_lot::RwLock;
-use sched_core::{SolveEnvelope, SolveResult, Solver};
-use std::collections::HashMap;
-use tracing::error;
+use parking_lot::{Mutex, RwLock};
+use sched_core::{CancelToken, SolveEnvelope, SolveResult, Solver};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use tokio::sync::Semaphore;
+use tracing::{error, warn};
+use types::Violation;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -13,53 +17,133 @@ pub struct JobId(pub String);
 pub enum JobStatus {
     Queued,
     Running,
-    Solved { result: SolveResult },
-    Infeasible,
-    Failed { message: String },
+    Solved {
+        result: SolveResult,
+    },
+    /// The solver gave up; `result` is the partial schedule it returned and
+    /// `violations` lists what is still wrong with it (unplaced meetings,
+    /// clashing pins, ...).
+    Infeasible {
+        reason: String,
+        violations: Vec<Violation>,
+        result: SolveResult,
+    },
+    /// A `timeLimitSec` budget expired before the solver found a schedule;
+    /// the instance may still be feasible, so a retry with a larger
+    /// `timeLimitSec` can help. Solvers that ignore the limit never report it.
+    TimedOut {
+        result: SolveResult,
+    },
+    Failed {
+        message: String,
+    },
+    /// Stopped on request; `result` is the best schedule found before the
+    /// solver noticed, absent when the job never started.
+    Cancelled {
+        result: Option<SolveResult>,
+    },
 }
 
 #[derive(Clone)]
 pub struct InMemJobs<S: Solver> {
     inner: std::sync::Arc<RwLock<HashMap<String, JobStatus>>>,
     solver: std::sync::Arc<S>,
+    /// One permit per solve allowed to run at once; jobs wait `Queued` for one.
+    permits: std::sync::Arc<Semaphore>,
+    queue: std::sync::Arc<Mutex<BinaryHeap<Pending>>>,
+    submitted: std::sync::Arc<AtomicU64>,
+    /// Tokens of jobs that are queued or running.
+    cancels: std::sync::Arc<RwLock<HashMap<String, CancelToken>>>,
+    /// Submitted envelope of every job, for resubmission.
+    envelopes: std::sync::Arc<RwLock<HashMap<String, SolveEnvelope>>>,
+}
+
+/// A queued job. The heap pops the highest `priority` first and, among
+/// equals, the earliest submission.
+struct Pending {
+    priority: i32,
+    seq: u64,
+    id: String,
+    env: SolveEnvelope,
+    cancel: CancelToken,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
 }
 
 impl<S: Solver> InMemJobs<S> {
+    /// Runs as many solves at once as the machine has cores.
     pub fn new(solver: S) -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_max_concurrency(solver, cores)
+    }
+
+    /// At most `max` solves run concurrently (at least one).
+    pub fn with_max_concurrency(solver: S, max: usize) -> Self {
         Self {
             inner: Default::default(),
             solver: std::sync::Arc::new(solver),
+            permits: std::sync::Arc::new(Semaphore::new(max.max(1))),
+            queue: Default::default(),
+            submitted: Default::default(),
+            cancels: Default::default(),
+            envelopes: Default::default(),
         }
     }
 
+    /// Queues `env` by its `priority`. Every submission spawns a worker that
+    /// waits for a permit and then runs whichever queued job ranks highest,
+    /// not necessarily its own.
     pub fn enqueue(&self, env: SolveEnvelope) -> JobId {
         let id = Uuid::new_v4().to_string();
+        let cancel = CancelToken::new();
         self.inner.write().insert(id.clone(), JobStatus::Queued);
+        self.cancels.write().insert(id.clone(), cancel.clone());
+        self.envelopes.write().insert(id.clone(), env.clone());
+        self.queue.lock().push(Pending {
+            priority: env.priority.unwrap_or(0),
+            seq: self.submitted.fetch_add(1, AtomicOrdering::Relaxed),
+            id: id.clone(),
+            env,
+            cancel,
+        });
 
         let map = self.inner.clone();
         let solver = self.solver.clone();
-        let id_for_task = id.clone();
+        let permits = self.permits.clone();
+        let queue = self.queue.clone();
+        let cancels = self.cancels.clone();
 
         tokio::spawn(async move {
-            {
-                let mut w = map.write();
-                w.insert(id_for_task.clone(), JobStatus::Running);
-            }
-            match solver.solve(env).await {
-                Ok(res) => {
-                    map.write()
-                        .insert(id_for_task, JobStatus::Solved { result: res });
-                }
-                Err(e) => {
-                    error!(?e, "job failed");
-                    map.write().insert(
-                        id_for_task,
-                        JobStatus::Failed {
-                            message: e.to_string(),
-                        },
-                    );
-                }
-            }
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .expect("job semaphore is never closed");
+            let Some(job) = queue.lock().pop() else {
+                return;
+            };
+            let id = job.id.clone();
+            run(&map, solver.as_ref(), job).await;
+            cancels.write().remove(&id);
         });
 
         JobId(id)
@@ -68,4 +152,234 @@ impl<S: Solver> InMemJobs<S> {
     pub fn get(&self, id: &str) -> Option<JobStatus> {
         self.inner.read().get(id).cloned()
     }
+
+    /// The envelope job `id` was submitted with.
+    pub fn envelope(&self, id: &str) -> Option<SolveEnvelope> {
+        self.envelopes.read().get(id).cloned()
+    }
+
+    /// Asks a queued or running job to stop. A queued job is marked
+    /// `Cancelled` right away and never starts; a running one keeps its
+    /// status until the solver returns. `false` if the job is unknown or
+    /// already finished.
+    pub fn cancel(&self, id: &str) -> bool {
+        let Some(token) = self.cancels.read().get(id).cloned() else {
+            return false;
+        };
+        token.cancel();
+        let mut map = self.inner.write();
+        if let Some(st @ JobStatus::Queued) = map.get_mut(id) {
+            *st = JobStatus::Cancelled { result: None };
+        }
+        true
+    }
+}
+
+async fn run<S: Solver>(map: &RwLock<HashMap<String, JobStatus>>, solver: &S, job: Pending) {
+    let Pending {
+        id, env, cancel, ..
+    } = job;
+    {
+        let mut w = map.write();
+        if matches!(w.get(&id), Some(JobStatus::Cancelled { .. })) {
+            return;
+        }
+        w.insert(id.clone(), JobStatus::Running);
+    }
+    let inst = env.instance.clone();
+    let pins = env.partial_pins.clone();
+    match solver.solve_cancellable(env, cancel).await {
+        Ok(res) if res.status == "cancelled" => {
+            map.write()
+                .insert(id, JobStatus::Cancelled { result: Some(res) });
+        }
+        Ok(res) if res.status == "infeasible" => {
+            let reason = infeasible_reason(&res);
+            let violations = if res.violations.is_empty() {
+                sched_core::feasibility::all_hard_violations(&inst, &res.assignments, &pins)
+            } else {
+                res.violations.clone()
+            };
+            warn!(job = %id, %reason, violations = violations.len(), "job infeasible");
+            map.write().insert(
+                id,
+                JobStatus::Infeasible {
+                    reason,
+                    violations,
+                    result: res,
+                },
+            );
+        }
+        Ok(res) if res.status == "timeout" => {
+            warn!(job = %id, "job timed out without a schedule");
+            map.write().insert(id, JobStatus::TimedOut { result: res });
+        }
+        Ok(res) => {
+            map.write().insert(id, JobStatus::Solved { result: res });
+        }
+        Err(e) => {
+            error!(?e, "job failed");
+            map.write().insert(
+                id,
+                JobStatus::Failed {
+                    message: e.to_string(),
+                },
+            );
+        }
+    }
+}
+
+fn infeasible_reason(res: &SolveResult) -> String {
+    ["infeasible_cause", "note"]
+        .iter()
+        .find_map(|k| res.stats.get(*k).and_then(|v| v.as_str()))
+        .unwrap_or("no feasible schedule found")
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use types::{Assignment, CourseId, RoomId, TeacherId, TimeslotId};
+
+    fn small() -> SolveEnvelope {
+        serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap()
+    }
+
+    /// Answers every solve with the same result.
+    struct Fixed(SolveResult);
+
+    #[async_trait]
+    impl Solver for Fixed {
+        async fn solve(&self, _env: SolveEnvelope) -> anyhow::Result<SolveResult> {
+            Ok(self.0.clone())
+        }
+    }
+
+    async fn finished<S: Solver>(jobs: &InMemJobs<S>, id: &JobId) -> JobStatus {
+        for _ in 0..500 {
+            match jobs.get(&id.0) {
+                Some(JobStatus::Queued | JobStatus::Running) | None => {
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await
+                }
+                Some(st) => return st,
+            }
+        }
+        panic!("job {} did not finish", id.0);
+    }
+
+    fn alg(ts: &str) -> Assignment {
+        Assignment {
+            courseId: CourseId("c_alg".into()),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId("r101".into()),
+            teacherId: TeacherId("t_kovalenko".into()),
+            meta: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn infeasible_job_carries_reason_and_violations() {
+        // Both meetings of c_alg squeezed into one slot: the best an
+        // over-constrained solve could do.
+        let jobs = InMemJobs::new(Fixed(SolveResult {
+            status: "infeasible".into(),
+            objective: 0.0,
+            assignments: vec![alg("mon.1"), alg("mon.1")],
+            violations: vec![],
+            stats: serde_json::json!({"note": "pinned assignments clash"}),
+        }));
+        let id = jobs.enqueue(small());
+        let JobStatus::Infeasible {
+            reason,
+            violations,
+            result,
+        } = finished(&jobs, &id).await
+        else {
+            panic!("expected an infeasible job");
+        };
+        assert_eq!(reason, "pinned assignments clash");
+        assert_eq!(result.assignments.len(), 2);
+        assert!(!violations.is_empty());
+        assert!(violations.iter().any(|v| v.r#type.contains("clash")));
+    }
+
+    /// Holds every solve until the test hands out permits, noting the
+    /// priority of each envelope as its solve starts.
+    struct Gate {
+        permits: std::sync::Arc<Semaphore>,
+        started: std::sync::Arc<parking_lot::Mutex<Vec<i32>>>,
+    }
+
+    impl Gate {
+        fn closed() -> Self {
+            Self {
+                permits: std::sync::Arc::new(Semaphore::new(0)),
+                started: Default::default(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Solver for Gate {
+        async fn solve(&self, env: SolveEnvelope) -> anyhow::Result<SolveResult> {
+            self.started.lock().push(env.priority.unwrap_or(0));
+            self.permits.acquire().await?.forget();
+            Ok(SolveResult {
+                status: "solved".into(),
+                objective: 0.0,
+                assignments: vec![],
+                violations: vec![],
+                stats: serde_json::json!({}),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn job_beyond_the_concurrency_limit_stays_queued() {
+        let gate = Gate::closed();
+        let permits = gate.permits.clone();
+        let jobs = InMemJobs::with_max_concurrency(gate, 2);
+        let ids: Vec<JobId> = (0..3).map(|_| jobs.enqueue(small())).collect();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let statuses: Vec<JobStatus> = ids.iter().map(|id| jobs.get(&id.0).unwrap()).collect();
+        let running = statuses
+            .iter()
+            .filter(|st| matches!(st, JobStatus::Running))
+            .count();
+        assert_eq!(running, 2);
+        assert!(matches!(statuses[2], JobStatus::Queued));
+
+        permits.add_permits(3);
+        for id in &ids {
+            assert!(matches!(
+                finished(&jobs, id).await,
+                JobStatus::Solved { .. }
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn high_priority_job_overtakes_an_earlier_low_priority_one() {
+        let gate = Gate::closed();
+        let (permits, started) = (gate.permits.clone(), gate.started.clone());
+        let jobs = InMemJobs::with_max_concurrency(gate, 1);
+        let with_priority = |p| SolveEnvelope {
+            priority: Some(p),
+            ..small()
+        };
+        // The only worker is busy by the time the other two arrive.
+        let busy = jobs.enqueue(with_priority(0));
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let low = jobs.enqueue(with_priority(1));
+        let high = jobs.enqueue(with_priority(5));
+
+        permits.add_permits(3);
+        for id in [&busy, &low, &high] {
+            finished(&jobs, id).await;
+        }
+        assert_eq!(*started.lock(), [0, 5, 1]);
+    }
 }
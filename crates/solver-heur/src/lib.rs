@@ -2,9 +2,12 @@ use async_trait::async_trait;
 use rand::{seq::SliceRandom, Rng};
 use rand_chacha::rand_core::SeedableRng;
 use rand_chacha::ChaCha8Rng;
-use sched_core::{SolveEnvelope, SolveResult, Solver};
+use sched_core::feasibility::{feasible_starts, linked_room};
+use sched_core::index::InstanceIndex;
+use sched_core::occupancy::{meeting_slots, Occupancy};
+use sched_core::{CancelToken, SolveEnvelope, SolveResult, Solver};
 use std::collections::{HashMap, HashSet};
-use types::{Assignment, Course, Instance, Room, Teacher};
+use types::{Assignment, Course, Instance};
 
 pub struct HeurSolver;
 impl HeurSolver {
@@ -13,134 +16,261 @@ impl HeurSolver {
     }
 }
 
+const GA_ITERS: usize = 300;
+const GA_TOURNAMENT: usize = 3;
+
+fn ga_pop_size(inst: &Instance) -> usize {
+    40usize.min(10 + inst.courses.len() * 2)
+}
+
+/// Effective `(tournament size, elitism)` for a population of `pop`.
+fn selection_params(params: &types::SolveParams, pop: usize) -> (usize, Option<usize>) {
+    let tournament = params
+        .tournamentSize
+        .map_or(GA_TOURNAMENT, |k| k as usize)
+        .clamp(1, pop.max(1));
+    let elitism = params
+        .elitism
+        .map(|e| (e as usize).min(pop.saturating_sub(1)));
+    (tournament, elitism)
+}
+
 #[async_trait]
 impl Solver for HeurSolver {
     async fn solve(&self, env: SolveEnvelope) -> anyhow::Result<SolveResult> {
-        let mut rng = ChaCha8Rng::seed_from_u64(env.params.seed);
-        let inst = env.instance;
+        self.solve_cancellable(env, CancelToken::new()).await
+    }
 
-        let feas = build_feasible(&inst);
-        let pinset: HashSet<(String, String, String, String)> =
-            env.pinned.iter().map(pin_key).collect();
+    async fn solve_cancellable(
+        &self,
+        env: SolveEnvelope,
+        cancel: CancelToken,
+    ) -> anyhow::Result<SolveResult> {
+        let reproduce = sched_core::fingerprint::reproduce_token(&env);
+        let seed = env.params.seed;
+        let budget = env.params.objectiveBudget;
+        let inst = env.instance.clone();
+        let mut params = serde_json::json!({
+            "pop_size": ga_pop_size(&env.instance),
+            "iters": GA_ITERS,
+            "tournament": GA_TOURNAMENT,
+            "objective_budget": budget,
+        });
+        let mut res = solve_ga(env, &cancel)?;
+        for key in ["tournament", "elitism"] {
+            if let Some(v) = res.stats.get(key) {
+                params[key] = v.clone();
+            }
+        }
+        res.stats["seed"] = serde_json::json!(seed);
+        res.stats["solver"] = serde_json::json!("heuristic");
+        res.stats["proven_optimal"] = serde_json::json!(false);
+        res.stats["params"] = params;
+        if let Some(budget) = budget {
+            res.stats["budget_met"] = serde_json::json!(sched_core::budget_met(&res, budget));
+        }
+        res.stats["reproduce"] = serde_json::json!(reproduce);
+        res.stats["placement"] = sched_core::feasibility::placement_report(&inst, &res.assignments);
+        Ok(res)
+    }
+}
 
-        let time_locked: HashSet<(String, String)> = env
-            .partial_pins
-            .iter()
-            .filter_map(|p| {
-                p.timeslot
-                    .as_ref()
-                    .map(|t| (p.courseId.0.clone(), t.0.clone()))
-            })
-            .collect();
-        let room_locked: HashSet<(String, String)> = env
-            .partial_pins
-            .iter()
-            .filter_map(|p| {
-                p.roomId
-                    .as_ref()
-                    .map(|r| (p.courseId.0.clone(), r.0.clone()))
-            })
-            .collect();
-        let time_room_locked: HashSet<(String, String, String)> = env
-            .partial_pins
-            .iter()
-            .filter_map(|p| match (&p.timeslot, &p.roomId) {
-                (Some(t), Some(r)) => Some((p.courseId.0.clone(), t.0.clone(), r.0.clone())),
-                _ => None,
-            })
-            .collect();
+fn solve_ga(env: SolveEnvelope, cancel: &CancelToken) -> anyhow::Result<SolveResult> {
+    if let Some(res) = sched_core::degenerate_result(&env.instance, "ga") {
+        return Ok(res);
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(sched_core::rng::derive_seed(
+        env.params.seed,
+        sched_core::rng::GA,
+    ));
+    let inst = env.instance;
+
+    let feas = feasible_starts(&InstanceIndex::new(&inst));
+    let pinset: HashSet<(String, String, String, String)> =
+        env.pinned.iter().map(pin_key).collect();
+
+    let time_locked: HashSet<(String, String)> = env
+        .partial_pins
+        .iter()
+        .filter_map(|p| {
+            p.timeslot
+                .as_ref()
+                .map(|t| (p.courseId.0.clone(), t.0.clone()))
+        })
+        .collect();
+    let room_locked: HashSet<(String, String)> = env
+        .partial_pins
+        .iter()
+        .filter_map(|p| {
+            p.roomId
+                .as_ref()
+                .map(|r| (p.courseId.0.clone(), r.0.clone()))
+        })
+        .collect();
+    let time_room_locked: HashSet<(String, String, String)> = env
+        .partial_pins
+        .iter()
+        .filter_map(|p| match (&p.timeslot, &p.roomId) {
+            (Some(t), Some(r)) => Some((p.courseId.0.clone(), t.0.clone(), r.0.clone())),
+            _ => None,
+        })
+        .collect();
 
-        let pop_size = 40usize.min(10 + inst.courses.len() * 2);
-        let iters = 300usize;
-        let mut population: Vec<Candidate> = Vec::new();
+    let pop_size = ga_pop_size(&inst);
+    let iters = GA_ITERS;
+    let mut population: Vec<Candidate> = Vec::new();
+
+    if let Some(c0) = randomized_construct_with_pins_and_base(
+        &inst,
+        &feas,
+        &env.pinned,
+        &env.base,
+        &env.partial_pins,
+        env.params.bestEffort,
+        &mut rng,
+    ) {
+        population.push(c0);
+    }
 
-        if let Some(c0) = randomized_construct_with_pins_and_base(
+    while population.len() < pop_size {
+        if let Some(c) = randomized_construct_with_pins_and_base(
             &inst,
             &feas,
             &env.pinned,
-            &env.base,
+            &Vec::new(),
             &env.partial_pins,
+            env.params.bestEffort,
             &mut rng,
         ) {
-            population.push(c0);
+            population.push(c);
+        } else {
+            break;
         }
+    }
 
-        while population.len() < pop_size {
-            if let Some(c) = randomized_construct_with_pins_and_base(
-                &inst,
-                &feas,
-                &env.pinned,
-                &Vec::new(),
-                &env.partial_pins,
-                &mut rng,
-            ) {
-                population.push(c);
-            } else {
-                break;
-            }
-        }
+    if population.is_empty() {
+        // Construction is seeded and ignores `timeLimitSec`, so more time
+        // would fail the same way; the reason tells a course with no
+        // placement at all apart from randomized construction giving up.
+        let violations = sched_core::feasibility::unplaceable_courses(&inst);
+        let reason = if violations.is_empty() {
+            "construction_failed"
+        } else {
+            "no_feasible_placement"
+        };
+        return Ok(SolveResult {
+            status: "infeasible".into(),
+            objective: 0.0,
+            assignments: vec![],
+            violations,
+            stats: serde_json::json!({
+                "method": "ga",
+                "note": "failed to construct with pins",
+                "reason": reason,
+            }),
+        });
+    }
+    for c in &mut population {
+        c.evaluate_with_locks(&inst, &env.partial_pins);
+    }
+    population.sort_by(|a, b| a.objective.total_cmp(&b.objective));
 
-        if population.is_empty() {
-            return Ok(SolveResult {
-                status: "infeasible".into(),
-                objective: 0.0,
-                assignments: vec![],
-                violations: vec![],
-                stats: serde_json::json!({"method":"ga","note":"failed to construct with pins"}),
-            });
-        }
-        population.sort_by(|a, b| a.objective.total_cmp(&b.objective));
+    let (tournament_size, elitism) = selection_params(&env.params, population.len());
 
-        for _ in 0..iters {
-            let parent = tournament(&population, 3, &mut rng).clone();
-            let mut child = mutate(
-                &inst,
-                &feas,
-                parent,
-                &mut rng,
-                &pinset,
-                &time_locked,
-                &room_locked,
-                &time_room_locked,
-            );
-            child.evaluate(&inst);
-            if let Some(worst) = population.last() {
-                if child.objective < worst.objective {
-                    population.pop();
-                    insert_sorted(&mut population, child);
-                }
-            } else {
-                insert_sorted(&mut population, child);
-            }
+    let mut generations = 0;
+    let mut cancelled = false;
+    for _ in 0..iters {
+        if cancel.is_cancelled() {
+            cancelled = true;
+            break;
         }
+        if env
+            .params
+            .objectiveBudget
+            .is_some_and(|b| population[0].objective <= b)
+        {
+            break;
+        }
+        generations += 1;
+        let parent = tournament(&population, tournament_size, &mut rng).clone();
+        let mut child = mutate(
+            &inst,
+            &feas,
+            parent,
+            &mut rng,
+            &pinset,
+            &time_locked,
+            &room_locked,
+            &time_room_locked,
+        );
+        child.evaluate_with_locks(&inst, &env.partial_pins);
+        replace(&mut population, child, elitism, &mut rng);
+    }
 
-        let best = &population[0];
-        Ok(SolveResult {
-            status: "solved".into(),
-            objective: best.objective,
-            assignments: best.assignments.clone(),
-            violations: vec![],
-            stats: serde_json::json!({
-                "method": "ga",
-                "pop": population.len(),
-                "best": best.objective,
-            }),
-        })
+    let best = &population[0];
+    let mut violations = unplaced_violations(&inst, &best.assignments);
+    violations.extend(sched_core::feasibility::unplaceable_courses(&inst));
+    // `unplaced` above already reports missing meetings, weighted by count.
+    violations.extend(
+        sched_core::feasibility::all_hard_violations(&inst, &best.assignments, &env.partial_pins)
+            .into_iter()
+            .filter(|v| v.r#type != "meeting_count"),
+    );
+    Ok(SolveResult {
+        status: if cancelled {
+            "cancelled".into()
+        } else if violations.is_empty() {
+            "solved".into()
+        } else {
+            "partial".into()
+        },
+        objective: best.objective,
+        assignments: best.assignments.clone(),
+        violations,
+        stats: serde_json::json!({
+            "method": "ga",
+            "pop": population.len(),
+            "best": best.objective,
+            "tournament": tournament_size,
+            "elitism": elitism,
+            "generations": generations,
+        }),
+    })
+}
+
+/// How long `HeurSolver::improve_from` may search: at most `steps`
+/// mutations, fewer once `deadline` passes or `cancel` trips.
+pub struct RepairLimits {
+    pub steps: usize,
+    pub deadline: Option<std::time::Instant>,
+    pub cancel: CancelToken,
+}
+
+impl RepairLimits {
+    fn reached(&self) -> bool {
+        self.cancel.is_cancelled()
+            || self
+                .deadline
+                .is_some_and(|d| std::time::Instant::now() >= d)
     }
 }
 
 impl HeurSolver {
+    /// Local search from `base` within `limits`; returns the best schedule
+    /// found when they run out.
     pub fn improve_from(
         &self,
-        inst: &types::Instance,
+        index: &InstanceIndex,
         base: Vec<types::Assignment>,
         pins: &Vec<types::Assignment>,
         locks: &Vec<types::PartialPin>,
         seed: u64,
-        steps: usize,
+        limits: &RepairLimits,
     ) -> (Vec<types::Assignment>, f64) {
-        let feas = build_feasible(inst);
-        let mut rng = ChaCha8Rng::seed_from_u64(seed ^ 0x9E37_79B9_7F4A_7C15);
+        let inst = index.inst;
+        let feas = feasible_starts(index);
+        let mut rng =
+            ChaCha8Rng::seed_from_u64(sched_core::rng::derive_seed(seed, sched_core::rng::REPAIR));
         let pinset: HashSet<(String, String, String, String)> = pins.iter().map(pin_key).collect();
 
         let time_locked: HashSet<(String, String)> = locks
@@ -167,15 +297,19 @@ impl HeurSolver {
             })
             .collect();
 
-        let mut parent =
-            randomized_construct_with_pins_and_base(inst, &feas, pins, &base, locks, &mut rng)
-                .unwrap_or_else(|| Candidate {
-                    assignments: base,
-                    objective: 0.0,
-                });
-        parent.evaluate(inst);
-
-        for _ in 0..steps {
+        let mut parent = randomized_construct_with_pins_and_base(
+            inst, &feas, pins, &base, locks, false, &mut rng,
+        )
+        .unwrap_or_else(|| Candidate {
+            assignments: base,
+            objective: 0.0,
+        });
+        parent.evaluate_with_locks(inst, locks);
+
+        for _ in 0..limits.steps {
+            if limits.reached() {
+                break;
+            }
             let mut child = mutate(
                 inst,
                 &feas,
@@ -186,7 +320,7 @@ impl HeurSolver {
                 &room_locked,
                 &time_room_locked,
             );
-            child.evaluate(inst);
+            child.evaluate_with_locks(inst, locks);
             if child.objective < parent.objective {
                 parent = child;
             }
@@ -204,71 +338,135 @@ struct Candidate {
 impl Candidate {
     fn evaluate(&mut self, inst: &Instance) {
         let s = sched_core::scoring::compute_soft_scores(inst, &self.assignments);
-        self.objective = s.objective;
+        let unplaced: f64 = unplaced_meetings(inst, &self.assignments)
+            .iter()
+            .map(|&(ci, missing)| {
+                UNPLACED_PENALTY
+                    * inst.courses[ci].priority_or_default().max(1) as f64
+                    * missing as f64
+            })
+            .sum();
+        let over_cap: i64 = inst
+            .teachers
+            .iter()
+            .filter_map(|t| {
+                let cap = t.prefs.max_windows? as i64;
+                let got = s.windows_teachers.get(&t.id.0).copied().unwrap_or(0);
+                Some((got - cap).max(0))
+            })
+            .sum();
+        let gapped_days =
+            sched_core::feasibility::contiguous_day_violations(inst, &self.assignments).len();
+        let over_weekly =
+            sched_core::feasibility::weekly_cap_violations(inst, &self.assignments).len();
+        let split_rooms =
+            sched_core::feasibility::same_room_violations(inst, &self.assignments).len();
+        self.objective = s.objective
+            + unplaced
+            + WINDOW_CAP_PENALTY * over_cap as f64
+            + GAPPED_DAY_PENALTY * gapped_days as f64
+            + WEEKLY_CAP_PENALTY * over_weekly as f64
+            + SAME_ROOM_PENALTY * split_rooms as f64;
     }
-}
 
-fn insert_sorted(pop: &mut Vec<Candidate>, c: Candidate) {
-    let pos = pop.partition_point(|x| x.objective <= c.objective);
-    pop.insert(pos, c);
+    /// `evaluate` plus a penalty per `meeting`-indexed partial pin that is
+    /// out of order: mutation may move a course's other meetings across the
+    /// pinned one.
+    fn evaluate_with_locks(&mut self, inst: &Instance, locks: &[types::PartialPin]) {
+        self.evaluate(inst);
+        let misordered =
+            sched_core::feasibility::meeting_order_violations(inst, &self.assignments, locks).len();
+        self.objective += MEETING_ORDER_PENALTY * misordered as f64;
+    }
 }
 
-fn build_feasible(inst: &Instance) -> Vec<Vec<(usize, usize)>> {
-    let times: Vec<&str> = inst.timeslots.iter().map(|t| t.0.as_str()).collect();
-    let group_size: HashMap<&str, u32> = inst
-        .groups
+/// Penalty per window above a teacher's `max_windows`, steep enough that the
+/// search trades any soft gain for getting back under the cap.
+const WINDOW_CAP_PENALTY: f64 = 1000.0;
+
+/// Penalty per day a `contiguous_days` group's classes are split by a gap.
+const GAPPED_DAY_PENALTY: f64 = 1000.0;
+
+/// Penalty per group over its `max_weekly` meetings.
+const WEEKLY_CAP_PENALTY: f64 = 1000.0;
+
+/// Penalty per `same_room_groups` set spread over several rooms; only
+/// pins or a supplied base can cause it, since construction and mutation
+/// keep sets together.
+const SAME_ROOM_PENALTY: f64 = 1000.0;
+
+/// Penalty per partial pin whose `meeting` index does not match the order
+/// of its course's meetings.
+const MEETING_ORDER_PENALTY: f64 = 1000.0;
+
+/// Penalty per missing meeting of a priority-1 course. Only best-effort
+/// construction produces partial candidates, so complete ones never pay it.
+const UNPLACED_PENALTY: f64 = 1000.0;
+
+/// `(course index, missing meetings)` for every under-placed course.
+fn unplaced_meetings(inst: &Instance, assignments: &[Assignment]) -> Vec<(usize, u32)> {
+    inst.courses
         .iter()
-        .map(|g| (g.id.0.as_str(), g.size))
-        .collect();
-    let teacher_by_id: HashMap<&str, &Teacher> =
-        inst.teachers.iter().map(|t| (t.id.0.as_str(), t)).collect();
+        .enumerate()
+        .filter_map(|(ci, c)| {
+            let have = assignments.iter().filter(|a| a.courseId == c.id).count() as u32;
+            let missing = c.required_meetings().saturating_sub(have);
+            (missing > 0).then_some((ci, missing))
+        })
+        .collect()
+}
 
-    let room_ok_for_course = |room: &Room, course: &Course| -> bool {
-        let gsz = *group_size.get(course.groupId.0.as_str()).unwrap_or(&0);
-        if room.capacity < gsz {
-            return false;
-        }
-        for need in &course.needs {
-            if !room.equip.contains(need) {
-                return false;
+fn unplaced_violations(inst: &Instance, assignments: &[Assignment]) -> Vec<types::Violation> {
+    unplaced_meetings(inst, assignments)
+        .into_iter()
+        .map(|(ci, missing)| {
+            let c = &inst.courses[ci];
+            types::Violation {
+                r#type: "unplaced".into(),
+                weight: missing as i64,
+                details: serde_json::json!({
+                    "course": c.id.0,
+                    "placed": c.required_meetings() - missing,
+                    "required": c.required_meetings(),
+                }),
             }
-        }
-        true
-    };
-    let is_teacher_available = |teacher: &Teacher, t: usize, dur2: bool| -> bool {
-        if teacher.available.is_empty() {
-            return !dur2 || (t + 1 < times.len());
-        }
-        let has_t = teacher.available.iter().any(|x| x.0 == times[t]);
-        if !dur2 {
-            return has_t;
-        }
-        let has_t1 = t + 1 < times.len() && teacher.available.iter().any(|x| x.0 == times[t + 1]);
-        has_t && has_t1
-    };
+        })
+        .collect()
+}
 
-    let mut feas: Vec<Vec<(usize, usize)>> = vec![Vec::new(); inst.courses.len()];
-    for (ci, c) in inst.courses.iter().enumerate() {
-        let dur2 = c.duration == 2;
-        let teacher = match teacher_by_id.get(c.teacherId.0.as_str()) {
-            Some(t) => *t,
-            None => continue,
-        };
-        for t in 0..times.len() {
-            if dur2 && t + 1 >= times.len() {
-                break;
-            }
-            if !is_teacher_available(teacher, t, dur2) {
-                continue;
+/// Steady-state replacement in a population sorted best first. With
+/// `elitism` the child may evict a random member outside the top `elite`,
+/// otherwise only the worst one; it survives only if it is better.
+fn replace(
+    population: &mut Vec<Candidate>,
+    child: Candidate,
+    elitism: Option<usize>,
+    rng: &mut ChaCha8Rng,
+) {
+    match elitism {
+        Some(elite) => {
+            let victim = rng.gen_range(elite..population.len());
+            if child.objective < population[victim].objective {
+                population.remove(victim);
+                insert_sorted(population, child);
             }
-            for (ri, r) in inst.rooms.iter().enumerate() {
-                if room_ok_for_course(r, c) {
-                    feas[ci].push((t, ri));
+        }
+        None => {
+            if let Some(worst) = population.last() {
+                if child.objective < worst.objective {
+                    population.pop();
+                    insert_sorted(population, child);
                 }
+            } else {
+                insert_sorted(population, child);
             }
         }
     }
-    feas
+}
+
+fn insert_sorted(pop: &mut Vec<Candidate>, c: Candidate) {
+    let pos = pop.partition_point(|x| x.objective <= c.objective);
+    pop.insert(pos, c);
 }
 
 fn pin_key(a: &types::Assignment) -> (String, String, String, String) {
@@ -280,37 +478,22 @@ fn pin_key(a: &types::Assignment) -> (String, String, String, String) {
     )
 }
 
-#[derive(Default, Clone)]
-struct Occupancy {
-    room: HashSet<(usize, usize)>,
-    teacher: HashSet<(usize, usize)>,
-    group: HashSet<(usize, usize)>,
-}
-
 fn randomized_construct(
     inst: &Instance,
     feas: &Vec<Vec<(usize, usize)>>,
     rng: &mut ChaCha8Rng,
 ) -> Option<Candidate> {
     let times = &inst.timeslots;
-    let teacher_index: HashMap<&str, usize> = inst
-        .teachers
-        .iter()
-        .enumerate()
-        .map(|(i, t)| (t.id.0.as_str(), i))
-        .collect();
-    let group_index: HashMap<&str, usize> = inst
-        .groups
-        .iter()
-        .enumerate()
-        .map(|(i, g)| (g.id.0.as_str(), i))
-        .collect();
-
-    let mut occ = Occupancy::default();
+    let mut occ = Occupancy::new(inst);
     let mut assignments = Vec::new();
 
     let mut order: Vec<usize> = (0..inst.courses.len()).collect();
-    order.sort_by_key(|&ci| feas[ci].len());
+    order.sort_by_key(|&ci| {
+        (
+            std::cmp::Reverse(inst.courses[ci].priority_or_default()),
+            feas[ci].len(),
+        )
+    });
 
     for &ci in &order {
         let c = &inst.courses[ci];
@@ -333,7 +516,12 @@ fn randomized_construct(
                 if used.contains(&(t, r)) {
                     continue;
                 }
-                if !place_ok(ci, c, t, r, &mut local_occ, &teacher_index, &group_index) {
+                if linked_room(inst, &c.id, assignments.iter().chain(&local_ass))
+                    .is_some_and(|lr| *lr != inst.rooms[r].id)
+                {
+                    continue;
+                }
+                if !place_ok(inst, c, t, r, &mut local_occ) {
                     continue;
                 }
                 local_ass.push(Assignment {
@@ -341,10 +529,11 @@ fn randomized_construct(
                     timeslot: times[t].clone(),
                     roomId: inst.rooms[r].id.clone(),
                     teacherId: c.teacherId.clone(),
+                    meta: c.meta.clone(),
                 });
                 used.insert((t, r));
                 placed += 1;
-                if placed == c.countPerWeek {
+                if placed == c.required_meetings() {
                     occ = local_occ;
                     assignments.extend(local_ass);
                     break 'outer;
@@ -353,7 +542,7 @@ fn randomized_construct(
             placed = 0;
         }
 
-        if placed < c.countPerWeek {
+        if placed < c.required_meetings() {
             return None;
         }
     }
@@ -372,6 +561,7 @@ fn randomized_construct_with_pins_and_base(
     pins: &Vec<Assignment>,
     base: &Vec<Assignment>,
     locks: &Vec<types::PartialPin>,
+    best_effort: bool,
     rng: &mut ChaCha8Rng,
 ) -> Option<Candidate> {
     use std::collections::{HashMap, HashSet};
@@ -395,20 +585,7 @@ fn randomized_construct_with_pins_and_base(
         .map(|(i, c)| (c.id.0.as_str(), i))
         .collect();
 
-    let teacher_index: HashMap<&str, usize> = inst
-        .teachers
-        .iter()
-        .enumerate()
-        .map(|(i, t)| (t.id.0.as_str(), i))
-        .collect();
-    let group_index: HashMap<&str, usize> = inst
-        .groups
-        .iter()
-        .enumerate()
-        .map(|(i, g)| (g.id.0.as_str(), i))
-        .collect();
-
-    let mut occ = Occupancy::default();
+    let mut occ = Occupancy::new(inst);
     let mut assignments: Vec<Assignment> = Vec::new();
     let mut pinned_set: HashSet<(String, String, String, String)> = HashSet::new();
 
@@ -421,7 +598,10 @@ fn randomized_construct_with_pins_and_base(
             continue;
         };
         let c = &inst.courses[ci];
-        if !place_ok(ci, c, ti, ri, &mut occ, &teacher_index, &group_index) {
+        if !place_ok(inst, c, ti, ri, &mut occ) {
+            if best_effort {
+                continue;
+            }
             return None;
         }
         assignments.push(a.clone());
@@ -441,10 +621,10 @@ fn randomized_construct_with_pins_and_base(
         };
         let c = &inst.courses[ci];
         let already = assignments.iter().filter(|x| x.courseId == c.id).count() as u32;
-        if already >= c.countPerWeek {
+        if already >= c.required_meetings() {
             continue;
         }
-        if place_ok(ci, c, ti, ri, &mut occ, &teacher_index, &group_index) {
+        if place_ok(inst, c, ti, ri, &mut occ) {
             assignments.push(a.clone());
         }
     }
@@ -494,7 +674,12 @@ fn randomized_construct_with_pins_and_base(
     }
 
     let mut order: Vec<usize> = (0..inst.courses.len()).collect();
-    order.sort_by_key(|&ci| feas[ci].len());
+    order.sort_by_key(|&ci| {
+        (
+            std::cmp::Reverse(inst.courses[ci].priority_or_default()),
+            feas[ci].len(),
+        )
+    });
 
     for &ci in &order {
         let c = &inst.courses[ci];
@@ -503,8 +688,11 @@ fn randomized_construct_with_pins_and_base(
 
         let mut course_locks = locks_by_course.remove(&ci).unwrap_or_default();
 
-        if have0 + (course_locks.len() as u32) > c.countPerWeek {
-            return None;
+        if have0 + (course_locks.len() as u32) > c.required_meetings() {
+            if !best_effort {
+                return None;
+            }
+            course_locks.truncate(c.required_meetings().saturating_sub(have0) as usize);
         }
 
         for (t_req, r_req) in course_locks.drain(..) {
@@ -519,24 +707,29 @@ fn randomized_construct_with_pins_and_base(
 
             let mut placed = false;
             for (t, r) in starts {
-                if place_ok(ci, c, t, r, &mut occ, &teacher_index, &group_index) {
+                if linked_room(inst, &c.id, &assignments).is_some_and(|lr| *lr != inst.rooms[r].id)
+                {
+                    continue;
+                }
+                if place_ok(inst, c, t, r, &mut occ) {
                     assignments.push(Assignment {
                         courseId: c.id.clone(),
                         timeslot: inst.timeslots[t].clone(),
                         roomId: inst.rooms[r].id.clone(),
                         teacherId: c.teacherId.clone(),
+                        meta: c.meta.clone(),
                     });
                     placed = true;
                     break;
                 }
             }
-            if !placed {
+            if !placed && !best_effort {
                 return None;
             }
         }
 
         let have = assignments.iter().filter(|x| x.courseId == c.id).count() as u32;
-        let need = c.countPerWeek.saturating_sub(have);
+        let need = c.required_meetings().saturating_sub(have);
         if need == 0 {
             continue;
         }
@@ -546,12 +739,16 @@ fn randomized_construct_with_pins_and_base(
 
         let mut placed = 0u32;
         for &(t, r) in &starts {
-            if place_ok(ci, c, t, r, &mut occ, &teacher_index, &group_index) {
+            if linked_room(inst, &c.id, &assignments).is_some_and(|lr| *lr != inst.rooms[r].id) {
+                continue;
+            }
+            if place_ok(inst, c, t, r, &mut occ) {
                 assignments.push(Assignment {
                     courseId: c.id.clone(),
                     timeslot: inst.timeslots[t].clone(),
                     roomId: inst.rooms[r].id.clone(),
                     teacherId: c.teacherId.clone(),
+                    meta: c.meta.clone(),
                 });
                 placed += 1;
                 if placed == need {
@@ -559,7 +756,7 @@ fn randomized_construct_with_pins_and_base(
                 }
             }
         }
-        if placed < need {
+        if placed < need && !best_effort {
             return None;
         }
     }
@@ -572,47 +769,25 @@ fn randomized_construct_with_pins_and_base(
     Some(cand)
 }
 
-fn place_ok(
-    ci: usize,
-    course: &Course,
+fn place_ok<'a>(
+    inst: &'a Instance,
+    course: &'a Course,
     t: usize,
     r: usize,
-    occ: &mut Occupancy,
-    teacher_index: &HashMap<&str, usize>,
-    group_index: &HashMap<&str, usize>,
+    occ: &mut Occupancy<'a>,
 ) -> bool {
-    let tidx = match teacher_index.get(course.teacherId.0.as_str()) {
-        Some(&i) => i,
-        None => return false,
-    };
-    let gidx = match group_index.get(course.groupId.0.as_str()) {
-        Some(&i) => i,
-        None => return false,
+    let Some(slots) = meeting_slots(inst, t, course.duration) else {
+        return false;
     };
-    let dur2 = course.duration == 2;
-
-    if occ.room.contains(&(r, t))
-        || occ.teacher.contains(&(tidx, t))
-        || occ.group.contains(&(gidx, t))
+    let room = inst.rooms[r].id.0.as_str();
+    let (tid, gid) = (course.teacherId.0.as_str(), course.groupId.0.as_str());
+    let parity = course.parity();
+    if !occ.is_free(room, tid, gid, &slots, parity) || !occ.course_free(&course.id.0, room, &slots)
     {
         return false;
     }
-    if dur2 {
-        if occ.room.contains(&(r, t + 1))
-            || occ.teacher.contains(&(tidx, t + 1))
-            || occ.group.contains(&(gidx, t + 1))
-        {
-            return false;
-        }
-    }
-    occ.room.insert((r, t));
-    occ.teacher.insert((tidx, t));
-    occ.group.insert((gidx, t));
-    if dur2 {
-        occ.room.insert((r, t + 1));
-        occ.teacher.insert((tidx, t + 1));
-        occ.group.insert((gidx, t + 1));
-    }
+    occ.insert(room, tid, gid, &slots, parity);
+    occ.insert_course(&course.id.0, room, &slots);
     true
 }
 
@@ -645,51 +820,14 @@ fn mutate(
         return parent;
     }
 
-    let teacher_index: HashMap<&str, usize> = inst
-        .teachers
-        .iter()
-        .enumerate()
-        .map(|(i, t)| (t.id.0.as_str(), i))
-        .collect();
-    let group_index: HashMap<&str, usize> = inst
-        .groups
-        .iter()
-        .enumerate()
-        .map(|(i, g)| (g.id.0.as_str(), i))
-        .collect();
-
-    let mut occ = Occupancy::default();
+    let mut occ = Occupancy::from_assignments(inst, &parent.assignments);
     let times = &inst.timeslots;
 
-    let mut slots_by_course: HashMap<&str, Vec<usize>> = HashMap::new();
     let mut course_by_id: HashMap<&str, &Course> = HashMap::new();
     for c in &inst.courses {
         course_by_id.insert(c.id.0.as_str(), c);
     }
 
-    for (ai, a) in parent.assignments.iter().enumerate() {
-        let c = course_by_id.get(a.courseId.0.as_str()).unwrap();
-        let ci = inst.courses.iter().position(|x| x.id == c.id).unwrap();
-        let t0 = inst
-            .timeslots
-            .iter()
-            .position(|x| x.0 == a.timeslot.0)
-            .unwrap();
-        let r = inst.rooms.iter().position(|x| x.id == a.roomId).unwrap();
-
-        let tidx = *teacher_index.get(c.teacherId.0.as_str()).unwrap();
-        let gidx = *group_index.get(c.groupId.0.as_str()).unwrap();
-        occ.room.insert((r, t0));
-        occ.teacher.insert((tidx, t0));
-        occ.group.insert((gidx, t0));
-        if c.duration == 2 {
-            occ.room.insert((r, t0 + 1));
-            occ.teacher.insert((tidx, t0 + 1));
-            occ.group.insert((gidx, t0 + 1));
-        }
-        slots_by_course.entry(c.id.0.as_str()).or_default().push(ai);
-    }
-
     let mutations = 1 + (parent.assignments.len() / 10).min(3);
     for _ in 0..mutations {
         let mut tries = 0usize;
@@ -720,21 +858,20 @@ fn mutate(
             .position(|x| x.0 == a.timeslot.0)
             .unwrap();
         let r0 = inst.rooms.iter().position(|x| x.id == a.roomId).unwrap();
-        let tidx = *teacher_index.get(c.teacherId.0.as_str()).unwrap();
-        let gidx = *group_index.get(c.groupId.0.as_str()).unwrap();
 
         if time_room_locked.contains(&(c.id.0.clone(), a.timeslot.0.clone(), a.roomId.0.clone())) {
             continue;
         }
 
-        occ.room.remove(&(r0, t0));
-        occ.teacher.remove(&(tidx, t0));
-        occ.group.remove(&(gidx, t0));
-        if c.duration == 2 {
-            occ.room.remove(&(r0, t0 + 1));
-            occ.teacher.remove(&(tidx, t0 + 1));
-            occ.group.remove(&(gidx, t0 + 1));
-        }
+        let slots0 = meeting_slots(inst, t0, c.duration).unwrap_or_else(|| vec![t0]);
+        occ.remove(
+            &inst.rooms[r0].id.0,
+            &c.teacherId.0,
+            &c.groupId.0,
+            &slots0,
+            c.parity(),
+        );
+        occ.remove_course(&c.id.0, &inst.rooms[r0].id.0, &slots0);
 
         let mut candidates = feas[ci].clone();
         candidates.shuffle(rng);
@@ -747,14 +884,25 @@ fn mutate(
             candidates.retain(|(_, r)| *r == r0);
         }
 
+        let others = parent
+            .assignments
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != ai)
+            .map(|(_, x)| x);
+        if let Some(lr) = linked_room(inst, &c.id, others) {
+            candidates.retain(|(_, r)| inst.rooms[*r].id == *lr);
+        }
+
         let mut placed = false;
         for &(t, r) in &candidates {
-            if place_ok(ci, c, t, r, &mut occ, &teacher_index, &group_index) {
+            if place_ok(inst, c, t, r, &mut occ) {
                 parent.assignments[ai] = Assignment {
                     courseId: c.id.clone(),
                     timeslot: times[t].clone(),
                     roomId: inst.rooms[r].id.clone(),
                     teacherId: c.teacherId.clone(),
+                    meta: c.meta.clone(),
                 };
                 placed = true;
                 break;
@@ -762,9 +910,570 @@ fn mutate(
         }
 
         if !placed {
-            let _ = place_ok(ci, c, t0, r0, &mut occ, &teacher_index, &group_index);
+            occ.insert(
+                &inst.rooms[r0].id.0,
+                &c.teacherId.0,
+                &c.groupId.0,
+                &slots0,
+                c.parity(),
+            );
+            occ.insert_course(&c.id.0, &inst.rooms[r0].id.0, &slots0);
         }
     }
 
     parent
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sched_core::scoring::compute_soft_scores;
+    use types::{CourseId, Parity, RoomId, SoftWeights, TimeslotId};
+
+    fn small() -> SolveEnvelope {
+        serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap()
+    }
+
+    fn slots(ids: &[&str]) -> Vec<TimeslotId> {
+        ids.iter().map(|s| TimeslotId(s.to_string())).collect()
+    }
+
+    /// Runs the GA to the end and checks that it placed every meeting.
+    fn solved(env: SolveEnvelope) -> SolveResult {
+        let res = solve_ga(env, &CancelToken::new()).unwrap();
+        assert_eq!(res.status, "solved");
+        res
+    }
+
+    /// Sorted start slots of `course`'s meetings.
+    fn starts(res: &SolveResult, course: &str) -> Vec<String> {
+        let mut ts: Vec<String> = res
+            .assignments
+            .iter()
+            .filter(|a| a.courseId.0 == course)
+            .map(|a| a.timeslot.0.clone())
+            .collect();
+        ts.sort();
+        ts
+    }
+
+    fn soft_meetings(mut env: SolveEnvelope, hard: &[&str], soft: &[&str]) -> i64 {
+        env.instance.policy.soft_weights = SoftWeights {
+            soft_availability: 10,
+            ..Default::default()
+        };
+        let t = &mut env.instance.teachers[0];
+        t.available = slots(hard);
+        t.soft_available = slots(soft);
+        let inst = env.instance.clone();
+        let res = solved(env);
+        compute_soft_scores(&inst, &res.assignments).soft_available_meetings
+    }
+
+    #[test]
+    fn soft_available_slots_are_used_only_when_necessary() {
+        let soft = ["tue.1", "tue.2", "wed.1"];
+        assert_eq!(soft_meetings(small(), &["mon.1", "mon.2"], &soft), 0);
+        assert_eq!(soft_meetings(small(), &["mon.1"], &soft), 1);
+    }
+
+    #[tokio::test]
+    async fn identical_envelopes_reproduce_identically() {
+        let a = HeurSolver::new().solve(small()).await.unwrap();
+        let b = HeurSolver::new().solve(small()).await.unwrap();
+        assert_eq!(a.stats["seed"], 42);
+        assert_eq!(a.stats["solver"], "heuristic");
+        assert!(a.stats["reproduce"].is_string());
+        assert_eq!(a.stats["reproduce"], b.stats["reproduce"]);
+        assert_eq!(
+            serde_json::to_value(&a.assignments).unwrap(),
+            serde_json::to_value(&b.assignments).unwrap()
+        );
+
+        let mut other = small();
+        other.params.seed = 7;
+        let c = HeurSolver::new().solve(other).await.unwrap();
+        assert_ne!(a.stats["reproduce"], c.stats["reproduce"]);
+    }
+
+    #[tokio::test]
+    async fn best_effort_keeps_the_high_priority_course_under_scarcity() {
+        let mut env = small();
+        env.params.bestEffort = true;
+        env.instance.teachers[0].available = slots(&["mon.1", "mon.2"]);
+        let (alg, prog) = (0, 1);
+        env.instance.courses[alg].priority = Some(5);
+        env.instance.courses[prog].groupId = env.instance.courses[alg].groupId.clone();
+        env.instance.courses[prog].teacherId = env.instance.courses[alg].teacherId.clone();
+
+        let res = HeurSolver::new().solve(env).await.unwrap();
+        let placement = &res.stats["placement"];
+        assert_eq!(placement["c_alg"]["placed"], 2);
+        assert_eq!(placement["c_prog"]["placed"], 0);
+        assert!(res.assignments.iter().all(|a| a.courseId.0 == "c_alg"));
+    }
+
+    #[test]
+    fn best_effort_returns_a_partial_schedule_with_unplaced_courses() {
+        let mut env = small();
+        env.instance.teachers[0].available = slots(&["mon.1"]);
+        let strict = solve_ga(env.clone(), &CancelToken::new()).unwrap();
+        assert_eq!(strict.status, "infeasible");
+        assert!(strict.assignments.is_empty());
+
+        env.params.bestEffort = true;
+        let res = solve_ga(env, &CancelToken::new()).unwrap();
+        assert_eq!(res.status, "partial");
+        assert_eq!(res.assignments.len(), 1 + 3);
+        assert_eq!(res.violations.len(), 1);
+        let v = &res.violations[0];
+        assert_eq!(v.r#type, "unplaced");
+        assert_eq!(v.weight, 1);
+        assert_eq!(v.details["course"], "c_alg");
+        assert_eq!(v.details["placed"], 1);
+        assert_eq!(v.details["required"], 2);
+    }
+
+    #[test]
+    fn three_section_course_gets_every_meeting_in_its_own_slot() {
+        let mut env = small();
+        env.instance.courses[0].sections = Some(3);
+        env.instance.teachers[0]
+            .available
+            .push(TimeslotId("wed.2".into()));
+        let res = solved(env);
+        let mut slots: Vec<&str> = res
+            .assignments
+            .iter()
+            .filter(|a| a.courseId.0 == "c_alg")
+            .map(|a| a.timeslot.0.as_str())
+            .collect();
+        assert_eq!(slots.len(), 3 * 2);
+        slots.sort();
+        slots.dedup();
+        assert_eq!(slots.len(), 3 * 2);
+    }
+
+    #[test]
+    fn building_cap_of_one_puts_courses_in_different_slots() {
+        let mut env = small();
+        for r in &mut env.instance.rooms {
+            r.building = Some("main".into());
+        }
+        env.instance.policy.building_concurrency = [("main".to_string(), 1)].into();
+        for t in &mut env.instance.teachers {
+            t.available = slots(&["mon.1", "mon.2"]);
+        }
+        for c in &mut env.instance.courses {
+            c.countPerWeek = 1;
+            c.duration = 1;
+        }
+        // Left alone, c_prog would share mon.1 with c_alg to dodge mon.2.
+        env.instance.teachers[1].prefs.avoid_slots = slots(&["mon.2"]);
+        env.instance.policy.soft_weights.unpreferred_time = 10;
+        env.pinned = vec![Assignment {
+            courseId: env.instance.courses[0].id.clone(),
+            timeslot: TimeslotId("mon.1".into()),
+            roomId: env.instance.rooms[0].id.clone(),
+            teacherId: env.instance.courses[0].teacherId.clone(),
+            meta: None,
+        }];
+        let inst = env.instance.clone();
+        let res = solved(env);
+        let prog = res.assignments.iter().find(|a| a.courseId.0 == "c_prog");
+        assert_eq!(prog.unwrap().timeslot.0, "mon.2");
+        assert!(sched_core::feasibility::hard_violations(&inst, &res.assignments).is_empty());
+    }
+
+    #[test]
+    fn elitism_never_lets_the_best_objective_worsen() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let candidate = |objective: f64| Candidate {
+            assignments: vec![],
+            objective,
+        };
+        let mut population: Vec<Candidate> = (1..=8).map(|o| candidate(o as f64)).collect();
+        let elite = 3;
+        let mut best = population[0].objective;
+        let mut elites: Vec<f64> = population[..elite].iter().map(|c| c.objective).collect();
+        for _ in 0..500 {
+            let child = candidate(rng.gen_range(0.0..10.0));
+            replace(&mut population, child, Some(elite), &mut rng);
+            assert_eq!(population.len(), 8);
+            assert!(population[0].objective <= best);
+            best = population[0].objective;
+            // Each elite rank only ever improves.
+            for (rank, c) in population[..elite].iter().enumerate() {
+                assert!(c.objective <= elites[rank]);
+                elites[rank] = c.objective;
+            }
+        }
+    }
+
+    #[test]
+    fn classes_cluster_in_the_home_building() {
+        let mut env = small();
+        env.instance.rooms[0].building = Some("north".into());
+        env.instance.rooms[1].building = Some("south".into());
+        for g in &mut env.instance.groups {
+            g.home_building = Some("south".into());
+        }
+        env.instance.courses[1].countPerWeek = 2;
+        env.instance.policy.soft_weights.away_from_home = 100;
+        let inst = env.instance.clone();
+        let res = solved(env);
+        assert!(res.assignments.iter().all(|a| a.roomId.0 == "r202"));
+        assert_eq!(
+            compute_soft_scores(&inst, &res.assignments).away_from_home_total,
+            0
+        );
+    }
+
+    #[test]
+    fn window_cap_forces_a_compact_arrangement() {
+        let mut env = small();
+        env.instance.policy.soft_weights = SoftWeights {
+            unpreferred_time: 10,
+            ..Default::default()
+        };
+        let t = &mut env.instance.teachers[0];
+        t.available = slots(&["mon.1", "mon.2", "mon.3"]);
+        t.prefs.avoid_slots = slots(&["mon.2"]);
+        t.prefs.preferred_days.clear();
+
+        // Uncapped, mon.1 + mon.3 is two teaching blocks but no avoided slot.
+        assert_eq!(starts(&solved(env.clone()), "c_alg"), ["mon.1", "mon.3"]);
+
+        // A cap of one block forces back-to-back meetings through mon.2.
+        env.instance.teachers[0].prefs.max_windows = Some(1);
+        assert!(starts(&solved(env), "c_alg").contains(&"mon.2".to_string()));
+    }
+
+    #[test]
+    fn whitelisted_course_is_never_placed_elsewhere() {
+        let rooms = |env: SolveEnvelope| -> Vec<String> {
+            let res = solved(env);
+            res.assignments
+                .iter()
+                .filter(|a| a.courseId.0 == "c_alg")
+                .map(|a| a.roomId.0.clone())
+                .collect()
+        };
+        let seeds = 1..=5;
+        // Both rooms fit c_alg, so without the whitelist r101 gets used.
+        assert!(seeds.clone().any(|seed| {
+            let mut env = small();
+            env.params.seed = seed;
+            rooms(env).iter().any(|r| r == "r101")
+        }));
+        for seed in seeds {
+            let mut env = small();
+            env.params.seed = seed;
+            env.instance.courses[0].allowed_rooms = vec![RoomId("r202".into())];
+            assert!(rooms(env).iter().all(|r| r == "r202"));
+        }
+    }
+
+    #[test]
+    fn anti_affine_courses_never_share_a_timeslot() {
+        let mut env = small();
+        env.instance.policy.soft_weights = SoftWeights {
+            unpreferred_time: 10,
+            ..Default::default()
+        };
+        // Both teachers would rather start on mon.1 than anywhere else
+        // left, so unconstrained the two courses meet there together.
+        env.instance.courses[0].countPerWeek = 1;
+        let [kovalenko, petrenko] = &mut env.instance.teachers[..] else {
+            unreachable!()
+        };
+        kovalenko.available = slots(&["mon.1", "wed.1"]);
+        kovalenko.prefs.avoid_slots = slots(&["wed.1"]);
+        petrenko.available.extend(slots(&["wed.2", "wed.3"]));
+        petrenko.prefs.avoid_slots = slots(&["thu.1", "thu.2", "wed.2", "wed.3"]);
+
+        let shared = |env: SolveEnvelope| -> bool {
+            let inst = env.instance.clone();
+            let res = solved(env);
+            let covered = |cid: &str| -> HashSet<usize> {
+                let c = inst.courses.iter().find(|c| c.id.0 == cid).unwrap();
+                res.assignments
+                    .iter()
+                    .filter(|a| a.courseId.0 == cid)
+                    .flat_map(|a| {
+                        let t0 = inst.timeslots.iter().position(|t| *t == a.timeslot);
+                        meeting_slots(&inst, t0.unwrap(), c.duration).unwrap()
+                    })
+                    .collect()
+            };
+            !covered("c_alg").is_disjoint(&covered("c_prog"))
+        };
+        assert!(shared(env.clone()));
+
+        let pair = (
+            env.instance.courses[0].id.clone(),
+            env.instance.courses[1].id.clone(),
+        );
+        env.instance.policy.no_overlap = vec![pair];
+        assert!(!shared(env));
+    }
+
+    #[test]
+    fn odd_and_even_courses_share_a_slot_without_conflict() {
+        let mut env = small();
+        // One room, one slot: the two courses only fit if they alternate weeks.
+        env.instance.rooms.truncate(1);
+        env.instance.courses.truncate(1);
+        env.instance.teachers[0].available = slots(&["mon.1"]);
+        let alg = &mut env.instance.courses[0];
+        alg.countPerWeek = 1;
+        alg.week_parity = Some(Parity::Odd);
+        let mut even = alg.clone();
+        even.id = CourseId("c_alg_even".into());
+        even.week_parity = Some(Parity::Even);
+        env.instance.courses.push(even);
+
+        let res = solved(env.clone());
+        assert_eq!(res.assignments.len(), 2);
+        assert!(res.assignments.iter().all(|a| a.timeslot.0 == "mon.1"));
+        let violations = sched_core::feasibility::hard_violations(&env.instance, &res.assignments);
+        assert!(violations.is_empty(), "{violations:?}");
+
+        env.instance.courses[1].week_parity = Some(Parity::Odd);
+        let res = solve_ga(env, &CancelToken::new()).unwrap();
+        assert_ne!(res.status, "solved");
+    }
+
+    #[tokio::test]
+    async fn degenerate_envelopes_return_instead_of_panicking() {
+        let mut env = small();
+        env.instance.courses.clear();
+        let res = HeurSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        assert!(res.assignments.is_empty());
+        assert_eq!(res.stats["method"], "ga");
+
+        let mut env = small();
+        env.instance.timeslots.clear();
+        let res = HeurSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "infeasible");
+        assert!(res.assignments.is_empty());
+        assert_eq!(res.stats["note"], "no timeslots");
+    }
+
+    #[tokio::test]
+    async fn cancelled_solve_returns_promptly_with_the_best_so_far() {
+        let env = small();
+        let inst = env.instance.clone();
+        let full = HeurSolver::new().solve(env.clone()).await.unwrap();
+        assert_eq!(full.stats["generations"], GA_ITERS);
+
+        // Tripped before the first generation: only construction runs.
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let res = HeurSolver::new()
+            .solve_cancellable(env, cancel)
+            .await
+            .unwrap();
+        assert_eq!(res.status, "cancelled");
+        assert_eq!(res.stats["generations"], 0);
+        assert_eq!(res.assignments.len(), full.assignments.len());
+        assert!(sched_core::feasibility::hard_violations(&inst, &res.assignments).is_empty());
+    }
+
+    #[test]
+    fn raising_the_active_days_weight_packs_meetings_into_fewer_days() {
+        let active_days = |weight, seed| {
+            let mut env = small();
+            env.params.seed = seed;
+            // c_alg's second meeting on the same day costs an avoided slot.
+            env.instance.teachers[0].prefs.avoid_slots = slots(&["mon.2", "tue.2"]);
+            let weights = &mut env.instance.policy.soft_weights;
+            weights.unpreferred_time = 10;
+            weights.active_days = weight;
+            let inst = env.instance.clone();
+            let res = solved(env);
+            compute_soft_scores(&inst, &res.assignments).active_days_total
+        };
+        // Any one seed may pack by chance; summed over a few, the weight shows.
+        let total = |weight| (1..=5).map(|seed| active_days(weight, seed)).sum::<i64>();
+        let (spread, packed) = (total(0), total(20));
+        assert!(packed < spread, "{packed} vs {spread}");
+    }
+
+    #[tokio::test]
+    async fn loose_budget_stops_early_and_tight_budget_keeps_searching() {
+        let with_budget = |budget| {
+            let mut env = small();
+            env.params.objectiveBudget = Some(budget);
+            env
+        };
+        let loose = HeurSolver::new().solve(with_budget(1e9)).await.unwrap();
+        assert_eq!(loose.status, "solved");
+        assert_eq!(loose.stats["generations"], 0);
+        assert_eq!(loose.stats["budget_met"], true);
+
+        // Below any reachable objective: the search runs its full course.
+        let tight = HeurSolver::new().solve(with_budget(-1.0)).await.unwrap();
+        assert_eq!(tight.status, "solved");
+        assert_eq!(tight.stats["generations"], GA_ITERS);
+        assert_eq!(tight.stats["budget_met"], false);
+    }
+
+    #[test]
+    fn meeting_index_pins_each_meeting_to_its_own_slot() {
+        let pin = |meeting, ts: &str| types::PartialPin {
+            courseId: CourseId("c_alg".into()),
+            timeslot: Some(TimeslotId(ts.into())),
+            roomId: None,
+            teacherId: None,
+            meeting: Some(meeting),
+        };
+        let alg = |env: SolveEnvelope| starts(&solved(env), "c_alg");
+
+        let mut env = small();
+        env.partial_pins = vec![pin(0, "mon.2"), pin(1, "tue.1")];
+        assert_eq!(alg(env), ["mon.2", "tue.1"]);
+
+        // Pinning tue.1 as the second meeting forces the first into the
+        // avoided Monday slots rather than tue.2 or wed.1.
+        let mut env = small();
+        env.instance.policy.soft_weights.unpreferred_time = 10;
+        let t = &mut env.instance.teachers[0];
+        t.prefs.avoid_slots = slots(&["mon.1", "mon.2"]);
+        t.prefs.preferred_days.clear();
+        env.partial_pins = vec![pin(1, "tue.1")];
+        let placed = alg(env);
+        assert!(placed[0].starts_with("mon."), "{placed:?}");
+        assert_eq!(placed[1], "tue.1");
+    }
+
+    #[test]
+    fn late_opening_room_is_never_used_early() {
+        let early_in_r202 = |seed, earliest| {
+            let mut env = small();
+            env.params.seed = seed;
+            env.instance.rooms[1].earliest = earliest;
+            let res = solved(env);
+            res.assignments
+                .iter()
+                .any(|a| a.roomId.0 == "r202" && a.timeslot.0.ends_with(".1"))
+        };
+        let seeds = 1..=5;
+        assert!(seeds.clone().any(|seed| early_in_r202(seed, None)));
+        for seed in seeds {
+            assert!(!early_in_r202(seed, Some(2)));
+        }
+    }
+
+    #[test]
+    fn teacher_window_weight_alone_compacts_the_teacher_day() {
+        let mut env = small();
+        env.instance.policy.soft_weights = SoftWeights {
+            unpreferred_time: 10,
+            windows_groups: Some(0),
+            ..Default::default()
+        };
+        let t = &mut env.instance.teachers[0];
+        t.available = slots(&["mon.1", "mon.2", "mon.3"]);
+        t.prefs.avoid_slots = slots(&["mon.2"]);
+        t.prefs.preferred_days.clear();
+        let alg = |env: SolveEnvelope| starts(&solved(env), "c_alg");
+        assert_eq!(alg(env.clone()), ["mon.1", "mon.3"]);
+
+        // Only teachers' windows get heavier; groups stay unweighted.
+        env.instance.policy.soft_weights.windows_teachers = Some(20);
+        assert!(alg(env).contains(&"mon.2".to_string()));
+    }
+
+    #[test]
+    fn rooms_used_weight_packs_meetings_into_one_room() {
+        let mut env = small();
+        env.instance.policy.soft_weights = SoftWeights {
+            unpreferred_time: 10,
+            ..Default::default()
+        };
+        env.instance.courses[0].countPerWeek = 1;
+        let t = &mut env.instance.teachers[0];
+        t.available = slots(&["mon.1", "mon.2", "wed.1"]);
+        t.prefs.avoid_slots = slots(&["wed.1"]);
+        t.prefs.preferred_days.clear();
+        env.instance.teachers[1].prefs.preferred_days.clear();
+        let solve = |env: SolveEnvelope| -> (usize, Vec<String>) {
+            let res = solved(env);
+            let rooms: HashSet<&str> = res
+                .assignments
+                .iter()
+                .map(|a| a.roomId.0.as_str())
+                .collect();
+            (rooms.len(), starts(&res, "c_alg"))
+        };
+        // c_prog always holds mon.1-2, so c_alg off wed.1 needs the other room.
+        let (rooms, alg) = solve(env.clone());
+        assert_eq!(rooms, 2);
+        assert!(!alg.contains(&"wed.1".to_string()));
+
+        // A second room now costs more than the avoided wed.1.
+        env.instance.policy.soft_weights.rooms_used = 20;
+        let (rooms, alg) = solve(env);
+        assert_eq!(rooms, 1);
+        assert!(alg.contains(&"wed.1".to_string()));
+    }
+
+    #[test]
+    fn unseatable_course_is_reported_as_no_feasible_placement() {
+        let mut env = small();
+        env.instance.groups[0].size = 100;
+        let res = solve_ga(env, &CancelToken::new()).unwrap();
+        assert_eq!(res.status, "infeasible");
+        let v = res
+            .violations
+            .iter()
+            .find(|v| v.r#type == "no_feasible_placement")
+            .unwrap();
+        assert_eq!(v.details["courseId"], "c_alg");
+        assert_eq!(v.details["reason"], "capacity");
+    }
+
+    #[test]
+    fn meetings_only_land_in_whitelisted_slots() {
+        let mut env = small();
+        let allowed = [
+            "mon.1", "mon.2", "tue.2", "tue.3", "wed.1", "thu.1", "thu.2",
+        ];
+        env.instance.policy.allowed_slots = Some(slots(&allowed));
+        for seed in 1..=5 {
+            env.params.seed = seed;
+            let res = solved(env.clone());
+            assert_eq!(res.assignments.len(), 5);
+            for a in &res.assignments {
+                assert!(allowed.contains(&a.timeslot.0.as_str()), "{}", a.timeslot.0);
+            }
+        }
+    }
+
+    #[test]
+    fn linked_courses_always_share_one_room() {
+        let rooms = |env: &SolveEnvelope| -> HashSet<String> {
+            let res = solved(env.clone());
+            res.assignments.iter().map(|a| a.roomId.0.clone()).collect()
+        };
+        let mut env = small();
+        // Room for c_alg on Wednesday, clear of c_prog's double meetings.
+        env.instance.teachers[0]
+            .available
+            .extend(slots(&["wed.2", "wed.3"]));
+        let seeds = 1..=5;
+        assert!(seeds.clone().any(|seed| {
+            env.params.seed = seed;
+            rooms(&env).len() > 1
+        }));
+
+        env.instance.policy.same_room_groups =
+            vec![vec![CourseId("c_alg".into()), CourseId("c_prog".into())]];
+        for seed in seeds {
+            env.params.seed = seed;
+            assert_eq!(rooms(&env).len(), 1, "seed {seed}");
+        }
+    }
+}
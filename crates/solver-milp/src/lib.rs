@@ -1,18 +1,22 @@
 #[cfg(feature = "with-milp")]
+mod lp_export;
+#[cfg(feature = "with-milp")]
 mod milp_core;
 
 #[cfg(feature = "with-milp")]
 use milp_core::*;
 
 use async_trait::async_trait;
+use sched_core::feasibility::linked_room;
+use sched_core::index::InstanceIndex;
+use sched_core::occupancy::{meeting_slots, room_open, slots_allowed, teacher_free, Occupancy};
 use sched_core::{SolveEnvelope, SolveResult, Solver};
 use tracing::info;
 
+#[cfg(feature = "with-milp")]
 use good_lp::Solution;
-use std::collections::{HashMap, HashSet};
-use types::{
-    Assignment, Course, CourseId, Group, Instance, Room, RoomId, Teacher, TeacherId, TimeslotId,
-};
+use std::collections::HashMap;
+use types::{Assignment, Course, GreedyOrder, Instance, Room, Teacher, TimeslotId};
 
 pub struct MilpSolver;
 impl MilpSolver {
@@ -24,21 +28,137 @@ impl MilpSolver {
 #[async_trait]
 impl Solver for MilpSolver {
     async fn solve(&self, env: SolveEnvelope) -> anyhow::Result<SolveResult> {
+        self.solve_indexed(&env, &InstanceIndex::new(&env.instance))
+            .await
+    }
+}
+
+impl MilpSolver {
+    /// `Solver::solve` over an index the caller already built for
+    /// `env.instance`, so a stage running after the MILP (heuristic repair)
+    /// can reuse it instead of indexing the instance again.
+    pub async fn solve_indexed(
+        &self,
+        env: &SolveEnvelope,
+        index: &InstanceIndex<'_>,
+    ) -> anyhow::Result<SolveResult> {
         info!(
             "received instance with {} courses",
             env.instance.courses.len()
         );
-        #[cfg(feature = "with-milp")]
-        {
-            if let Ok(r) = solve_with_milp(&env).await {
-                return Ok(r);
+        let mut res = solve_backend(env, index).await?;
+        res.stats["seed"] = serde_json::json!(env.params.seed);
+        res.stats["solver"] = serde_json::json!("milp");
+        // Only a completed backend solve proves optimality; CBC runs without
+        // a gap limit and reports an expired time limit as an error, so any
+        // solution it returns is optimal.
+        if res.stats.get("proven_optimal").is_none() {
+            res.stats["proven_optimal"] = serde_json::json!(false);
+        }
+        res.stats["params"] = serde_json::json!({
+            "backend": if cfg!(feature = "with-milp") { "cbc" } else { "greedy" },
+            "time_limit_sec": env.params.timeLimitSec,
+            "relax_pins": env.params.relaxPins,
+            "best_effort": env.params.bestEffort,
+            "objective_scale": env.params.objectiveScale.unwrap_or(1).max(1),
+            "greedy_order": env.params.greedyOrder,
+            "allow_fallback": env.params.allowFallback,
+            "objective_budget": env.params.objectiveBudget,
+            "two_phase": env.params.twoPhase,
+        });
+        if let Some(budget) = env.params.objectiveBudget {
+            res.stats["budget_met"] = serde_json::json!(sched_core::budget_met(&res, budget));
+        }
+        res.stats["reproduce"] = serde_json::json!(sched_core::fingerprint::reproduce_token(env));
+        res.stats["placement"] =
+            sched_core::feasibility::placement_report(&env.instance, &res.assignments);
+        Ok(res)
+    }
+}
+
+/// The MILP `env` would be solved with, as CPLEX LP text for running in
+/// an external solver.
+pub fn export_lp(env: &SolveEnvelope) -> anyhow::Result<String> {
+    #[cfg(feature = "with-milp")]
+    return Ok(lp_export::write_lp(env));
+    #[cfg(not(feature = "with-milp"))]
+    {
+        let _ = env;
+        anyhow::bail!("MILP backend not built (feature with-milp)")
+    }
+}
+
+async fn solve_backend(
+    env: &SolveEnvelope,
+    index: &InstanceIndex<'_>,
+) -> anyhow::Result<SolveResult> {
+    if let Some(res) = sched_core::degenerate_result(&env.instance, "milp") {
+        return Ok(res);
+    }
+    #[cfg(feature = "with-milp")]
+    let deadline = Deadline::after_secs(env.params.timeLimitSec);
+    #[cfg(feature = "with-milp")]
+    let (reason, error, timed_out) = match solve_with_milp(env, index, deadline).await {
+        // The time limit ran out before CBC reported a solution; a greedy
+        // schedule is more useful than the bare pins.
+        Ok(r) if r.status == "timeout" => ("milp_timeout", None, Some(r)),
+        Ok(r) => {
+            if r.status == "infeasible" && env.params.relaxPins && !env.pinned.is_empty() {
+                if let Some(relaxed) = relax_pins(env, index, deadline).await {
+                    return Ok(relaxed);
+                }
             }
+            return Ok(r);
+        }
+        Err(e) => {
+            tracing::warn!("milp backend failed, falling back to greedy: {e}");
+            ("milp_error", Some(e.to_string()), None)
+        }
+    };
+    #[cfg(not(feature = "with-milp"))]
+    let (reason, error, timed_out): (&str, Option<String>, Option<SolveResult>) = {
+        let _ = index;
+        ("feature_disabled", None, None)
+    };
+    fall_back(env, reason, error, timed_out)
+}
+
+/// Greedy schedule standing in for a MILP answer lost to `reason`; a
+/// `timed_out` MILP result is returned as is when fallback is off.
+fn fall_back(
+    env: &SolveEnvelope,
+    reason: &str,
+    error: Option<String>,
+    timed_out: Option<SolveResult>,
+) -> anyhow::Result<SolveResult> {
+    if !env.params.allowFallback {
+        if let Some(r) = timed_out {
+            return Ok(r);
+        }
+        anyhow::bail!(
+            "milp backend unavailable ({reason}{}) and allowFallback is false",
+            error.map(|e| format!(": {e}")).unwrap_or_default()
+        );
+    }
+
+    let mut res = solve_greedy(&env.instance, env.params.bestEffort, env.params.greedyOrder);
+    res.stats["fallback"] = serde_json::json!(true);
+    res.stats["fallback_reason"] = serde_json::json!(reason);
+    if reason == "milp_timeout" {
+        res.stats["fallback_after_timeout"] = serde_json::json!(true);
+        // Greedy failing proves nothing the MILP could not show in time.
+        if res.status == "infeasible" {
+            res.status = "timeout".into();
+            res.stats["reason"] = serde_json::json!("time_limit");
         }
-        Ok(solve_greedy(&env.instance))
     }
+    if let Some(e) = error {
+        res.stats["fallback_error"] = serde_json::json!(e);
+    }
+    Ok(res)
 }
 
-fn solve_greedy(inst: &Instance) -> SolveResult {
+fn solve_greedy(inst: &Instance, best_effort: bool, order: GreedyOrder) -> SolveResult {
     let times: Vec<String> = inst.timeslots.iter().map(|t| t.0.clone()).collect();
 
     let group_size: HashMap<&str, u32> = inst
@@ -49,138 +169,135 @@ fn solve_greedy(inst: &Instance) -> SolveResult {
     let teacher_by_id: HashMap<&str, &Teacher> =
         inst.teachers.iter().map(|t| (t.id.0.as_str(), t)).collect();
 
-    let mut occ_room: HashMap<(&str, usize), bool> = HashMap::new();
-    let mut occ_teacher: HashMap<(&str, usize), bool> = HashMap::new();
-    let mut occ_group: HashMap<(&str, usize), bool> = HashMap::new();
+    let mut occ = Occupancy::new(inst);
 
     let mut assignments: Vec<Assignment> = Vec::new();
     let mut infeasible = false;
 
-    let is_teacher_available = |teacher: &Teacher, t: usize, dur2: bool| -> bool {
-        if teacher.available.is_empty() {
-            return !dur2 || (t + 1 < times.len());
-        }
-        let has_t = teacher.available.iter().any(|x| x.0 == times[t]);
-        if !dur2 {
-            return has_t;
-        }
-        let has_t1 = t + 1 < times.len() && teacher.available.iter().any(|x| x.0 == times[t + 1]);
-        has_t && has_t1
-    };
-
     let room_ok_for_course = |room: &Room, course: &Course| -> bool {
         let gsz = group_size
             .get(course.groupId.0.as_str())
             .copied()
             .unwrap_or(0);
-        if room.capacity < gsz {
+        if room.capacity < gsz || !course.allows_room(&room.id) {
             return false;
         }
-        for need in &course.needs {
-            if !room.equip.contains(need) {
-                return false;
-            }
-        }
-        true
+        sched_core::equip::room_meets_needs(room, &course.needs, &inst.policy)
+    };
+
+    let feasible_starts = |c: &Course| -> usize {
+        let Some(teacher) = teacher_by_id.get(c.teacherId.0.as_str()) else {
+            return 0;
+        };
+        let rooms = inst
+            .rooms
+            .iter()
+            .filter(|r| room_ok_for_course(r, c))
+            .count();
+        let slots = (0..times.len())
+            .filter(|&t| {
+                meeting_slots(inst, t, c.duration)
+                    .is_some_and(|s| slots_allowed(inst, &s) && teacher_free(inst, teacher, &s))
+            })
+            .count();
+        slots * rooms
     };
 
-    'course_loop: for c in &inst.courses {
-        let dur2 = c.duration == 2;
+    let mut courses: Vec<&Course> = inst.courses.iter().collect();
+    match order {
+        GreedyOrder::Instance => {}
+        GreedyOrder::Priority => {
+            courses.sort_by_key(|c| std::cmp::Reverse(c.priority_or_default()));
+        }
+        GreedyOrder::MostConstrainedFirst => {
+            courses.sort_by_cached_key(|c| {
+                (
+                    feasible_starts(c),
+                    std::cmp::Reverse(c.priority_or_default()),
+                )
+            });
+        }
+    }
+
+    'course_loop: for c in courses {
         let mut placed = 0u32;
 
-        for t in 0..times.len() {
-            if dur2 && t + 1 >= times.len() {
-                break;
+        for (t, time) in times.iter().enumerate() {
+            let Some(slots) = meeting_slots(inst, t, c.duration) else {
+                continue;
+            };
+            if !slots_allowed(inst, &slots) {
+                continue;
             }
             let teacher = match teacher_by_id.get(c.teacherId.0.as_str()) {
                 Some(tch) => *tch,
+                None if best_effort => {
+                    infeasible = true;
+                    continue 'course_loop;
+                }
                 None => {
                     infeasible = true;
                     break 'course_loop;
                 }
             };
-            if !is_teacher_available(teacher, t, dur2) {
+            if !teacher_free(inst, teacher, &slots) {
                 continue;
             }
 
             for r in &inst.rooms {
-                if !room_ok_for_course(r, c) {
+                if !room_ok_for_course(r, c) || !room_open(inst, r, &slots) {
+                    continue;
+                }
+                if linked_room(inst, &c.id, &assignments).is_some_and(|lr| *lr != r.id) {
                     continue;
                 }
 
-                let clash = || -> bool {
-                    // room
-                    if *occ_room.get(&(r.id.0.as_str(), t)).unwrap_or(&false) {
-                        return true;
-                    }
-                    if dur2 && *occ_room.get(&(r.id.0.as_str(), t + 1)).unwrap_or(&false) {
-                        return true;
-                    }
-                    // teacher
-                    let tid = teacher.id.0.as_str();
-                    if *occ_teacher.get(&(tid, t)).unwrap_or(&false) {
-                        return true;
-                    }
-                    if dur2 && *occ_teacher.get(&(tid, t + 1)).unwrap_or(&false) {
-                        return true;
-                    }
-                    // group
-                    let gid = c.groupId.0.as_str();
-                    if *occ_group.get(&(gid, t)).unwrap_or(&false) {
-                        return true;
-                    }
-                    if dur2 && *occ_group.get(&(gid, t + 1)).unwrap_or(&false) {
-                        return true;
-                    }
-                    false
-                }();
-
-                if clash {
+                if !occ.is_free(&r.id.0, &teacher.id.0, &c.groupId.0, &slots, c.parity())
+                    || !occ.course_free(&c.id.0, &r.id.0, &slots)
+                {
                     continue;
                 }
 
                 assignments.push(Assignment {
                     courseId: c.id.clone(),
-                    timeslot: TimeslotId(times[t].clone()),
+                    timeslot: TimeslotId(time.clone()),
                     roomId: r.id.clone(),
                     teacherId: c.teacherId.clone(),
+                    meta: c.meta.clone(),
                 });
 
-                *occ_room.entry((r.id.0.as_str(), t)).or_default() = true;
-                *occ_teacher.entry((teacher.id.0.as_str(), t)).or_default() = true;
-                *occ_group.entry((c.groupId.0.as_str(), t)).or_default() = true;
-                if dur2 {
-                    *occ_room.entry((r.id.0.as_str(), t + 1)).or_default() = true;
-                    *occ_teacher
-                        .entry((teacher.id.0.as_str(), t + 1))
-                        .or_default() = true;
-                    *occ_group.entry((c.groupId.0.as_str(), t + 1)).or_default() = true;
-                }
+                occ.insert(&r.id.0, &teacher.id.0, &c.groupId.0, &slots, c.parity());
+                occ.insert_course(&c.id.0, &r.id.0, &slots);
 
                 placed += 1;
-                if placed == c.countPerWeek {
+                if placed == c.required_meetings() {
                     break;
                 }
             }
-            if placed == c.countPerWeek {}
+            if placed == c.required_meetings() {
+                break;
+            }
         }
 
-        if placed < c.countPerWeek {
+        if placed < c.required_meetings() {
             infeasible = true;
         }
     }
 
     SolveResult {
-        status: if infeasible {
+        status: if infeasible && best_effort {
+            "partial".into()
+        } else if infeasible {
             "infeasible".into()
         } else {
             "solved".into()
         },
-        objective: 0.0,
+        objective: sched_core::scoring::compute_soft_scores(inst, &assignments).objective,
         assignments,
-        violations: vec![],
+        violations: sched_core::feasibility::unplaceable_courses(inst),
         stats: serde_json::json!({
             "method": "greedy",
+            "order": order,
             "timeslots": inst.timeslots.len(),
             "courses": inst.courses.len(),
             "rooms": inst.rooms.len()
@@ -189,49 +306,98 @@ fn solve_greedy(inst: &Instance) -> SolveResult {
 }
 
 #[cfg(feature = "with-milp")]
-async fn solve_with_milp(env: &types::SolveEnvelope) -> anyhow::Result<SolveResult> {
+async fn solve_with_milp(
+    env: &types::SolveEnvelope,
+    index: &InstanceIndex<'_>,
+    deadline: Deadline,
+) -> anyhow::Result<SolveResult> {
     use good_lp::{default_solver, ProblemVariables, SolverModel};
 
-    let prep = build_prep(env);
+    let pin_clash = env.pinned.iter().enumerate().any(|(i, a)| {
+        env.pinned[i + 1..]
+            .iter()
+            .any(|b| pins_clash(&env.instance, a, b))
+    });
+    if pin_clash {
+        return Ok(SolveResult {
+            status: "infeasible".into(),
+            objective: 0.0,
+            assignments: env.pinned.clone(),
+            violations: vec![],
+            stats: serde_json::json!({"method":"milp","note":"pinned assignments clash","pinned":env.pinned.len(),"base":env.base.len()}),
+        });
+    }
+
+    let mut prep = build_prep(env, index);
 
     let mut pvars = ProblemVariables::new();
-    let starts = declare_starts(&prep, &mut pvars);
-    if starts.is_empty() {
+    let v = declare_vars(&prep, &mut pvars);
+    if v.starts.is_empty() {
         return Ok(SolveResult {
             status: "infeasible".into(),
             objective: 0.0,
             assignments: env.pinned.clone(),
-            violations: vec![],
+            violations: sched_core::feasibility::unplaceable_courses(&env.instance),
             stats: serde_json::json!({"method":"milp","note":"no feasible start variables","pinned":env.pinned.len(),"base":env.base.len()}),
         });
     }
-    let (ot, og) = declare_occupancy_vars(&prep, &mut pvars);
-    let (adj_t, adj_g) = declare_adjacency_vars(&prep, &mut pvars, &ot, &og);
-    let v = milp_core::Vars {
-        starts,
-        ot,
-        og,
-        adj_t,
-        adj_g,
-    };
 
-    let objective = build_objective(&prep, &v);
+    if let Some(budget) = env.params.objectiveBudget {
+        if let Some(res) = solve_within_budget(&prep, env, budget, deadline) {
+            return Ok(res);
+        }
+    }
+
+    let started = std::time::Instant::now();
+    let phase1 = env
+        .params
+        .twoPhase
+        .then(|| prep.with_feasibility_only(|prep| feasibility_phase(env, prep, deadline)));
+    let phase1_secs = started.elapsed().as_secs_f64();
+    let first = phase1.flatten();
 
-    let mut model = pvars.minimise(objective.clone()).using(default_solver);
-    model = add_course_count_constraints(model, &prep, &v);
-    model = add_room_capacity_constraints(model, &prep, &v);
-    model = add_teacher_capacity_constraints(model, &prep, &v);
-    model = add_group_capacity_constraints(model, &prep, &v);
-    model = link_occupancy(model, &prep, &v);
-    model = add_adjacency_constraints(model, &v);
-    model = add_partial_lock_constraints(model, &prep, &v);
+    let (bounds, stages) = lexicographic_stages(&prep, deadline);
+    if deadline.expired() {
+        return Ok(match first {
+            Some(first) => with_phases(first, phase1_secs, None),
+            None => timed_out(env),
+        });
+    }
 
-    match model.solve() {
-        Ok(sol) => {
+    let phase2_started = std::time::Instant::now();
+    // `solve` consumes the model, so each attempt declares it afresh.
+    let (solved, retries) = retry_transient(env.params.seed, deadline, || {
+        let mut pvars = ProblemVariables::new();
+        let v = declare_vars(&prep, &mut pvars);
+        let mut model = pvars
+            .minimise(build_objective(&prep, &v))
+            .using(default_solver);
+        set_time_limit(&mut model, deadline);
+        let mut model = add_constraints(model, &prep, &v, None);
+        for &(term, bound) in &bounds {
+            model = model.with(term_objective(&prep, &v, term).leq(bound));
+        }
+        model.solve().map(|sol| (v, sol))
+    })
+    .await;
+    let phase2_secs = phase2_started.elapsed().as_secs_f64();
+    if let (Err(_), Some(first)) = (&solved, first.clone()) {
+        // The soft phase ran out of time (or failed numerically); the
+        // feasible first-phase schedule still beats a greedy fallback.
+        return Ok(with_phases(first, phase1_secs, Some(phase2_secs)));
+    }
+    let two_phase = first.is_some();
+    match solved {
+        Ok((v, sol)) => {
             let assignments = extract_solution(&prep, &v, &sol);
-            Ok(SolveResult {
-                status: "solved".into(),
-                objective: sol.eval(objective.clone()),
+            let unmet: f64 = v.unmet.iter().map(|&(_, u)| sol.value(u)).sum();
+            let res = SolveResult {
+                status: if unmet > 0.5 {
+                    "partial".into()
+                } else {
+                    "solved".into()
+                },
+                objective: sol.eval(soft_objective(&prep, &v)) / prep.objective_scale,
                 assignments,
                 violations: vec![],
                 stats: serde_json::json!({
@@ -241,16 +407,788 @@ async fn solve_with_milp(env: &types::SolveEnvelope) -> anyhow::Result<SolveResu
                     "courses": prep.inst.courses.len(),
                     "rooms": prep.inst.rooms.len(),
                     "pinned": env.pinned.len(),
-                    "base": env.base.len()
+                    "base": env.base.len(),
+                    "objective_scale": prep.objective_scale,
+                    "lexicographic": stages,
+                    "retries": retries,
+                    "proven_optimal": true
                 }),
+            };
+            Ok(if two_phase {
+                let mut res = with_phases(res, phase1_secs, Some(phase2_secs));
+                res.stats["phase"] = serde_json::json!(2);
+                res
+            } else {
+                res
             })
         }
+        Err(good_lp::ResolutionError::Other("Stopped")) => Ok(timed_out(env)),
         Err(e) => Ok(SolveResult {
             status: "infeasible".into(),
             objective: 0.0,
             assignments: env.pinned.clone(),
             violations: vec![],
-            stats: serde_json::json!({"method":"milp","error": e.to_string(),"pinned":env.pinned.len(),"base":env.base.len()}),
+            stats: serde_json::json!({
+                "method": "milp",
+                "error": e.to_string(),
+                "retries": retries,
+                "infeasible_cause": probe_infeasible_cause(&prep, deadline),
+                "pinned": env.pinned.len(),
+                "base": env.base.len()
+            }),
+        }),
+    }
+}
+
+/// End of the `timeLimitSec` budget. One deadline is shared by every CBC
+/// run a request makes (budget, both phases, lexicographic stages, the
+/// infeasibility probe and pin relaxation), so together they stay within
+/// the limit instead of each getting it afresh.
+#[cfg(feature = "with-milp")]
+#[derive(Debug, Clone, Copy)]
+struct Deadline(Option<std::time::Instant>);
+
+#[cfg(feature = "with-milp")]
+impl Deadline {
+    /// 0 means unlimited, as in `SolveParams.timeLimitSec`.
+    fn after_secs(secs: u64) -> Self {
+        Deadline(
+            (secs > 0).then(|| std::time::Instant::now() + std::time::Duration::from_secs(secs)),
+        )
+    }
+
+    /// Time left, `None` when unlimited.
+    fn remaining(self) -> Option<std::time::Duration> {
+        self.0
+            .map(|end| end.saturating_duration_since(std::time::Instant::now()))
+    }
+
+    fn expired(self) -> bool {
+        self.remaining().is_some_and(|left| left.is_zero())
+    }
+}
+
+/// Caps CBC's wall-clock time at what is left of `deadline`. CBC reports a
+/// stop on the limit as an error even when it holds an incumbent, so an
+/// expired limit always counts as "no solution".
+#[cfg(feature = "with-milp")]
+fn set_time_limit(model: &mut good_lp::solvers::coin_cbc::CoinCbcProblem, deadline: Deadline) {
+    if let Some(left) = deadline.remaining() {
+        let secs = left.as_secs_f64().max(0.001);
+        model.set_parameter("seconds", &format!("{secs:.3}"));
+    }
+}
+
+/// Result for a solve the time limit stopped before CBC found a schedule.
+#[cfg(feature = "with-milp")]
+fn timed_out(env: &types::SolveEnvelope) -> SolveResult {
+    SolveResult {
+        status: "timeout".into(),
+        objective: 0.0,
+        assignments: env.pinned.clone(),
+        violations: vec![],
+        stats: serde_json::json!({
+            "method": "milp",
+            "timed_out": true,
+            "reason": "time_limit",
+            "time_limit_sec": env.params.timeLimitSec,
+            "pinned": env.pinned.len(),
+            "base": env.base.len()
+        }),
+    }
+}
+
+/// Retries after the first attempt that `retry_transient` allows.
+#[cfg(feature = "with-milp")]
+const MAX_SOLVE_RETRIES: u32 = 2;
+
+/// Runs `attempt` until it succeeds or fails in a way a retry cannot change,
+/// at most `MAX_SOLVE_RETRIES` more times, sleeping a doubling backoff with
+/// seeded jitter in between. The backoff yields to the runtime rather than
+/// blocking its worker thread. Infeasible, unbounded and a time-limit stop are
+/// answers rather than backend hiccups, and no retry starts once `deadline`
+/// has passed. Returns the last result and the number of retries made.
+#[cfg(feature = "with-milp")]
+async fn retry_transient<T>(
+    seed: u64,
+    deadline: Deadline,
+    mut attempt: impl FnMut() -> Result<T, good_lp::ResolutionError>,
+) -> (Result<T, good_lp::ResolutionError>, u32) {
+    use good_lp::ResolutionError;
+
+    let mut retries = 0;
+    loop {
+        let res = attempt();
+        let transient = match &res {
+            Err(ResolutionError::Other(msg)) => *msg != "Stopped",
+            Err(ResolutionError::Str(_)) => true,
+            _ => false,
+        };
+        if !transient || retries == MAX_SOLVE_RETRIES || deadline.expired() {
+            return (res, retries);
+        }
+        retries += 1;
+        let jitter =
+            sched_core::rng::derive_seed(seed, sched_core::rng::RETRY + retries as u64) % 100;
+        info!(retries, "transient MILP backend error, retrying");
+        let pause = std::time::Duration::from_millis((100 << retries) + jitter);
+        tokio::time::sleep(deadline.remaining().map_or(pause, |left| pause.min(left))).await;
+    }
+}
+
+/// First phase of `SolveParams.twoPhase`: the hard constraints alone, with
+/// no soft-term variables, which CBC usually satisfies far faster than it
+/// proves a weighted optimum. The objective is scored afterwards so the
+/// result is comparable with a full solve. `None` when no schedule is found.
+#[cfg(feature = "with-milp")]
+fn feasibility_phase(
+    env: &types::SolveEnvelope,
+    prep: &Prep,
+    deadline: Deadline,
+) -> Option<SolveResult> {
+    use good_lp::{default_solver, ProblemVariables, SolverModel};
+
+    let mut pvars = ProblemVariables::new();
+    let v = declare_vars(prep, &mut pvars);
+    let mut model = pvars
+        .minimise(unmet_objective(prep, &v))
+        .using(default_solver);
+    set_time_limit(&mut model, deadline);
+    let sol = add_constraints(model, prep, &v, None).solve().ok()?;
+    let unmet: f64 = v.unmet.iter().map(|&(_, u)| sol.value(u)).sum();
+    let assignments = extract_solution(prep, &v, &sol);
+    let objective = sched_core::scoring::compute_soft_scores(&env.instance, &assignments).objective
+        + sol.eval(unmet_objective(prep, &v)) / prep.objective_scale;
+    Some(SolveResult {
+        status: if unmet > 0.5 {
+            "partial".into()
+        } else {
+            "solved".into()
+        },
+        objective,
+        assignments,
+        violations: vec![],
+        stats: serde_json::json!({
+            "method": "milp",
+            "phase": 1,
+            "timeslots": prep.inst.timeslots.len(),
+            "courses": prep.inst.courses.len(),
+            "rooms": prep.inst.rooms.len(),
+            "pinned": env.pinned.len(),
+            "base": env.base.len(),
+            "objective_scale": prep.objective_scale,
+            "proven_optimal": false
+        }),
+    })
+}
+
+/// Records two-phase timings; `phase2_secs` is `None` when no time was
+/// left for the second phase. `warm_start` is always false: the second
+/// phase cannot be handed the first one's schedule as a CBC MIP start.
+#[cfg(feature = "with-milp")]
+fn with_phases(mut res: SolveResult, phase1_secs: f64, phase2_secs: Option<f64>) -> SolveResult {
+    res.stats["phases"] = serde_json::json!({
+        "feasibility_secs": phase1_secs,
+        "optimize_secs": phase2_secs,
+        "warm_start": false,
+    });
+    res
+}
+
+/// Satisficing solve: the weighted objective becomes a `<= budget`
+/// constraint and the backend only looks for a feasible point, which is
+/// usually far quicker than proving an optimum. `None` when nothing fits
+/// under the budget (or the time limit ran out), so the caller optimizes as
+/// usual and reports the budget as missed.
+#[cfg(feature = "with-milp")]
+fn solve_within_budget(
+    prep: &Prep,
+    env: &types::SolveEnvelope,
+    budget: f64,
+    deadline: Deadline,
+) -> Option<SolveResult> {
+    use good_lp::{default_solver, Expression, ProblemVariables, SolverModel};
+
+    let mut pvars = ProblemVariables::new();
+    let v = declare_vars(prep, &mut pvars);
+    let objective = soft_objective(prep, &v);
+    let mut model = pvars.minimise(Expression::from(0.0)).using(default_solver);
+    set_time_limit(&mut model, deadline);
+    let model = add_constraints(model, prep, &v, None)
+        .with(objective.clone().leq(budget * prep.objective_scale));
+    let sol = model.solve().ok()?;
+    let unmet: f64 = v.unmet.iter().map(|&(_, u)| sol.value(u)).sum();
+    Some(SolveResult {
+        status: if unmet > 0.5 {
+            "partial".into()
+        } else {
+            "solved".into()
+        },
+        objective: sol.eval(objective) / prep.objective_scale,
+        assignments: extract_solution(prep, &v, &sol),
+        violations: vec![],
+        stats: serde_json::json!({
+            "method": "milp",
+            "satisficing": true,
+            "timeslots": prep.inst.timeslots.len(),
+            "courses": prep.inst.courses.len(),
+            "rooms": prep.inst.rooms.len(),
+            "pinned": env.pinned.len(),
+            "base": env.base.len(),
+            "objective_scale": prep.objective_scale,
+            "proven_optimal": false
         }),
+    })
+}
+
+/// Slack on a stage optimum when it becomes a bound, so float noise in the
+/// backend does not make the next stage infeasible.
+#[cfg(feature = "with-milp")]
+const LEX_TOLERANCE: f64 = 1e-6;
+
+/// Minimizes each `policy.objective_order` term in turn, every stage bounded
+/// by the optima before it. Returns the bounds for the final weighted solve
+/// and the per-stage optima for `stats`. A stage that fails, or the deadline
+/// passing, stops the sequence; the final solve then reports the failure as
+/// usual.
+#[cfg(feature = "with-milp")]
+fn lexicographic_stages(
+    prep: &Prep,
+    deadline: Deadline,
+) -> (Vec<(types::ObjectiveTerm, f64)>, Vec<serde_json::Value>) {
+    use good_lp::{default_solver, ProblemVariables, SolverModel};
+
+    let mut bounds: Vec<(types::ObjectiveTerm, f64)> = Vec::new();
+    let mut stages = Vec::new();
+    for &term in &prep.inst.policy.objective_order {
+        if deadline.expired() {
+            break;
+        }
+        let mut pvars = ProblemVariables::new();
+        let v = declare_vars(prep, &mut pvars);
+        let term_expr = term_objective(prep, &v, term);
+        // Unplaced meetings still dominate, so a stage cannot buy a better
+        // term value by dropping meetings in best-effort mode.
+        let stage_objective = unmet_objective(prep, &v) + term_expr.clone();
+        let mut model = pvars.minimise(stage_objective).using(default_solver);
+        set_time_limit(&mut model, deadline);
+        let mut model = add_constraints(model, prep, &v, None);
+        for &(t, bound) in &bounds {
+            model = model.with(term_objective(prep, &v, t).leq(bound));
+        }
+        let Ok(sol) = model.solve() else {
+            break;
+        };
+        let optimum = sol.eval(term_expr);
+        bounds.push((term, optimum + LEX_TOLERANCE));
+        stages.push(serde_json::json!({
+            "term": term,
+            "optimum": optimum / prep.objective_scale,
+        }));
+    }
+    (bounds, stages)
+}
+
+/// Re-solves with one constraint family dropped at a time, smallest family
+/// first, and returns the first whose removal makes the model feasible.
+/// Only runs after a failed solve, so the extra solves are acceptable; they
+/// share what is left of `deadline` and give up (`None`) once it passes.
+#[cfg(feature = "with-milp")]
+fn probe_infeasible_cause(prep: &Prep, deadline: Deadline) -> Option<&'static str> {
+    use good_lp::{default_solver, ProblemVariables, SolverModel};
+
+    let mut families: Vec<(&'static str, usize)> = CONSTRAINT_FAMILIES
+        .iter()
+        .map(|&f| (f, family_size(prep, f)))
+        .filter(|&(_, n)| n > 0)
+        .collect();
+    families.sort_by_key(|&(_, n)| n);
+
+    for (family, _) in families {
+        if deadline.expired() {
+            return None;
+        }
+        let mut pvars = ProblemVariables::new();
+        let v = declare_vars(prep, &mut pvars);
+        let objective = build_objective(prep, &v);
+        let mut model = pvars.minimise(objective).using(default_solver);
+        set_time_limit(&mut model, deadline);
+        if add_constraints(model, prep, &v, Some(family))
+            .solve()
+            .is_ok()
+        {
+            return Some(family);
+        }
+    }
+    None
+}
+
+#[cfg(feature = "with-milp")]
+fn pins_clash(inst: &Instance, a: &Assignment, b: &Assignment) -> bool {
+    let span = |x: &Assignment| -> Option<(usize, usize, &Course)> {
+        let c = inst.courses.iter().find(|c| c.id == x.courseId)?;
+        let t = inst.timeslots.iter().position(|t| *t == x.timeslot)?;
+        Some((t, t + c.duration.max(1) as usize - 1, c))
+    };
+    let (Some((a0, a1, ca)), Some((b0, b1, cb))) = (span(a), span(b)) else {
+        return false;
+    };
+    if a1 < b0 || b1 < a0 || !ca.parity().overlaps(cb.parity()) {
+        return false;
+    }
+    a.roomId == b.roomId || ca.teacherId == cb.teacherId || ca.groupId == cb.groupId
+}
+
+/// Greedily drops pins until the MILP becomes feasible. Pins that clash with
+/// the most other pins are tried first; a single removal that restores
+/// feasibility always wins over a forced one. Every trial solve shares
+/// `deadline`, and relaxation gives up once it passes.
+#[cfg(feature = "with-milp")]
+async fn relax_pins(
+    env: &types::SolveEnvelope,
+    index: &InstanceIndex<'_>,
+    deadline: Deadline,
+) -> Option<SolveResult> {
+    let mut env = env.clone();
+    let mut relaxed: Vec<Assignment> = Vec::new();
+
+    loop {
+        if deadline.expired() {
+            return None;
+        }
+        if !relaxed.is_empty() {
+            let mut r = solve_with_milp(&env, index, deadline).await.ok()?;
+            if r.status == "solved" {
+                r.stats["relaxed_pins"] = serde_json::json!(relaxed);
+                return Some(r);
+            }
+        }
+        if env.pinned.is_empty() {
+            return None;
+        }
+
+        let mut order: Vec<(usize, usize)> = (0..env.pinned.len())
+            .map(|i| {
+                let clashes = env
+                    .pinned
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, p)| j != i && pins_clash(&env.instance, &env.pinned[i], p))
+                    .count();
+                (i, clashes)
+            })
+            .collect();
+        order.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+
+        for &(i, _) in &order {
+            if deadline.expired() {
+                return None;
+            }
+            let mut trial = env.clone();
+            let dropped = trial.pinned.remove(i);
+            let Ok(mut r) = solve_with_milp(&trial, index, deadline).await else {
+                continue;
+            };
+            if r.status == "solved" {
+                relaxed.push(dropped);
+                r.stats["relaxed_pins"] = serde_json::json!(relaxed);
+                return Some(r);
+            }
+        }
+
+        relaxed.push(env.pinned.remove(order[0].0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "with-milp")]
+    use types::RoomId;
+
+    fn small() -> SolveEnvelope {
+        serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap()
+    }
+
+    #[cfg(feature = "with-milp")]
+    fn meet(inst: &Instance, course: &str, ts: &str, room: &str) -> Assignment {
+        let c = inst.courses.iter().find(|c| c.id.0 == course).unwrap();
+        Assignment {
+            courseId: c.id.clone(),
+            timeslot: TimeslotId(ts.into()),
+            roomId: RoomId(room.into()),
+            teacherId: c.teacherId.clone(),
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn best_effort_greedy_keeps_the_high_priority_course() {
+        let mut inst = small().instance;
+        inst.teachers[0].available = vec![TimeslotId("mon.1".into()), TimeslotId("mon.2".into())];
+        let (alg, prog) = (0, 1);
+        inst.courses[alg].priority = Some(5);
+        inst.courses[prog].groupId = inst.courses[alg].groupId.clone();
+        inst.courses[prog].teacherId = inst.courses[alg].teacherId.clone();
+
+        assert_eq!(
+            solve_greedy(&inst, false, GreedyOrder::Priority).status,
+            "infeasible"
+        );
+        let res = solve_greedy(&inst, true, GreedyOrder::Priority);
+        assert_eq!(res.status, "partial");
+        let placement = sched_core::feasibility::placement_report(&inst, &res.assignments);
+        assert_eq!(placement["c_alg"]["placed"], 2);
+        assert_eq!(placement["c_prog"]["placed"], 0);
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[test]
+    fn probe_sizes_families_and_stops_at_the_deadline() {
+        let mut env = small();
+        env.instance.groups[0].max_weekly = Some(1);
+        let index = InstanceIndex::new(&env.instance);
+        let prep = build_prep(&env, &index);
+        let slots = env.instance.timeslots.len();
+        assert_eq!(family_size(&prep, "course_count"), 2);
+        assert_eq!(family_size(&prep, "room_capacity"), 2 * slots);
+        assert_eq!(family_size(&prep, "group_weekly_cap"), 1);
+        assert_eq!(family_size(&prep, "same_room"), 0);
+
+        let expired = Deadline(Some(std::time::Instant::now()));
+        assert_eq!(probe_infeasible_cause(&prep, expired), None);
+    }
+
+    #[test]
+    fn most_constrained_first_places_what_instance_order_strands() {
+        let mut inst = small().instance;
+        // c_prog shares c_alg's group and is listed first; c_alg's teacher
+        // can only make mon.1, where naive order has already put c_prog.
+        inst.courses.reverse();
+        inst.courses[0].groupId = inst.courses[1].groupId.clone();
+        inst.courses[0].duration = 1;
+        for c in &mut inst.courses {
+            c.countPerWeek = 1;
+        }
+        inst.teachers[0].available = vec![TimeslotId("mon.1".into())];
+
+        let naive = solve_greedy(&inst, false, GreedyOrder::Instance);
+        assert_eq!(naive.status, "infeasible");
+
+        let res = solve_greedy(&inst, false, GreedyOrder::MostConstrainedFirst);
+        assert_eq!(res.status, "solved");
+        let alg = res.assignments.iter().find(|a| a.courseId.0 == "c_alg");
+        assert_eq!(alg.unwrap().timeslot.0, "mon.1");
+        assert_eq!(res.assignments.len(), 2);
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[tokio::test]
+    async fn tiny_instance_reports_proven_optimality() {
+        let mut env = small();
+        env.instance.courses.truncate(1);
+        env.instance.courses[0].countPerWeek = 1;
+        env.params.timeLimitSec = 0;
+        let res = MilpSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        assert_eq!(res.stats["proven_optimal"], true);
+        assert!(res.stats.get("fallback").is_none());
+    }
+
+    #[cfg(not(feature = "with-milp"))]
+    #[tokio::test]
+    async fn missing_backend_falls_back_to_greedy_with_a_reason() {
+        let res = MilpSolver::new().solve(small()).await.unwrap();
+        assert_eq!(res.status, "solved");
+        assert_eq!(res.stats["fallback"], true);
+        assert_eq!(res.stats["fallback_reason"], "feature_disabled");
+    }
+
+    #[cfg(not(feature = "with-milp"))]
+    #[tokio::test]
+    async fn missing_backend_without_fallback_is_an_error() {
+        let mut env = small();
+        env.params.allowFallback = false;
+        let err = MilpSolver::new().solve(env).await.unwrap_err();
+        assert!(err.to_string().contains("feature_disabled"));
+        assert!(err.to_string().contains("allowFallback is false"));
+    }
+
+    /// Objective coefficients by variable name, read off the exported LP.
+    #[cfg(feature = "with-milp")]
+    fn objective_terms(env: &SolveEnvelope) -> std::collections::BTreeMap<String, f64> {
+        let lp = export_lp(env).unwrap();
+        let obj = lp.lines().find_map(|l| l.strip_prefix(" obj:")).unwrap();
+        let words: Vec<&str> = obj.split_whitespace().collect();
+        words
+            .chunks(3)
+            .map(|t| {
+                let coef: f64 = t[1].parse().unwrap();
+                (t[2].to_string(), if t[0] == "-" { -coef } else { coef })
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[test]
+    fn model_is_built_in_the_same_order_every_time() {
+        let env = small();
+        let first = export_lp(&env).unwrap();
+        for _ in 0..5 {
+            assert_eq!(export_lp(&env).unwrap(), first);
+        }
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[test]
+    fn objective_scale_multiplies_every_coefficient() {
+        let mut env = small();
+        env.instance.policy.soft_weights.load_imbalance = 3;
+        let unscaled = objective_terms(&env);
+        env.params.objectiveScale = Some(100);
+        let scaled = objective_terms(&env);
+        assert!(!unscaled.is_empty());
+        // A uniformly scaled objective has the same minimisers, so the
+        // chosen assignment cannot change.
+        assert_eq!(
+            unscaled.keys().collect::<Vec<_>>(),
+            scaled.keys().collect::<Vec<_>>()
+        );
+        for (var, coef) in &unscaled {
+            assert!((scaled[var] - 100.0 * coef).abs() < 1e-6, "{var}");
+        }
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[tokio::test]
+    async fn clashing_pins_are_reported_before_solving() {
+        let mut env = small();
+        let inst = &env.instance;
+        // c_prog lasts two slots, so it holds r101 through mon.2.
+        let prog = meet(inst, "c_prog", "mon.1", "r101");
+        assert!(pins_clash(
+            inst,
+            &prog,
+            &meet(inst, "c_alg", "mon.2", "r101")
+        ));
+        assert!(!pins_clash(
+            inst,
+            &prog,
+            &meet(inst, "c_alg", "mon.3", "r101")
+        ));
+        assert!(!pins_clash(
+            inst,
+            &prog,
+            &meet(inst, "c_alg", "mon.2", "r202")
+        ));
+
+        env.pinned = vec![prog, meet(&env.instance, "c_alg", "mon.2", "r101")];
+        let index = InstanceIndex::new(&env.instance);
+        let res = solve_with_milp(&env, &index, Deadline::after_secs(0))
+            .await
+            .unwrap();
+        assert_eq!(res.status, "infeasible");
+        assert_eq!(res.stats["note"], "pinned assignments clash");
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[tokio::test]
+    async fn lexicographic_first_term_stays_at_its_own_optimum() {
+        use sched_core::scoring::compute_soft_scores;
+        use types::{ObjectiveTerm, SoftWeights};
+
+        let mut env = small();
+        env.params.timeLimitSec = 0;
+        env.instance.policy.soft_weights = SoftWeights {
+            unpreferred_time: 1,
+            ..Default::default()
+        };
+        let alone = MilpSolver::new().solve(env.clone()).await.unwrap();
+        assert_eq!(alone.status, "solved");
+
+        // Windows weighted far above unpreferred time would trade one for
+        // the other in a single weighted solve; ranked first, it cannot.
+        env.instance.policy.soft_weights.windows = 100;
+        env.instance.policy.objective_order =
+            vec![ObjectiveTerm::UnpreferredTime, ObjectiveTerm::Windows];
+        let lex = MilpSolver::new().solve(env.clone()).await.unwrap();
+        assert_eq!(lex.status, "solved");
+
+        let stages = lex.stats["lexicographic"].as_array().unwrap();
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages[0]["term"], "unpreferred_time");
+        let optimum = stages[0]["optimum"].as_f64().unwrap();
+        assert!((optimum - alone.objective).abs() < 1e-6);
+        let unpreferred = |res: &SolveResult| {
+            compute_soft_scores(&env.instance, &res.assignments).unpreferred_meetings
+        };
+        assert_eq!(unpreferred(&lex), unpreferred(&alone));
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[test]
+    fn timeout_without_incumbent_falls_through_to_greedy() {
+        let mut env = small();
+        env.params.timeLimitSec = 1;
+        let res = fall_back(&env, "milp_timeout", None, Some(timed_out(&env))).unwrap();
+        assert_eq!(res.status, "solved");
+        assert_eq!(res.stats["fallback_after_timeout"], true);
+        assert_eq!(res.stats["fallback_reason"], "milp_timeout");
+        assert_eq!(res.assignments.len(), 5);
+
+        env.params.allowFallback = false;
+        let res = fall_back(&env, "milp_timeout", None, Some(timed_out(&env))).unwrap();
+        assert_eq!(res.status, "timeout");
+        assert!(res.stats.get("fallback").is_none());
+    }
+
+    #[tokio::test]
+    async fn degenerate_envelopes_return_instead_of_panicking() {
+        let mut env = small();
+        env.instance.courses.clear();
+        let res = MilpSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        assert!(res.assignments.is_empty());
+        assert_eq!(res.stats["method"], "milp");
+
+        let mut env = small();
+        env.instance.timeslots.clear();
+        let res = MilpSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "infeasible");
+        assert!(res.assignments.is_empty());
+        assert_eq!(res.stats["note"], "no timeslots");
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[tokio::test]
+    async fn two_phase_runs_both_phases_and_reaches_the_same_optimum() {
+        let mut env = small();
+        env.params.timeLimitSec = 0;
+        let single = MilpSolver::new().solve(env.clone()).await.unwrap();
+        assert_eq!(single.status, "solved");
+
+        env.params.twoPhase = true;
+        let res = MilpSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        assert_eq!(res.stats["phase"], 2);
+        assert!(res.stats["phases"]["feasibility_secs"].is_f64());
+        assert!(res.stats["phases"]["optimize_secs"].is_f64());
+        assert_eq!(res.stats["phases"]["warm_start"], false);
+        assert!((res.objective - single.objective).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unseatable_course_is_reported_as_no_feasible_placement() {
+        let mut env = small();
+        env.instance.groups[0].size = 100;
+        let res = solve_greedy(&env.instance, false, GreedyOrder::Instance);
+        assert_eq!(res.status, "infeasible");
+        let v = res
+            .violations
+            .iter()
+            .find(|v| v.r#type == "no_feasible_placement")
+            .unwrap();
+        assert_eq!(v.details["courseId"], "c_alg");
+        assert_eq!(v.details["reason"], "capacity");
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[tokio::test]
+    async fn tiebreak_picks_the_earliest_of_equal_schedules() {
+        let mut env = small();
+        env.params.timeLimitSec = 0;
+        env.instance.policy.soft_weights = Default::default();
+        env.instance.policy.tiebreak_early = true;
+        env.instance.courses.truncate(1);
+        env.instance.courses[0].countPerWeek = 1;
+        // Every slot is equally good; mon.1 is unavailable, tue.1 is the
+        // teacher's earliest remaining one.
+        env.instance.teachers[0].available = ["wed.1", "tue.1", "tue.2"]
+            .into_iter()
+            .map(|ts| TimeslotId(ts.into()))
+            .collect();
+        let res = MilpSolver::new().solve(env).await.unwrap();
+        assert_eq!(res.status, "solved");
+        assert_eq!(res.objective, 0.0);
+        assert_eq!(res.assignments.len(), 1);
+        assert_eq!(res.assignments[0].timeslot.0, "tue.1");
+    }
+
+    #[test]
+    fn feasible_instance_out_of_time_reports_timeout() {
+        let mut env = small();
+        env.params.allowFallback = true;
+        env.params.greedyOrder = GreedyOrder::Instance;
+        // Feasible (c_alg at mon.1, c_prog after it), but instance-order
+        // greedy gives mon.1 to c_prog first and strands c_alg.
+        let inst = &mut env.instance;
+        inst.courses.reverse();
+        inst.courses[0].groupId = inst.courses[1].groupId.clone();
+        inst.courses[0].duration = 1;
+        for c in &mut inst.courses {
+            c.countPerWeek = 1;
+        }
+        inst.teachers[0].available = vec![TimeslotId("mon.1".into())];
+
+        let res = fall_back(&env, "milp_timeout", None, None).unwrap();
+        assert_eq!(res.status, "timeout");
+        assert_eq!(res.stats["reason"], "time_limit");
+
+        // Without a time limit in play the greedy verdict stands.
+        let res = fall_back(&env, "milp_error", None, None).unwrap();
+        assert_eq!(res.status, "infeasible");
+    }
+
+    #[tokio::test]
+    async fn shared_index_gives_identical_results() {
+        let env = small();
+        let own = MilpSolver::new().solve(env.clone()).await.unwrap();
+        let index = InstanceIndex::new(&env.instance);
+        let shared = MilpSolver::new().solve_indexed(&env, &index).await.unwrap();
+        assert_eq!(own.status, shared.status);
+        assert_eq!(own.objective, shared.objective);
+        assert_eq!(
+            serde_json::to_value(&own.assignments).unwrap(),
+            serde_json::to_value(&shared.assignments).unwrap()
+        );
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[tokio::test]
+    async fn retry_recovers_when_backend_fails_once() {
+        use good_lp::ResolutionError;
+
+        let mut calls = 0;
+        let (res, retries) = retry_transient(42, Deadline::after_secs(0), || {
+            calls += 1;
+            if calls == 1 {
+                Err(ResolutionError::Str("connection reset".into()))
+            } else {
+                Ok(calls)
+            }
+        })
+        .await;
+        assert_eq!(res.unwrap(), 2);
+        assert_eq!(retries, 1);
+    }
+
+    #[cfg(feature = "with-milp")]
+    #[tokio::test]
+    async fn retry_leaves_answers_alone() {
+        use good_lp::ResolutionError;
+
+        for answer in [
+            ResolutionError::Infeasible,
+            ResolutionError::Other("Stopped"),
+        ] {
+            let mut calls = 0;
+            let (res, retries) = retry_transient(42, Deadline::after_secs(0), || {
+                calls += 1;
+                Err::<(), _>(answer.clone())
+            })
+            .await;
+            assert!(res.is_err());
+            assert_eq!((calls, retries), (1, 0));
+        }
     }
 }
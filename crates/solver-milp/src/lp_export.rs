@@ -0,0 +1,170 @@
+use crate::milp_core::{add_constraints, build_objective, build_prep, declare_vars};
+use good_lp::constraint::ConstraintReference;
+use good_lp::{
+    default_solver, Constraint, IntoAffineExpression, ProblemVariables, SolverModel,
+    VariableDefinition,
+};
+use sched_core::index::InstanceIndex;
+use std::fmt::Write;
+use types::SolveEnvelope;
+
+/// Passes constraints on to `inner` and keeps each as an LP row.
+struct Recorder<M> {
+    inner: M,
+    rows: Vec<String>,
+}
+
+impl<M: SolverModel> SolverModel for Recorder<M> {
+    type Solution = M::Solution;
+    type Error = M::Error;
+
+    fn solve(self) -> Result<Self::Solution, Self::Error> {
+        self.inner.solve()
+    }
+
+    fn add_constraint(&mut self, c: Constraint) -> ConstraintReference {
+        // good_lp prints rows as `2 v0 + -1 v1 <= -0`, naming variables the
+        // way `ProblemVariables::display` does.
+        let row = format!("{c:?}").replace("+ -", "- ");
+        let row = match row.strip_suffix(" -0") {
+            Some(head) => format!("{head} 0"),
+            None => row,
+        };
+        self.rows.push(row);
+        self.inner.add_constraint(c)
+    }
+
+    fn name() -> &'static str {
+        "LP export"
+    }
+}
+
+/// `(min, max, integer)` of `def`. good_lp keeps these private and only
+/// shows them through `Debug`.
+fn bounds(def: &VariableDefinition) -> (f64, f64, bool) {
+    let text = format!("{def:?}");
+    let field = |key: &str| {
+        text.split(key)
+            .nth(1)
+            .and_then(|rest| rest.split([',', ' ', '}']).next())
+            .unwrap_or("")
+            .to_string()
+    };
+    (
+        field("min: ").parse().unwrap_or(f64::NEG_INFINITY),
+        field("max: ").parse().unwrap_or(f64::INFINITY),
+        field("is_integer: ") == "true",
+    )
+}
+
+fn lp_number(x: f64) -> String {
+    if x == f64::INFINITY {
+        "+inf".into()
+    } else if x == f64::NEG_INFINITY {
+        "-inf".into()
+    } else {
+        x.to_string()
+    }
+}
+
+/// The weighted MILP for `env` (after masks and overrides have been applied)
+/// in CPLEX LP format. Lexicographic `objective_order` stages are solved in
+/// turn and cannot be written as one model, so only the weighted objective
+/// is exported. Variables are named `v0`, `v1`, ... in declaration order.
+pub(crate) fn write_lp(env: &SolveEnvelope) -> String {
+    let index = InstanceIndex::new(&env.instance);
+    let prep = build_prep(env, &index);
+    let mut pvars = ProblemVariables::new();
+    let v = declare_vars(&prep, &mut pvars);
+    let objective = build_objective(&prep, &v);
+
+    let vars: Vec<(String, (f64, f64, bool))> = pvars
+        .iter_variables_with_def()
+        .map(|(var, def)| (pvars.display(&var).to_string(), bounds(def)))
+        .collect();
+    let mut obj = String::new();
+    for (var, coef) in (&objective).linear_coefficients() {
+        if coef == 0.0 {
+            continue;
+        }
+        let sign = if coef < 0.0 { '-' } else { '+' };
+        let _ = write!(obj, " {sign} {} {}", coef.abs(), pvars.display(&var));
+    }
+    let offset = objective.constant();
+
+    let model = pvars.minimise(objective).using(default_solver);
+    let recorded = add_constraints(
+        Recorder {
+            inner: model,
+            rows: Vec::new(),
+        },
+        &prep,
+        &v,
+        None,
+    );
+
+    let mut out = String::from("\\ unischedule MILP\n");
+    if offset != 0.0 {
+        let _ = writeln!(out, "\\ objective constant omitted: {offset}");
+    }
+    let _ = writeln!(out, "Minimize\n obj:{obj}");
+    out.push_str("Subject To\n");
+    // Rows without variables are constant checks, not constraints.
+    for (i, row) in recorded
+        .rows
+        .iter()
+        .filter(|r| !r.starts_with("0 "))
+        .enumerate()
+    {
+        let _ = writeln!(out, " c{i}: {row}");
+    }
+    out.push_str("Bounds\n");
+    let (mut binaries, mut generals) = (Vec::new(), Vec::new());
+    for (name, (min, max, integer)) in &vars {
+        if *integer && *min == 0.0 && *max == 1.0 {
+            binaries.push(name.as_str());
+            continue;
+        }
+        if *integer {
+            generals.push(name.as_str());
+        }
+        if *min == f64::NEG_INFINITY && *max == f64::INFINITY {
+            let _ = writeln!(out, " {name} free");
+        } else if *min != 0.0 || *max != f64::INFINITY {
+            let _ = writeln!(out, " {} <= {name} <= {}", lp_number(*min), lp_number(*max));
+        }
+    }
+    for (section, names) in [("Binaries", binaries), ("Generals", generals)] {
+        if !names.is_empty() {
+            let _ = writeln!(out, "{section}\n {}", names.join(" "));
+        }
+    }
+    out.push_str("End\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unweighted_model_exports_every_binary() {
+        let mut env: SolveEnvelope =
+            serde_json::from_str(include_str!("../../../examples/small_instance.json")).unwrap();
+        env.instance.policy.soft_weights = Default::default();
+        let lp = write_lp(&env);
+        let binaries = lp
+            .lines()
+            .skip_while(|l| *l != "Binaries")
+            .nth(1)
+            .unwrap()
+            .split_whitespace()
+            .count();
+        // Starts: c_alg 5 slots and c_prog 4 double-slot starts, in either
+        // room. Occupancy: 12 slots for each of 2 teachers and 2 groups.
+        // Adjacency: 7 back-to-back slot pairs for each of those 4.
+        assert_eq!(binaries, (5 + 4) * 2 + 12 * 4 + 7 * 4);
+        assert!(!lp.contains("Generals"));
+        assert!(lp.ends_with("End\n"));
+    }
+}
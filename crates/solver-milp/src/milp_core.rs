@@ -1,13 +1,22 @@
 #![allow(clippy::needless_lifetimes)]
 
 use good_lp::{Expression, ProblemVariables, Solution, SolverModel, Variable};
-use std::collections::{HashMap, HashSet};
-use types::{Assignment, Course, Instance, Room, Teacher, TimeslotId};
+use sched_core::feasibility::same_room_sets;
+use sched_core::index::InstanceIndex;
+use sched_core::occupancy::{meeting_slots, room_open, slots_allowed, teacher_free, Occupancy};
+use sched_core::scoring::{away_from_home, ideal_day_gap, parity_pairs, window_slots};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use types::{
+    Assignment, Course, DayOfWeek, Group, Instance, ObjectiveTerm, Parity, Room, SoftWeights,
+    Teacher, TimeslotId,
+};
 
 pub(crate) struct PartialLock {
     pub c: usize,
     pub t: Option<usize>,
     pub r: Option<usize>,
+    /// Meetings of `c` that must start before `t` (`PartialPin.meeting`).
+    pub before: Option<u32>,
 }
 
 #[derive(Clone)]
@@ -20,35 +29,154 @@ pub(crate) struct StartVar {
 
 pub(crate) struct PinnedState<'a> {
     pub vec: Vec<Assignment>,
-    pub room: HashMap<(usize, usize), bool>,
-    pub teacher: HashMap<(&'a str, usize), bool>,
-    pub group: HashMap<(&'a str, usize), bool>,
+    pub occ: Occupancy<'a>,
     pub count_by_course: HashMap<usize, u32>,
     pub unpref_pinned_count: i64,
+    pub soft_pinned_count: i64,
 }
 
 pub(crate) struct Prep<'a> {
     pub inst: &'a Instance,
-    pub times: Vec<&'a str>,
-    pub day_slots: HashMap<&'a str, Vec<usize>>,
-    pub group_size: HashMap<&'a str, u32>,
-    pub teacher_by_id: HashMap<&'a str, &'a Teacher>,
+    pub weights: SoftWeights,
+    pub best_effort: bool,
+    pub objective_scale: f64,
+    pub index: &'a InstanceIndex<'a>,
     pub avoid_by_teacher: HashMap<&'a str, HashSet<&'a str>>,
-    pub idx_ts: HashMap<&'a str, usize>,
-    pub idx_room: HashMap<&'a str, usize>,
-    pub idx_course: HashMap<&'a str, usize>,
     pub teacher_ids: Vec<&'a str>,
     pub group_ids: Vec<&'a str>,
     pub pinned: PinnedState<'a>,
     pub locks: Vec<PartialLock>,
+    /// First phase of a two-phase solve: hard constraints only, no soft
+    /// terms and none of their auxiliary variables.
+    pub feasibility_only: bool,
+}
+
+impl Prep<'_> {
+    /// Whether `term` enters any objective, weighted or lexicographic, so its
+    /// auxiliary variables are needed.
+    pub fn uses(&self, term: ObjectiveTerm) -> bool {
+        !self.feasibility_only
+            && (self.weights.weight(term) > 0 || self.inst.policy.objective_order.contains(&term))
+    }
+
+    /// Runs `f` on the same problem with every soft term dropped, then
+    /// restores them, so both phases of a two-phase solve share one prep.
+    pub fn with_feasibility_only<R>(&mut self, f: impl FnOnce(&Self) -> R) -> R {
+        let weights = std::mem::take(&mut self.weights);
+        self.feasibility_only = true;
+        let out = f(self);
+        self.weights = weights;
+        self.feasibility_only = false;
+        out
+    }
 }
 
 pub(crate) struct Vars<'a> {
     pub starts: Vec<StartVar>,
-    pub ot: HashMap<(&'a str, usize), Variable>,
-    pub og: HashMap<(&'a str, usize), Variable>,
+    pub ot: BTreeMap<(&'a str, usize), Variable>,
+    pub og: BTreeMap<(&'a str, usize), Variable>,
     pub adj_t: Vec<(Variable, (&'a str, usize), (&'a str, usize))>,
     pub adj_g: Vec<(Variable, (&'a str, usize), (&'a str, usize))>,
+    pub load_dev: Vec<(&'a str, Variable)>,
+    pub spans: Vec<DaySpan<'a>>,
+    pub unmet: Vec<(usize, Variable)>,
+    pub room_used: Vec<RoomUsed>,
+    pub teacher_room_used: Vec<TeacherRoomUsed<'a>>,
+    pub adjacent: Vec<AdjacentPair>,
+    pub parity_gaps: Vec<ParityGap>,
+    pub active_days: Vec<ActiveDay>,
+    pub building_spread: Vec<BuildingSpread<'a>>,
+    /// One indicator per room some start may use and no pin already does.
+    pub rooms_in_use: Vec<(usize, Variable)>,
+    pub building_switches: Vec<BuildingSwitch<'a>>,
+    pub same_room: Vec<SameRoom>,
+    pub course_spacing: Vec<CourseSpacing>,
+}
+
+/// `met` holds one entry per weekday course `c` may meet on: an indicator
+/// that is 1 when it does, or `None` when a pin already holds the day.
+/// Each `(i, j, short, close)` is 1 when the course meets on both days `i`
+/// and `j` of `met`, which are `short` days closer than its ideal gap.
+pub(crate) struct CourseSpacing {
+    pub c: usize,
+    pub met: Vec<(DayOfWeek, Option<Variable>)>,
+    pub close: Vec<(usize, usize, f64, Variable)>,
+}
+
+/// One indicator per room the `courses` of a `policy.same_room_groups` set
+/// may meet in; at most one is 1, and every start and pin of the set sits
+/// in it.
+pub(crate) struct SameRoom {
+    pub courses: Vec<usize>,
+    pub rooms: Vec<(usize, Variable)>,
+}
+
+/// `switch` is 1 when teacher (or group) `id` meets in building `from.1` in
+/// slot `from.0` and in building `to.1` in the next slot `to.0`.
+pub(crate) struct BuildingSwitch<'a> {
+    pub teacher: bool,
+    pub id: &'a str,
+    pub from: (usize, &'a str),
+    pub to: (usize, &'a str),
+    pub distance: f64,
+    pub switch: Variable,
+}
+
+/// `used` holds one indicator per building course `c` may still meet in,
+/// 1 when it does; `pinned` counts buildings its pins already occupy, and
+/// `spread` covers the buildings beyond the first.
+pub(crate) struct BuildingSpread<'a> {
+    pub c: usize,
+    pub used: Vec<(&'a str, Variable)>,
+    pub pinned: usize,
+    pub spread: Variable,
+}
+
+/// `active` is 1 when any of the `busy` occupancy vars (one teacher's or
+/// group's slots of one day) is.
+pub(crate) struct ActiveDay {
+    pub busy: Vec<Variable>,
+    pub active: Variable,
+}
+
+/// `gap` bounds the difference between the meetings of odd course `a` and
+/// even course `b` starting in slot `t`.
+pub(crate) struct ParityGap {
+    pub a: usize,
+    pub b: usize,
+    pub t: usize,
+    pub gap: Variable,
+}
+
+/// `met` may be 1 only when some `(z, k, k1)` link is 1, i.e. course `a`
+/// ends in slot `k` and course `b` starts in the next slot `k1`.
+pub(crate) struct AdjacentPair {
+    pub a: usize,
+    pub b: usize,
+    pub met: Variable,
+    pub links: Vec<(Variable, usize, usize)>,
+}
+
+/// `used` is 1 when teacher `tid` teaches in room `r` at least once.
+pub(crate) struct TeacherRoomUsed<'a> {
+    pub tid: &'a str,
+    pub r: usize,
+    pub used: Variable,
+}
+
+/// `used` is 1 when course `c` meets in room `r` at least once.
+pub(crate) struct RoomUsed {
+    pub c: usize,
+    pub r: usize,
+    pub used: Variable,
+}
+
+pub(crate) struct DaySpan<'a> {
+    pub gid: &'a str,
+    pub slots: &'a [usize],
+    pub first: Variable,
+    pub last: Variable,
+    pub span: Variable,
 }
 
 mod prep {
@@ -58,29 +186,6 @@ mod prep {
     }
 }
 
-pub(crate) fn compute_day_slots<'a>(times: &Vec<&'a str>) -> HashMap<&'a str, Vec<usize>> {
-    let mut day_of: Vec<&str> = Vec::with_capacity(times.len());
-    let mut day_index: Vec<u32> = Vec::with_capacity(times.len());
-    for &ts in times {
-        let mut parts = ts.split('.');
-        let d = parts.next().unwrap_or("");
-        let idx = parts
-            .next()
-            .and_then(|x| x.parse::<u32>().ok())
-            .unwrap_or(0);
-        day_of.push(d);
-        day_index.push(idx);
-    }
-    let mut day_slots: HashMap<&str, Vec<usize>> = HashMap::new();
-    for k in 0..times.len() {
-        day_slots.entry(day_of[k]).or_default().push(k);
-    }
-    for v in day_slots.values_mut() {
-        v.sort_by_key(|&k| day_index[k]);
-    }
-    day_slots
-}
-
 pub(crate) fn compute_avoid_by_teacher<'a>(
     inst: &'a Instance,
 ) -> HashMap<&'a str, HashSet<&'a str>> {
@@ -94,69 +199,29 @@ pub(crate) fn compute_avoid_by_teacher<'a>(
     avoid_by_teacher
 }
 
-pub(crate) fn compute_indices<'a>(
-    inst: &'a Instance,
-) -> (
-    HashMap<&'a str, usize>,
-    HashMap<&'a str, usize>,
-    HashMap<&'a str, usize>,
-) {
-    let idx_ts = inst
-        .timeslots
-        .iter()
-        .enumerate()
-        .map(|(i, t)| (t.0.as_str(), i))
-        .collect();
-    let idx_room = inst
-        .rooms
-        .iter()
-        .enumerate()
-        .map(|(i, r)| (r.id.0.as_str(), i))
-        .collect();
-    let idx_course = inst
-        .courses
-        .iter()
-        .enumerate()
-        .map(|(i, c)| (c.id.0.as_str(), i))
-        .collect();
-    (idx_ts, idx_room, idx_course)
-}
-
-pub(crate) fn teacher_available(
-    teacher: &Teacher,
-    times: &Vec<&str>,
-    t: usize,
-    dur2: bool,
-) -> bool {
-    if teacher.available.is_empty() {
-        return !dur2 || (t + 1 < times.len());
-    }
-    let has_t = teacher.available.iter().any(|x| x.0 == times[t]);
-    if !dur2 {
-        return has_t;
+/// Whether a meeting starting at `t` touches a slot that is only soft-available.
+pub(crate) fn soft_start(teacher: &Teacher, times: &[&str], t: usize, dur2: bool) -> bool {
+    let mut soft = sched_core::availability::slot_is_soft(teacher, times[t]);
+    if dur2 && t + 1 < times.len() {
+        soft = soft || sched_core::availability::slot_is_soft(teacher, times[t + 1]);
     }
-    let has_t1 = t + 1 < times.len() && teacher.available.iter().any(|x| x.0 == times[t + 1]);
-    has_t && has_t1
+    soft
 }
 
 pub(crate) fn room_ok_for_course(
     room: &Room,
     course: &Course,
     group_size: &HashMap<&str, u32>,
+    policy: &types::Policy,
 ) -> bool {
     let gsz = group_size
         .get(course.groupId.0.as_str())
         .copied()
         .unwrap_or(0);
-    if room.capacity < gsz {
+    if room.capacity < gsz || !course.allows_room(&room.id) {
         return false;
     }
-    for need in &course.needs {
-        if !room.equip.contains(need) {
-            return false;
-        }
-    }
-    true
+    sched_core::equip::room_meets_needs(room, &course.needs, policy)
 }
 
 pub(crate) fn occupies(courses: &Vec<Course>, s: &StartVar, k: usize) -> bool {
@@ -167,23 +232,69 @@ pub(crate) fn occupies(courses: &Vec<Course>, s: &StartVar, k: usize) -> bool {
     }
 }
 
+/// Weeks a per-slot constraint over `starts` is stated for: all weeks at once
+/// unless one of the courses is biweekly, then odd and even weeks apart.
+fn constraint_weeks(courses: &[Course], starts: &[&StartVar]) -> &'static [Parity] {
+    if starts
+        .iter()
+        .all(|s| courses[s.c].parity() == Parity::Every)
+    {
+        &[Parity::Every]
+    } else {
+        &[Parity::Odd, Parity::Even]
+    }
+}
+
+/// Sum of the starts whose course meets in a `week` week.
+fn week_sum(courses: &[Course], starts: &[&StartVar], week: Parity) -> Expression {
+    let mut sum = Expression::from(0.0);
+    for s in starts
+        .iter()
+        .filter(|s| courses[s.c].parity().overlaps(week))
+    {
+        sum += s.var;
+    }
+    sum
+}
+
+/// A start whose slots the course's group would rather avoid.
+pub(crate) fn group_unpreferred_start(
+    inst: &Instance,
+    c: &Course,
+    times: &[&str],
+    t: usize,
+) -> bool {
+    let Some(g) = inst.groups.iter().find(|g| g.id == c.groupId) else {
+        return false;
+    };
+    sched_core::availability::group_avoids(g, times[t])
+        || (c.duration == 2
+            && t + 1 < times.len()
+            && sched_core::availability::group_avoids(g, times[t + 1]))
+}
+
 pub(crate) fn build_pinned<'a>(
     env: &'a types::SolveEnvelope,
-    inst: &'a Instance,
-    times: &Vec<&'a str>,
+    index: &InstanceIndex<'a>,
     avoid_by_teacher: &HashMap<&'a str, HashSet<&'a str>>,
 ) -> PinnedState<'a> {
-    let (idx_ts, idx_room, idx_course) = compute_indices(inst);
+    let InstanceIndex {
+        inst,
+        times,
+        idx_ts,
+        idx_room,
+        idx_course,
+        ..
+    } = index;
 
-    let mut room: HashMap<(usize, usize), bool> = HashMap::new();
-    let mut teacher: HashMap<(&'a str, usize), bool> = HashMap::new();
-    let mut group: HashMap<(&'a str, usize), bool> = HashMap::new();
     let mut count_by_course: HashMap<usize, u32> = HashMap::new();
     let mut unpref_pinned_count: i64 = 0;
+    let mut soft_pinned_count: i64 = 0;
     let mut vec: Vec<Assignment> = Vec::new();
+    let mut valid: Vec<&'a Assignment> = Vec::new();
 
     for a in &env.pinned {
-        let (Some(&ci), Some(&ti), Some(&ri)) = (
+        let (Some(&ci), Some(&ti), Some(_)) = (
             idx_course.get(a.courseId.0.as_str()),
             idx_ts.get(a.timeslot.0.as_str()),
             idx_room.get(a.roomId.0.as_str()),
@@ -192,27 +303,12 @@ pub(crate) fn build_pinned<'a>(
         };
 
         vec.push(a.clone());
+        valid.push(a);
         *count_by_course.entry(ci).or_default() += 1;
 
         let c = &inst.courses[ci];
         let dur2 = c.duration == 2;
-
-        room.insert((ri, ti), true);
-        if dur2 && ti + 1 < inst.timeslots.len() {
-            room.insert((ri, ti + 1), true);
-        }
-
         let tid = a.teacherId.0.as_str();
-        teacher.insert((tid, ti), true);
-        if dur2 && ti + 1 < inst.timeslots.len() {
-            teacher.insert((tid, ti + 1), true);
-        }
-
-        let gid = c.groupId.0.as_str();
-        group.insert((gid, ti), true);
-        if dur2 && ti + 1 < inst.timeslots.len() {
-            group.insert((gid, ti + 1), true);
-        }
 
         if let Some(avoid) = avoid_by_teacher.get(&tid) {
             let mut penalize = avoid.contains(times[ti]);
@@ -223,48 +319,52 @@ pub(crate) fn build_pinned<'a>(
                 unpref_pinned_count += 1;
             }
         }
+
+        if group_unpreferred_start(inst, c, times, ti) {
+            unpref_pinned_count += 1;
+        }
+
+        if let Some(t) = inst.teachers.iter().find(|t| t.id.0 == tid) {
+            if soft_start(t, times, ti, dur2) {
+                soft_pinned_count += 1;
+            }
+        }
     }
 
     PinnedState {
         vec,
-        room,
-        teacher,
-        group,
+        occ: Occupancy::from_assignments(inst, valid),
         count_by_course,
         unpref_pinned_count,
+        soft_pinned_count,
     }
 }
 
-pub(crate) fn build_prep<'a>(env: &'a types::SolveEnvelope) -> Prep<'a> {
+/// `index` must be built over `env.instance` (or an identical copy); the
+/// caller owns it so later stages can reuse it.
+pub(crate) fn build_prep<'a>(
+    env: &'a types::SolveEnvelope,
+    index: &'a InstanceIndex<'a>,
+) -> Prep<'a> {
     let inst = &env.instance;
-    let times: Vec<&str> = inst.timeslots.iter().map(|t| t.0.as_str()).collect();
-    let day_slots = compute_day_slots(&times);
-    let group_size: HashMap<&str, u32> = inst
-        .groups
-        .iter()
-        .map(|g| (g.id.0.as_str(), g.size))
-        .collect();
-    let teacher_by_id: HashMap<&str, &Teacher> =
-        inst.teachers.iter().map(|t| (t.id.0.as_str(), t)).collect();
     let avoid_by_teacher = compute_avoid_by_teacher(inst);
-    let (idx_ts, idx_room, idx_course) = compute_indices(inst);
 
     let mut teacher_ids: Vec<&str> = {
-        let mut t = HashSet::new();
+        let mut t = BTreeSet::new();
         inst.courses.iter().for_each(|c| {
             t.insert(c.teacherId.0.as_str());
         });
         t.into_iter().collect()
     };
     let group_ids: Vec<&str> = {
-        let mut g = HashSet::new();
+        let mut g = BTreeSet::new();
         inst.courses.iter().for_each(|c| {
             g.insert(c.groupId.0.as_str());
         });
         g.into_iter().collect()
     };
 
-    let pinned = build_pinned(env, inst, &times, &avoid_by_teacher);
+    let pinned = build_pinned(env, index, &avoid_by_teacher);
     for a in &env.pinned {
         if !teacher_ids.iter().any(|&x| x == a.teacherId.0.as_str()) {
             teacher_ids.push(a.teacherId.0.as_str());
@@ -273,7 +373,7 @@ pub(crate) fn build_prep<'a>(env: &'a types::SolveEnvelope) -> Prep<'a> {
 
     let mut locks: Vec<PartialLock> = Vec::new();
     for l in &env.partial_pins {
-        if let Some(&ci) = idx_course.get(l.courseId.0.as_str()) {
+        if let Some(&ci) = index.idx_course.get(l.courseId.0.as_str()) {
             let t = l
                 .timeslot
                 .as_ref()
@@ -283,83 +383,57 @@ pub(crate) fn build_prep<'a>(env: &'a types::SolveEnvelope) -> Prep<'a> {
                 .as_ref()
                 .and_then(|rr| inst.rooms.iter().position(|x| x.id == *rr))
                 .map(|x| x);
-            locks.push(PartialLock { c: ci, t, r });
+            let before = l.meeting.filter(|_| t.is_some());
+            locks.push(PartialLock {
+                c: ci,
+                t,
+                r,
+                before,
+            });
         }
     }
 
     Prep {
         inst,
-        times,
-        day_slots,
-        group_size,
-        teacher_by_id,
+        weights: sched_core::presets::effective_weights(&inst.policy),
+        best_effort: env.params.bestEffort,
+        objective_scale: env.params.objectiveScale.unwrap_or(1).max(1) as f64,
+        index,
         avoid_by_teacher,
-        idx_ts,
-        idx_room,
-        idx_course,
         teacher_ids,
         group_ids,
         pinned,
         locks,
+        feasibility_only: false,
     }
 }
 
 pub(crate) fn declare_starts<'a>(prep: &'a Prep, vars: &mut ProblemVariables) -> Vec<StartVar> {
     let mut starts = Vec::new();
     for (ci, c) in prep.inst.courses.iter().enumerate() {
-        let dur2 = c.duration == 2;
-        let teacher = match prep.teacher_by_id.get(c.teacherId.0.as_str()) {
+        let teacher = match prep.index.teacher_by_id.get(c.teacherId.0.as_str()) {
             Some(t) => *t,
             None => continue,
         };
-        for t in 0..prep.times.len() {
-            if dur2 && t + 1 >= prep.times.len() {
-                break;
+        for t in 0..prep.index.times.len() {
+            let Some(slots) = meeting_slots(prep.inst, t, c.duration) else {
+                continue;
+            };
+            if !slots_allowed(prep.inst, &slots) {
+                continue;
             }
-            if !teacher_available(teacher, &prep.times, t, dur2) {
+            if !teacher_free(prep.inst, teacher, &slots) {
                 continue;
             }
             for (ri, r) in prep.inst.rooms.iter().enumerate() {
-                if !room_ok_for_course(r, c, &prep.group_size) {
-                    continue;
-                }
-                if *prep.pinned.room.get(&(ri, t)).unwrap_or(&false) {
-                    continue;
-                }
-                if dur2 && *prep.pinned.room.get(&(ri, t + 1)).unwrap_or(&false) {
-                    continue;
-                }
-                if *prep
-                    .pinned
-                    .teacher
-                    .get(&(c.teacherId.0.as_str(), t))
-                    .unwrap_or(&false)
-                {
-                    continue;
-                }
-                if dur2
-                    && *prep
-                        .pinned
-                        .teacher
-                        .get(&(c.teacherId.0.as_str(), t + 1))
-                        .unwrap_or(&false)
-                {
-                    continue;
-                }
-                if *prep
-                    .pinned
-                    .group
-                    .get(&(c.groupId.0.as_str(), t))
-                    .unwrap_or(&false)
+                if !room_ok_for_course(r, c, &prep.index.group_size, &prep.inst.policy)
+                    || !room_open(prep.inst, r, &slots)
                 {
                     continue;
                 }
-                if dur2
-                    && *prep
-                        .pinned
-                        .group
-                        .get(&(c.groupId.0.as_str(), t + 1))
-                        .unwrap_or(&false)
+                let occ = &prep.pinned.occ;
+                if !occ.is_free(&r.id.0, &c.teacherId.0, &c.groupId.0, &slots, c.parity())
+                    || !occ.course_free(&c.id.0, &r.id.0, &slots)
                 {
                     continue;
                 }
@@ -381,18 +455,18 @@ pub(crate) fn declare_occupancy_vars<'a>(
     prep: &'a Prep,
     vars: &mut ProblemVariables,
 ) -> (
-    HashMap<(&'a str, usize), Variable>,
-    HashMap<(&'a str, usize), Variable>,
+    BTreeMap<(&'a str, usize), Variable>,
+    BTreeMap<(&'a str, usize), Variable>,
 ) {
-    let mut ot = HashMap::new();
+    let mut ot = BTreeMap::new();
     for &tid in &prep.teacher_ids {
-        for k in 0..prep.times.len() {
+        for k in 0..prep.index.times.len() {
             ot.insert((tid, k), vars.add(good_lp::variable().binary()));
         }
     }
-    let mut og = HashMap::new();
+    let mut og = BTreeMap::new();
     for &gid in &prep.group_ids {
-        for k in 0..prep.times.len() {
+        for k in 0..prep.index.times.len() {
             og.insert((gid, k), vars.add(good_lp::variable().binary()));
         }
     }
@@ -402,15 +476,28 @@ pub(crate) fn declare_occupancy_vars<'a>(
 pub(crate) fn declare_adjacency_vars<'a>(
     prep: &'a Prep,
     vars: &mut ProblemVariables,
-    _ot: &HashMap<(&'a str, usize), Variable>,
-    _og: &HashMap<(&'a str, usize), Variable>,
+    _ot: &BTreeMap<(&'a str, usize), Variable>,
+    _og: &BTreeMap<(&'a str, usize), Variable>,
 ) -> (
     Vec<(Variable, (&'a str, usize), (&'a str, usize))>,
     Vec<(Variable, (&'a str, usize), (&'a str, usize))>,
 ) {
+    // Without soft terms only `max_windows` caps need the teacher pairs.
+    let capped = |tid: &str| {
+        prep.index
+            .teacher_by_id
+            .get(tid)
+            .is_some_and(|t| t.prefs.max_windows.is_some())
+    };
     let mut adj_t = Vec::new();
     for &tid in &prep.teacher_ids {
-        for (_day, slots) in &prep.day_slots {
+        if prep.feasibility_only && !capped(tid) {
+            continue;
+        }
+        for (_day, slots) in &prep.index.day_slots {
+            let slots = window_slots(prep.inst, tid, slots, |k| {
+                prep.pinned.occ.teacher.contains(&(tid, k))
+            });
             for w in slots.windows(2) {
                 let a = vars.add(good_lp::variable().binary());
                 adj_t.push((a, (tid, w[0]), (tid, w[1])));
@@ -418,8 +505,8 @@ pub(crate) fn declare_adjacency_vars<'a>(
         }
     }
     let mut adj_g = Vec::new();
-    for &gid in &prep.group_ids {
-        for (_day, slots) in &prep.day_slots {
+    for &gid in prep.group_ids.iter().filter(|_| !prep.feasibility_only) {
+        for (_day, slots) in &prep.index.day_slots {
             for w in slots.windows(2) {
                 let a = vars.add(good_lp::variable().binary());
                 adj_g.push((a, (gid, w[0]), (gid, w[1])));
@@ -429,192 +516,1485 @@ pub(crate) fn declare_adjacency_vars<'a>(
     (adj_t, adj_g)
 }
 
-pub(crate) fn build_objective(prep: &Prep, v: &Vars) -> Expression {
-    let mut objective = Expression::from(0.0);
-    let w_unpref = prep.inst.policy.soft_weights.unpreferred_time as f64;
-    let w_windows = prep.inst.policy.soft_weights.windows as f64;
+pub(crate) fn declare_load_vars<'a>(
+    prep: &'a Prep,
+    vars: &mut ProblemVariables,
+) -> Vec<(&'a str, Variable)> {
+    if !prep.uses(ObjectiveTerm::LoadImbalance) {
+        return Vec::new();
+    }
+    prep.inst
+        .teachers
+        .iter()
+        .map(|t| (t.id.0.as_str(), vars.add(good_lp::variable().min(0.0))))
+        .collect()
+}
 
-    if w_unpref > 0.0 {
-        for s in &v.starts {
-            let c = &prep.inst.courses[s.c];
-            if let Some(avoid) = prep.avoid_by_teacher.get(&c.teacherId.0.as_str()) {
-                let mut penalize = avoid.contains(prep.times[s.t]);
-                if c.duration == 2 && s.t + 1 < prep.times.len() {
-                    penalize = penalize || avoid.contains(prep.times[s.t + 1]);
-                }
-                if penalize {
-                    objective = objective + w_unpref * s.var;
-                }
+pub(crate) fn declare_span_vars<'a>(
+    prep: &'a Prep,
+    vars: &mut ProblemVariables,
+) -> Vec<DaySpan<'a>> {
+    let mut spans = Vec::new();
+    if !prep.uses(ObjectiveTerm::Compactness) {
+        return spans;
+    }
+    for &gid in &prep.group_ids {
+        for slots in prep.index.day_slots.values() {
+            if slots.len() < 2 {
+                continue;
             }
-        }
-        if prep.pinned.unpref_pinned_count > 0 {
-            objective = objective + w_unpref * (prep.pinned.unpref_pinned_count as f64);
+            let n = slots.len() as f64;
+            spans.push(DaySpan {
+                gid,
+                slots,
+                first: vars.add(good_lp::variable().min(0.0).max(n)),
+                last: vars.add(good_lp::variable().min(0.0).max(n)),
+                span: vars.add(good_lp::variable().min(0.0)),
+            });
         }
     }
+    spans
+}
 
-    if w_windows > 0.0 {
-        for &tid in &prep.teacher_ids {
-            for (_day, slots) in &prep.day_slots {
-                if slots.len() < 2 {
-                    continue;
-                }
-                for &k in slots {
-                    objective = objective + w_windows * v.ot[&(tid, k)];
-                }
-            }
-        }
-        for &(a, (tid, _k), (_tid2, _k1)) in &v.adj_t {
-            debug_assert_eq!(tid, _tid2);
-            objective = objective - w_windows * a;
-        }
-        for &gid in &prep.group_ids {
-            for (_day, slots) in &prep.day_slots {
-                if slots.len() < 2 {
-                    continue;
-                }
-                for &k in slots {
-                    objective = objective + w_windows * v.og[&(gid, k)];
-                }
-            }
-        }
-        for &(a, (gid, _k), (_gid2, _k1)) in &v.adj_g {
-            debug_assert_eq!(gid, _gid2);
-            objective = objective - w_windows * a;
+pub(crate) fn add_span_constraints<M: SolverModel>(mut model: M, v: &Vars) -> M {
+    for ds in &v.spans {
+        let n = ds.slots.len() as f64;
+        for (p, &k) in ds.slots.iter().enumerate() {
+            let occ = v.og[&(ds.gid, k)];
+            model = model.with((ds.last - p as f64 * occ).geq(0.0));
+            model = model.with((ds.first + n * occ).leq(p as f64 + n));
         }
+        model = model.with((ds.span - ds.last + ds.first).geq(0.0));
     }
+    model
+}
 
-    objective
+fn teacher_load_expr(prep: &Prep, v: &Vars, tid: &str) -> Expression {
+    let mut load = Expression::from(0.0);
+    for s in v
+        .starts
+        .iter()
+        .filter(|s| prep.inst.courses[s.c].teacherId.0.as_str() == tid)
+    {
+        load += s.var;
+    }
+    let pinned = prep
+        .pinned
+        .vec
+        .iter()
+        .filter_map(|a| prep.index.idx_course.get(a.courseId.0.as_str()))
+        .filter(|&&ci| prep.inst.courses[ci].teacherId.0.as_str() == tid)
+        .count();
+    load + pinned as f64
 }
 
-pub(crate) fn add_course_count_constraints<M: SolverModel>(
+pub(crate) fn add_load_balance_constraints<M: SolverModel>(
     mut model: M,
     prep: &Prep,
     v: &Vars,
 ) -> M {
+    if v.load_dev.is_empty() {
+        return model;
+    }
+    let loads: Vec<Expression> = v
+        .load_dev
+        .iter()
+        .map(|&(tid, _)| teacher_load_expr(prep, v, tid))
+        .collect();
+    let mut total = Expression::from(0.0);
+    for l in &loads {
+        total += l.clone();
+    }
+    let mean = total * (1.0 / v.load_dev.len() as f64);
+    for (&(_tid, dev), load) in v.load_dev.iter().zip(loads) {
+        model = model.with((dev - load.clone() + mean.clone()).geq(0.0));
+        model = model.with((dev + load - mean.clone()).geq(0.0));
+    }
+    model
+}
+
+pub(crate) fn declare_vars<'a>(prep: &'a Prep, pvars: &mut ProblemVariables) -> Vars<'a> {
+    let starts = declare_starts(prep, pvars);
+    let (ot, og) = declare_occupancy_vars(prep, pvars);
+    let (adj_t, adj_g) = declare_adjacency_vars(prep, pvars, &ot, &og);
+    let load_dev = declare_load_vars(prep, pvars);
+    let spans = declare_span_vars(prep, pvars);
+    let unmet = declare_unmet_vars(prep, pvars);
+    let room_used = declare_room_used_vars(prep, pvars, &starts);
+    let teacher_room_used = declare_teacher_room_used_vars(prep, pvars, &starts);
+    let adjacent = declare_adjacent_pair_vars(prep, pvars);
+    let parity_gaps = declare_parity_gap_vars(prep, pvars, &starts);
+    let active_days = declare_active_day_vars(prep, pvars, &ot, &og);
+    let building_spread = declare_building_spread_vars(prep, pvars, &starts);
+    let rooms_in_use = declare_rooms_in_use_vars(prep, pvars, &starts);
+    let building_switches = declare_building_switch_vars(prep, pvars, &starts);
+    let same_room = declare_same_room_vars(prep, pvars, &starts);
+    let course_spacing = declare_course_spacing_vars(prep, pvars, &starts);
+    Vars {
+        starts,
+        ot,
+        og,
+        adj_t,
+        adj_g,
+        load_dev,
+        spans,
+        unmet,
+        room_used,
+        teacher_room_used,
+        adjacent,
+        parity_gaps,
+        active_days,
+        building_spread,
+        rooms_in_use,
+        building_switches,
+        same_room,
+        course_spacing,
+    }
+}
+
+fn start_day(prep: &Prep, t: usize) -> Option<DayOfWeek> {
+    TimeslotId::parse_str(prep.index.times[t]).map(|(day, _, _)| day)
+}
+
+pub(crate) fn declare_course_spacing_vars(
+    prep: &Prep,
+    vars: &mut ProblemVariables,
+    starts: &[StartVar],
+) -> Vec<CourseSpacing> {
+    if !prep.uses(ObjectiveTerm::CourseSpacing) {
+        return Vec::new();
+    }
+    let grid_days = prep.index.day_slots.len();
+    let mut out = Vec::new();
     for (ci, c) in prep.inst.courses.iter().enumerate() {
-        let mut sum = Expression::from(0.0);
-        for s in v.starts.iter().filter(|s| s.c == ci) {
-            sum = sum + s.var;
+        if c.required_meetings() < 2 {
+            continue;
+        }
+        let ideal = ideal_day_gap(grid_days, c.required_meetings());
+        let pinned: BTreeSet<DayOfWeek> = prep
+            .pinned
+            .vec
+            .iter()
+            .filter(|a| a.courseId == c.id)
+            .filter_map(|a| a.timeslot.parse().map(|(day, _, _)| day))
+            .collect();
+        let mut days: BTreeSet<DayOfWeek> = starts
+            .iter()
+            .filter(|s| s.c == ci)
+            .filter_map(|s| start_day(prep, s.t))
+            .collect();
+        days.extend(&pinned);
+        let met: Vec<(DayOfWeek, Option<Variable>)> = days
+            .into_iter()
+            .map(|d| {
+                let var = (!pinned.contains(&d)).then(|| vars.add(good_lp::variable().binary()));
+                (d, var)
+            })
+            .collect();
+        let mut close = Vec::new();
+        for i in 0..met.len() {
+            for j in i + 1..met.len() {
+                let gap = met[j].0 as u32 - met[i].0 as u32;
+                if gap < ideal {
+                    let both = vars.add(good_lp::variable().binary());
+                    close.push((i, j, (ideal - gap) as f64, both));
+                }
+            }
+        }
+        if !close.is_empty() {
+            out.push(CourseSpacing { c: ci, met, close });
         }
-        let pinned_cnt = *prep.pinned.count_by_course.get(&ci).unwrap_or(&0);
-        let need = c.countPerWeek.saturating_sub(pinned_cnt);
-        model = model.with(sum.eq(need as f64));
     }
-    model
+    out
 }
 
-pub(crate) fn add_room_capacity_constraints<M: SolverModel>(
+pub(crate) fn add_course_spacing_constraints<M: SolverModel>(
     mut model: M,
     prep: &Prep,
     v: &Vars,
 ) -> M {
-    for (ri, _r) in prep.inst.rooms.iter().enumerate() {
-        for k in 0..prep.times.len() {
-            let mut sum = Expression::from(0.0);
+    for cs in &v.course_spacing {
+        for &(d, met) in &cs.met {
+            let Some(met) = met else {
+                continue;
+            };
             for s in v
                 .starts
                 .iter()
-                .filter(|s| s.r == ri && occupies(&prep.inst.courses, s, k))
+                .filter(|s| s.c == cs.c && start_day(prep, s.t) == Some(d))
             {
-                sum = sum + s.var;
+                model = model.with((met - s.var).geq(0.0));
             }
-            let rhs = if *prep.pinned.room.get(&(ri, k)).unwrap_or(&false) {
-                0.0
-            } else {
-                1.0
-            };
-            model = model.with(sum.leq(rhs));
+        }
+        let day = |i: usize| -> Expression { cs.met[i].1.map_or(1.0.into(), Expression::from) };
+        for &(i, j, _, close) in &cs.close {
+            model = model.with((close - day(i) - day(j)).geq(-1.0));
         }
     }
     model
 }
 
-pub(crate) fn add_teacher_capacity_constraints<M: SolverModel>(
-    mut model: M,
+/// Hard, so declared regardless of the objective.
+pub(crate) fn declare_same_room_vars(
     prep: &Prep,
-    v: &Vars,
-) -> M {
-    for &tid in &prep.teacher_ids {
-        for k in 0..prep.times.len() {
-            let mut sum = Expression::from(0.0);
-            for s in v.starts.iter().filter(|s| {
-                prep.inst.courses[s.c].teacherId.0.as_str() == tid
-                    && occupies(&prep.inst.courses, s, k)
-            }) {
-                sum = sum + s.var;
-            }
-            let rhs = if *prep.pinned.teacher.get(&(tid, k)).unwrap_or(&false) {
-                0.0
-            } else {
-                1.0
-            };
-            model = model.with(sum.leq(rhs));
-        }
-    }
-    model
+    vars: &mut ProblemVariables,
+    starts: &[StartVar],
+) -> Vec<SameRoom> {
+    same_room_sets(prep.inst)
+        .into_iter()
+        .filter(|courses| !courses.is_empty())
+        .map(|courses| {
+            let mut rooms: BTreeSet<usize> = starts
+                .iter()
+                .filter(|s| courses.contains(&s.c))
+                .map(|s| s.r)
+                .collect();
+            for &ci in &courses {
+                rooms.extend(
+                    pinned_rooms(prep, ci)
+                        .into_iter()
+                        .filter_map(|r| prep.index.idx_room.get(r).copied()),
+                );
+            }
+            SameRoom {
+                courses,
+                rooms: rooms
+                    .into_iter()
+                    .map(|r| (r, vars.add(good_lp::variable().binary())))
+                    .collect(),
+            }
+        })
+        .collect()
 }
 
-pub(crate) fn add_group_capacity_constraints<M: SolverModel>(
-    mut model: M,
-    prep: &Prep,
-    v: &Vars,
-) -> M {
-    for &gid in &prep.group_ids {
-        for k in 0..prep.times.len() {
-            let mut sum = Expression::from(0.0);
-            for s in v.starts.iter().filter(|s| {
-                prep.inst.courses[s.c].groupId.0.as_str() == gid
-                    && occupies(&prep.inst.courses, s, k)
-            }) {
-                sum = sum + s.var;
-            }
-            let rhs = if *prep.pinned.group.get(&(gid, k)).unwrap_or(&false) {
-                0.0
-            } else {
-                1.0
-            };
-            model = model.with(sum.leq(rhs));
+pub(crate) fn add_same_room_constraints<M: SolverModel>(mut model: M, prep: &Prep, v: &Vars) -> M {
+    for set in &v.same_room {
+        let chosen: Expression = set.rooms.iter().map(|&(_, z)| z).sum();
+        model = model.with(chosen.leq(1.0));
+        let pinned: HashSet<&str> = set
+            .courses
+            .iter()
+            .flat_map(|&ci| pinned_rooms(prep, ci))
+            .collect();
+        for &(r, z) in &set.rooms {
+            if pinned.contains(prep.inst.rooms[r].id.0.as_str()) {
+                model = model.with(Expression::from(z).geq(1.0));
+            }
+            for s in v
+                .starts
+                .iter()
+                .filter(|s| s.r == r && set.courses.contains(&s.c))
+            {
+                model = model.with((z - s.var).geq(0.0));
+            }
         }
     }
     model
 }
 
-pub(crate) fn link_occupancy<M: SolverModel>(mut model: M, prep: &Prep, v: &Vars) -> M {
-    for (&(tid, k), var) in &v.ot {
-        let mut sum = Expression::from(0.0);
-        for s in v.starts.iter().filter(|s| {
-            prep.inst.courses[s.c].teacherId.0.as_str() == tid && occupies(&prep.inst.courses, s, k)
-        }) {
-            sum = sum + s.var;
-        }
-        let pinned = if *prep.pinned.teacher.get(&(tid, k)).unwrap_or(&false) {
-            1.0
-        } else {
-            0.0
+/// `(teacher?, id, slot, building)` for every slot a pinned meeting holds,
+/// once for its teacher and once for its group.
+fn pinned_places<'a>(prep: &'a Prep) -> Vec<(bool, &'a str, usize, &'a str)> {
+    let mut out = Vec::new();
+    for a in &prep.pinned.vec {
+        let (Some(&ci), Some(&t), Some(&r)) = (
+            prep.index.idx_course.get(a.courseId.0.as_str()),
+            prep.index.idx_ts.get(a.timeslot.0.as_str()),
+            prep.index.idx_room.get(a.roomId.0.as_str()),
+        ) else {
+            continue;
         };
-        model = model.with((sum + pinned).eq(*var));
-    }
-    for (&(gid, k), var) in &v.og {
-        let mut sum = Expression::from(0.0);
-        for s in v.starts.iter().filter(|s| {
-            prep.inst.courses[s.c].groupId.0.as_str() == gid && occupies(&prep.inst.courses, s, k)
-        }) {
-            sum = sum + s.var;
-        }
-        let pinned = if *prep.pinned.group.get(&(gid, k)).unwrap_or(&false) {
-            1.0
-        } else {
-            0.0
+        let Some(b) = prep.inst.rooms[r].building.as_deref() else {
+            continue;
         };
-        model = model.with((sum + pinned).eq(*var));
+        let c = &prep.inst.courses[ci];
+        for k in t..t + c.duration as usize {
+            out.push((true, a.teacherId.0.as_str(), k, b));
+            out.push((false, c.groupId.0.as_str(), k, b));
+        }
     }
-    model
+    out
 }
 
-pub(crate) fn add_adjacency_constraints<M: SolverModel>(mut model: M, v: &Vars) -> M {
-    for &(a, (tid, k), (_tid2, k1)) in &v.adj_t {
-        model = model.with((a - v.ot[&(tid, k)]).leq(0.0));
-        model = model.with((a - v.ot[&(tid, k1)]).leq(0.0));
+/// One switch per teacher or group, pair of consecutive slots of a day and
+/// pair of distinct buildings it could meet in across them.
+pub(crate) fn declare_building_switch_vars<'a>(
+    prep: &'a Prep,
+    vars: &mut ProblemVariables,
+    starts: &[StartVar],
+) -> Vec<BuildingSwitch<'a>> {
+    if !prep.uses(ObjectiveTerm::BuildingSwitch) {
+        return Vec::new();
+    }
+    let mut places: BTreeMap<(bool, &str, usize), BTreeSet<&str>> = BTreeMap::new();
+    for s in starts {
+        let Some(b) = prep.inst.rooms[s.r].building.as_deref() else {
+            continue;
+        };
+        let c = &prep.inst.courses[s.c];
+        for k in s.t..s.t + c.duration as usize {
+            places
+                .entry((true, c.teacherId.0.as_str(), k))
+                .or_default()
+                .insert(b);
+            places
+                .entry((false, c.groupId.0.as_str(), k))
+                .or_default()
+                .insert(b);
+        }
+    }
+    for (teacher, id, k, b) in pinned_places(prep) {
+        places.entry((teacher, id, k)).or_default().insert(b);
+    }
+    let next: HashMap<usize, usize> = prep
+        .index
+        .day_slots
+        .values()
+        .flat_map(|slots| slots.windows(2).map(|w| (w[0], w[1])))
+        .collect();
+    let mut out = Vec::new();
+    for (&(teacher, id, k), here) in &places {
+        let Some(&k1) = next.get(&k) else {
+            continue;
+        };
+        let Some(there) = places.get(&(teacher, id, k1)) else {
+            continue;
+        };
+        for &b1 in here {
+            for &b2 in there.iter().filter(|&&b2| b2 != b1) {
+                let distance = prep.inst.policy.building_distance(b1, b2);
+                if distance <= 0 {
+                    continue;
+                }
+                out.push(BuildingSwitch {
+                    teacher,
+                    id,
+                    from: (k, b1),
+                    to: (k1, b2),
+                    distance: distance as f64,
+                    switch: vars.add(good_lp::variable().min(0.0)),
+                });
+            }
+        }
+    }
+    out
+}
+
+pub(crate) fn add_building_switch_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    if v.building_switches.is_empty() {
+        return model;
+    }
+    let pinned = pinned_places(prep);
+    let in_building = |teacher: bool, id: &str, (k, b): (usize, &str)| {
+        let mut sum = Expression::from(0.0);
+        for s in &v.starts {
+            let c = &prep.inst.courses[s.c];
+            let who = if teacher {
+                &c.teacherId.0
+            } else {
+                &c.groupId.0
+            };
+            if who == id
+                && occupies(&prep.inst.courses, s, k)
+                && prep.inst.rooms[s.r].building.as_deref() == Some(b)
+            {
+                sum += s.var;
+            }
+        }
+        if pinned.contains(&(teacher, id, k, b)) {
+            sum += 1.0;
+        }
+        sum
+    };
+    for s in &v.building_switches {
+        let from = in_building(s.teacher, s.id, s.from);
+        let to = in_building(s.teacher, s.id, s.to);
+        model = model.with((s.switch - from - to).geq(-1.0));
+    }
+    model
+}
+
+/// Only for courses that can end up in more than one building.
+pub(crate) fn declare_building_spread_vars<'a>(
+    prep: &'a Prep,
+    vars: &mut ProblemVariables,
+    starts: &[StartVar],
+) -> Vec<BuildingSpread<'a>> {
+    if !prep.uses(ObjectiveTerm::CourseBuildingSpread) {
+        return Vec::new();
+    }
+    let building = |r: usize| prep.inst.rooms[r].building.as_deref();
+    let mut out = Vec::new();
+    for ci in 0..prep.inst.courses.len() {
+        let pinned: BTreeSet<&str> = pinned_rooms(prep, ci)
+            .into_iter()
+            .filter_map(|room| prep.index.idx_room.get(room))
+            .filter_map(|&r| building(r))
+            .collect();
+        let open: BTreeSet<&str> = starts
+            .iter()
+            .filter(|s| s.c == ci)
+            .filter_map(|s| building(s.r))
+            .filter(|b| !pinned.contains(b))
+            .collect();
+        if pinned.len() + open.len() < 2 {
+            continue;
+        }
+        out.push(BuildingSpread {
+            c: ci,
+            used: open
+                .into_iter()
+                .map(|b| (b, vars.add(good_lp::variable().binary())))
+                .collect(),
+            pinned: pinned.len(),
+            spread: vars.add(good_lp::variable().min(0.0)),
+        });
+    }
+    out
+}
+
+pub(crate) fn add_building_spread_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    for bs in &v.building_spread {
+        let mut total = Expression::from(bs.pinned as f64 - 1.0);
+        for &(b, used) in &bs.used {
+            for s in v
+                .starts
+                .iter()
+                .filter(|s| s.c == bs.c && prep.inst.rooms[s.r].building.as_deref() == Some(b))
+            {
+                model = model.with((used - s.var).geq(0.0));
+            }
+            total += used;
+        }
+        model = model.with((bs.spread - total).geq(0.0));
+    }
+    model
+}
+
+/// One indicator per teacher/group and day.
+pub(crate) fn declare_active_day_vars(
+    prep: &Prep,
+    vars: &mut ProblemVariables,
+    ot: &BTreeMap<(&str, usize), Variable>,
+    og: &BTreeMap<(&str, usize), Variable>,
+) -> Vec<ActiveDay> {
+    if !prep.uses(ObjectiveTerm::ActiveDays) {
+        return Vec::new();
+    }
+    let mut out = Vec::new();
+    for (ids, occ) in [(&prep.teacher_ids, ot), (&prep.group_ids, og)] {
+        for &id in ids {
+            for slots in prep.index.day_slots.values() {
+                out.push(ActiveDay {
+                    busy: slots.iter().map(|&k| occ[&(id, k)]).collect(),
+                    active: vars.add(good_lp::variable().binary()),
+                });
+            }
+        }
+    }
+    out
+}
+
+pub(crate) fn add_active_day_constraints<M: SolverModel>(mut model: M, v: &Vars) -> M {
+    for d in &v.active_days {
+        for &busy in &d.busy {
+            model = model.with((d.active - busy).geq(0.0));
+        }
+    }
+    model
+}
+
+/// One gap per pair and slot where either course can start or is pinned.
+pub(crate) fn declare_parity_gap_vars(
+    prep: &Prep,
+    vars: &mut ProblemVariables,
+    starts: &[StartVar],
+) -> Vec<ParityGap> {
+    if !prep.uses(ObjectiveTerm::ParitySlotConsistency) {
+        return Vec::new();
+    }
+    let mut out = Vec::new();
+    for (a, b) in parity_pairs(prep.inst) {
+        let slots: BTreeSet<usize> = starts
+            .iter()
+            .filter(|s| s.c == a || s.c == b)
+            .map(|s| s.t)
+            .chain((0..prep.index.times.len()).filter(|&t| {
+                pinned_meetings_at(prep, a, t, false) + pinned_meetings_at(prep, b, t, false) > 0.0
+            }))
+            .collect();
+        for t in slots {
+            out.push(ParityGap {
+                a,
+                b,
+                t,
+                gap: vars.add(good_lp::variable().min(0.0)),
+            });
+        }
+    }
+    out
+}
+
+pub(crate) fn declare_adjacent_pair_vars(
+    prep: &Prep,
+    vars: &mut ProblemVariables,
+) -> Vec<AdjacentPair> {
+    if !prep.uses(ObjectiveTerm::CourseAdjacency) {
+        return Vec::new();
+    }
+    let next: Vec<(usize, usize)> = prep
+        .index
+        .day_slots
+        .values()
+        .flat_map(|slots| slots.windows(2).map(|w| (w[0], w[1])))
+        .collect();
+    prep.inst
+        .policy
+        .prefer_adjacent
+        .iter()
+        .filter_map(|(a, b)| {
+            let a = *prep.index.idx_course.get(a.0.as_str())?;
+            let b = *prep.index.idx_course.get(b.0.as_str())?;
+            Some(AdjacentPair {
+                a,
+                b,
+                met: vars.add(good_lp::variable().binary()),
+                links: next
+                    .iter()
+                    .map(|&(k, k1)| (vars.add(good_lp::variable().binary()), k, k1))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Pinned meetings of course `ci` starting in slot `k`, or with `by_end`,
+/// ending in it.
+fn pinned_meetings_at(prep: &Prep, ci: usize, k: usize, by_end: bool) -> f64 {
+    let c = &prep.inst.courses[ci];
+    prep.pinned
+        .vec
+        .iter()
+        .filter(|a| a.courseId == c.id)
+        .filter_map(|a| prep.index.idx_ts.get(a.timeslot.0.as_str()))
+        .filter(|&&t| {
+            let last = if by_end {
+                t + c.duration as usize - 1
+            } else {
+                t
+            };
+            last == k
+        })
+        .count() as f64
+}
+
+pub(crate) fn add_parity_gap_constraints<M: SolverModel>(mut model: M, prep: &Prep, v: &Vars) -> M {
+    let begins = |ci: usize, t: usize| {
+        let mut sum = Expression::from(pinned_meetings_at(prep, ci, t, false));
+        for s in v.starts.iter().filter(|s| s.c == ci && s.t == t) {
+            sum += s.var;
+        }
+        sum
+    };
+    for g in &v.parity_gaps {
+        let diff = begins(g.a, g.t) - begins(g.b, g.t);
+        model = model.with((diff.clone() - g.gap).leq(0.0));
+        model = model.with((-diff - g.gap).leq(0.0));
+    }
+    model
+}
+
+pub(crate) fn add_adjacent_pair_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    let courses = &prep.inst.courses;
+    for p in &v.adjacent {
+        let dur_a = courses[p.a].duration as usize;
+        let mut any = Expression::from(0.0);
+        for &(z, k, k1) in &p.links {
+            let mut ends = Expression::from(pinned_meetings_at(prep, p.a, k, true));
+            for s in v
+                .starts
+                .iter()
+                .filter(|s| s.c == p.a && s.t + dur_a - 1 == k)
+            {
+                ends += s.var;
+            }
+            let mut begins = Expression::from(pinned_meetings_at(prep, p.b, k1, false));
+            for s in v.starts.iter().filter(|s| s.c == p.b && s.t == k1) {
+                begins += s.var;
+            }
+            model = model.with((z - ends).leq(0.0));
+            model = model.with((z - begins).leq(0.0));
+            any += z;
+        }
+        model = model.with((p.met - any).leq(0.0));
+    }
+    model
+}
+
+/// Pinned rooms of course `ci`; they count as used without a variable.
+fn pinned_rooms<'a>(prep: &'a Prep, ci: usize) -> HashSet<&'a str> {
+    let cid = &prep.inst.courses[ci].id;
+    prep.pinned
+        .vec
+        .iter()
+        .filter(|a| a.courseId == *cid)
+        .map(|a| a.roomId.0.as_str())
+        .collect()
+}
+
+pub(crate) fn declare_room_used_vars(
+    prep: &Prep,
+    vars: &mut ProblemVariables,
+    starts: &[StartVar],
+) -> Vec<RoomUsed> {
+    if !prep.uses(ObjectiveTerm::CourseRoomConsistency) {
+        return Vec::new();
+    }
+    let mut pairs: Vec<(usize, usize)> = starts.iter().map(|s| (s.c, s.r)).collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+        .into_iter()
+        .filter(|&(c, r)| !pinned_rooms(prep, c).contains(prep.inst.rooms[r].id.0.as_str()))
+        .map(|(c, r)| RoomUsed {
+            c,
+            r,
+            used: vars.add(good_lp::variable().binary()),
+        })
+        .collect()
+}
+
+pub(crate) fn add_room_used_constraints<M: SolverModel>(mut model: M, prep: &Prep, v: &Vars) -> M {
+    for ru in &v.room_used {
+        for s in v.starts.iter().filter(|s| s.c == ru.c && s.r == ru.r) {
+            model = model.with((ru.used - s.var).geq(0.0));
+        }
+    }
+    for tru in &v.teacher_room_used {
+        for s in v
+            .starts
+            .iter()
+            .filter(|s| s.r == tru.r && prep.inst.courses[s.c].teacherId.0.as_str() == tru.tid)
+        {
+            model = model.with((tru.used - s.var).geq(0.0));
+        }
+    }
+    for &(r, used) in &v.rooms_in_use {
+        for s in v.starts.iter().filter(|s| s.r == r) {
+            model = model.with((used - s.var).geq(0.0));
+        }
+    }
+    model
+}
+
+/// Rooms held by any pin; they count as used without a variable.
+fn pinned_rooms_in_use<'a>(prep: &'a Prep) -> HashSet<&'a str> {
+    prep.pinned
+        .vec
+        .iter()
+        .map(|a| a.roomId.0.as_str())
+        .collect()
+}
+
+pub(crate) fn declare_rooms_in_use_vars(
+    prep: &Prep,
+    vars: &mut ProblemVariables,
+    starts: &[StartVar],
+) -> Vec<(usize, Variable)> {
+    if !prep.uses(ObjectiveTerm::RoomsUsed) {
+        return Vec::new();
+    }
+    let pinned = pinned_rooms_in_use(prep);
+    let rooms: BTreeSet<usize> = starts
+        .iter()
+        .map(|s| s.r)
+        .filter(|&r| !pinned.contains(prep.inst.rooms[r].id.0.as_str()))
+        .collect();
+    rooms
+        .into_iter()
+        .map(|r| (r, vars.add(good_lp::variable().binary())))
+        .collect()
+}
+
+/// Pinned rooms of teacher `tid`; they count as used without a variable.
+fn pinned_teacher_rooms<'a>(prep: &'a Prep, tid: &str) -> HashSet<&'a str> {
+    prep.pinned
+        .vec
+        .iter()
+        .filter(|a| a.teacherId.0 == tid)
+        .map(|a| a.roomId.0.as_str())
+        .collect()
+}
+
+pub(crate) fn declare_teacher_room_used_vars<'a>(
+    prep: &'a Prep,
+    vars: &mut ProblemVariables,
+    starts: &[StartVar],
+) -> Vec<TeacherRoomUsed<'a>> {
+    if !prep.uses(ObjectiveTerm::TeacherRoomSpread) {
+        return Vec::new();
+    }
+    let mut pairs: Vec<(&'a str, usize)> = starts
+        .iter()
+        .map(|s| (prep.inst.courses[s.c].teacherId.0.as_str(), s.r))
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+        .into_iter()
+        .filter(|&(tid, r)| {
+            !pinned_teacher_rooms(prep, tid).contains(prep.inst.rooms[r].id.0.as_str())
+        })
+        .map(|(tid, r)| TeacherRoomUsed {
+            tid,
+            r,
+            used: vars.add(good_lp::variable().binary()),
+        })
+        .collect()
+}
+
+/// Penalty per unplaced meeting of a priority-1 course in best-effort mode.
+/// Large enough to dominate every soft term.
+pub(crate) const UNMET_PENALTY: f64 = 1000.0;
+
+pub(crate) fn declare_unmet_vars(
+    prep: &Prep,
+    vars: &mut ProblemVariables,
+) -> Vec<(usize, Variable)> {
+    if !prep.best_effort {
+        return Vec::new();
+    }
+    prep.inst
+        .courses
+        .iter()
+        .enumerate()
+        .map(|(ci, c)| {
+            let v = good_lp::variable()
+                .integer()
+                .min(0.0)
+                .max(c.required_meetings() as f64);
+            (ci, vars.add(v))
+        })
+        .collect()
+}
+
+/// Hard-constraint families that `probe_infeasible_cause` may drop.
+pub(crate) const CONSTRAINT_FAMILIES: &[&str] = &[
+    "course_count",
+    "room_capacity",
+    "teacher_capacity",
+    "group_capacity",
+    "building_concurrency",
+    "teacher_windows",
+    "contiguous_days",
+    "group_weekly_cap",
+    "no_overlap",
+    "partial_locks",
+    "same_room",
+];
+
+pub(crate) fn family_size(prep: &Prep, family: &str) -> usize {
+    let n = prep.index.times.len();
+    match family {
+        "course_count" => prep.inst.courses.len(),
+        "room_capacity" => prep.inst.rooms.len() * n,
+        "teacher_capacity" => prep.teacher_ids.len() * n,
+        "group_capacity" => prep.group_ids.len() * n,
+        "building_concurrency" => prep.inst.policy.building_concurrency.len() * n,
+        "teacher_windows" => prep
+            .inst
+            .teachers
+            .iter()
+            .filter(|t| t.prefs.max_windows.is_some())
+            .count(),
+        "contiguous_days" => prep
+            .inst
+            .groups
+            .iter()
+            .filter(|g| g.contiguous_days)
+            .count(),
+        "group_weekly_cap" => prep
+            .inst
+            .groups
+            .iter()
+            .filter(|g| g.max_weekly.is_some())
+            .count(),
+        "no_overlap" => prep.inst.policy.no_overlap.len() * n,
+        "partial_locks" => prep.locks.len(),
+        "same_room" => prep.inst.policy.same_room_groups.len(),
+        _ => 0,
+    }
+}
+
+/// Adds every constraint family to `model`, except `skip` when given.
+pub(crate) fn add_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+    skip: Option<&str>,
+) -> M {
+    if skip != Some("course_count") {
+        model = add_course_count_constraints(model, prep, v);
+    }
+    if skip != Some("room_capacity") {
+        model = add_room_capacity_constraints(model, prep, v);
+    }
+    if skip != Some("teacher_capacity") {
+        model = add_teacher_capacity_constraints(model, prep, v);
+    }
+    if skip != Some("group_capacity") {
+        model = add_group_capacity_constraints(model, prep, v);
+    }
+    if skip != Some("building_concurrency") {
+        model = add_building_concurrency_constraints(model, prep, v);
+    }
+    // Occupancy vars are binary, so linking them would re-impose the
+    // teacher/group capacity that is being probed.
+    if !matches!(skip, Some("teacher_capacity" | "group_capacity")) {
+        model = link_occupancy(model, prep, v);
+    }
+    model = add_adjacency_constraints(model, v);
+    if skip != Some("teacher_windows") {
+        model = add_teacher_window_cap_constraints(model, prep, v);
+    }
+    if skip != Some("contiguous_days") {
+        model = add_contiguous_day_constraints(model, prep, v);
+    }
+    if skip != Some("group_weekly_cap") {
+        model = add_group_weekly_cap_constraints(model, prep, v);
+    }
+    if skip != Some("no_overlap") {
+        model = add_no_overlap_constraints(model, prep, v);
+    }
+    if skip != Some("partial_locks") {
+        model = add_partial_lock_constraints(model, prep, v);
+    }
+    if skip != Some("same_room") {
+        model = add_same_room_constraints(model, prep, v);
+    }
+    model = add_load_balance_constraints(model, prep, v);
+    model = add_span_constraints(model, v);
+    model = add_room_used_constraints(model, prep, v);
+    model = add_adjacent_pair_constraints(model, prep, v);
+    model = add_parity_gap_constraints(model, prep, v);
+    model = add_active_day_constraints(model, v);
+    model = add_building_spread_constraints(model, prep, v);
+    model = add_building_switch_constraints(model, prep, v);
+    model = add_course_spacing_constraints(model, prep, v);
+    model
+}
+
+/// Objective coefficient after integer scaling, rounded so backends compare
+/// whole numbers rather than accumulated float noise.
+pub(crate) fn scaled(prep: &Prep, w: f64) -> f64 {
+    (w * prep.objective_scale).round()
+}
+
+pub(crate) fn build_objective(prep: &Prep, v: &Vars) -> Expression {
+    soft_objective(prep, v) + tiebreak_objective(prep, v)
+}
+
+/// The objective as reported: weighted soft terms plus unmet meetings,
+/// without the tie-break.
+pub(crate) fn soft_objective(prep: &Prep, v: &Vars) -> Expression {
+    weighted_objective(prep, v, &prep.weights) + unmet_objective(prep, v)
+}
+
+/// `Policy.tiebreak_early`: every start pays its slot index times a
+/// coefficient small enough that all starts together stay below one scaled
+/// unit, so it only decides between schedules the real terms rank equal.
+fn tiebreak_objective(prep: &Prep, v: &Vars) -> Expression {
+    let mut objective = Expression::from(0.0);
+    if !prep.inst.policy.tiebreak_early || prep.feasibility_only {
+        return objective;
+    }
+    let meetings: u64 = prep
+        .inst
+        .courses
+        .iter()
+        .map(|c| c.required_meetings() as u64)
+        .sum();
+    let eps = prep.objective_scale / (meetings * prep.index.times.len() as u64 + 1) as f64;
+    for s in &v.starts {
+        objective += (eps * s.t as f64) * s.var;
+    }
+    objective
+}
+
+/// One soft term at unit weight (times `objective_scale`), for lexicographic
+/// stages.
+pub(crate) fn term_objective(prep: &Prep, v: &Vars, term: ObjectiveTerm) -> Expression {
+    weighted_objective(prep, v, &SoftWeights::only(term))
+}
+
+pub(crate) fn unmet_objective(prep: &Prep, v: &Vars) -> Expression {
+    let mut objective = Expression::from(0.0);
+    for &(ci, unmet) in &v.unmet {
+        let priority = prep.inst.courses[ci].priority_or_default().max(1) as f64;
+        objective += scaled(prep, UNMET_PENALTY * priority) * unmet;
+    }
+
+    objective
+}
+
+fn weighted_objective(prep: &Prep, v: &Vars, weights: &SoftWeights) -> Expression {
+    let mut objective = Expression::from(0.0);
+    let w_unpref = scaled(prep, weights.unpreferred_time as f64);
+    let w_windows_teachers = scaled(prep, weights.teacher_windows() as f64);
+    let w_windows_groups = scaled(prep, weights.group_windows() as f64);
+
+    if w_unpref > 0.0 {
+        for s in &v.starts {
+            let c = &prep.inst.courses[s.c];
+            if let Some(avoid) = prep.avoid_by_teacher.get(&c.teacherId.0.as_str()) {
+                let mut penalize = avoid.contains(prep.index.times[s.t]);
+                if c.duration == 2 && s.t + 1 < prep.index.times.len() {
+                    penalize = penalize || avoid.contains(prep.index.times[s.t + 1]);
+                }
+                if penalize {
+                    objective += w_unpref * s.var;
+                }
+            }
+            if group_unpreferred_start(prep.inst, c, &prep.index.times, s.t) {
+                objective += w_unpref * s.var;
+            }
+        }
+        if prep.pinned.unpref_pinned_count > 0 {
+            objective = objective + w_unpref * (prep.pinned.unpref_pinned_count as f64);
+        }
+    }
+
+    if w_windows_teachers > 0.0 {
+        for &tid in &prep.teacher_ids {
+            for (_day, slots) in &prep.index.day_slots {
+                if slots.len() < 2 {
+                    continue;
+                }
+                for &k in slots {
+                    objective += w_windows_teachers * v.ot[&(tid, k)];
+                }
+            }
+        }
+        for &(a, (tid, _k), (_tid2, _k1)) in &v.adj_t {
+            debug_assert_eq!(tid, _tid2);
+            objective -= w_windows_teachers * a;
+        }
+    }
+    if w_windows_groups > 0.0 {
+        for &gid in &prep.group_ids {
+            for (_day, slots) in &prep.index.day_slots {
+                if slots.len() < 2 {
+                    continue;
+                }
+                for &k in slots {
+                    objective += w_windows_groups * v.og[&(gid, k)];
+                }
+            }
+        }
+        for &(a, (gid, _k), (_gid2, _k1)) in &v.adj_g {
+            debug_assert_eq!(gid, _gid2);
+            objective -= w_windows_groups * a;
+        }
+    }
+
+    let w_soft = scaled(prep, weights.soft_availability as f64);
+    if w_soft > 0.0 {
+        for s in &v.starts {
+            let c = &prep.inst.courses[s.c];
+            if let Some(t) = prep.index.teacher_by_id.get(c.teacherId.0.as_str()) {
+                if soft_start(t, &prep.index.times, s.t, c.duration == 2) {
+                    objective += w_soft * s.var;
+                }
+            }
+        }
+        if prep.pinned.soft_pinned_count > 0 {
+            objective += w_soft * (prep.pinned.soft_pinned_count as f64);
+        }
+    }
+
+    let w_compact = scaled(prep, weights.compactness as f64);
+    if w_compact > 0.0 {
+        for ds in &v.spans {
+            objective += w_compact * ds.span;
+        }
+    }
+
+    let w_room = scaled(prep, weights.course_room_consistency as f64);
+    if w_room > 0.0 {
+        for ru in &v.room_used {
+            objective += w_room * ru.used;
+        }
+        for (ci, c) in prep.inst.courses.iter().enumerate() {
+            if c.required_meetings() == 0 {
+                continue;
+            }
+            // Rooms beyond the first: pinned rooms are fixed, one room is free.
+            let pinned = pinned_rooms(prep, ci).len() as f64;
+            objective += w_room * (pinned - 1.0);
+        }
+    }
+
+    let w_away = scaled(prep, weights.away_from_home as f64);
+    if w_away > 0.0 {
+        let group_by_id: HashMap<&str, &Group> = prep
+            .inst
+            .groups
+            .iter()
+            .map(|g| (g.id.0.as_str(), g))
+            .collect();
+        for s in &v.starts {
+            let gid = prep.inst.courses[s.c].groupId.0.as_str();
+            if let Some(g) = group_by_id.get(gid) {
+                if away_from_home(g, &prep.inst.rooms[s.r]) {
+                    objective += w_away * s.var;
+                }
+            }
+        }
+        let pinned_away = prep
+            .pinned
+            .vec
+            .iter()
+            .filter(|a| {
+                let c = prep.inst.courses.iter().find(|c| c.id == a.courseId);
+                let g = c.and_then(|c| group_by_id.get(c.groupId.0.as_str()));
+                let r = prep.index.idx_room.get(a.roomId.0.as_str());
+                matches!((g, r), (Some(g), Some(&ri)) if away_from_home(g, &prep.inst.rooms[ri]))
+            })
+            .count();
+        if pinned_away > 0 {
+            objective += w_away * (pinned_away as f64);
+        }
+    }
+
+    let w_teacher_room = scaled(prep, weights.teacher_room_spread as f64);
+    if w_teacher_room > 0.0 {
+        for tru in &v.teacher_room_used {
+            objective += w_teacher_room * tru.used;
+        }
+        for &tid in &prep.teacher_ids {
+            let teaches = prep
+                .inst
+                .courses
+                .iter()
+                .any(|c| c.teacherId.0 == tid && c.required_meetings() > 0);
+            if !teaches {
+                continue;
+            }
+            let pinned = pinned_teacher_rooms(prep, tid).len() as f64;
+            objective += w_teacher_room * (pinned - 1.0);
+        }
+    }
+
+    let w_adjacent = scaled(prep, weights.course_adjacency as f64);
+    if w_adjacent > 0.0 {
+        for p in &v.adjacent {
+            objective = objective + w_adjacent - w_adjacent * p.met;
+        }
+    }
+
+    let w_parity = scaled(prep, weights.parity_slot_consistency as f64);
+    if w_parity > 0.0 {
+        for g in &v.parity_gaps {
+            objective += w_parity * g.gap;
+        }
+    }
+
+    let w_building = scaled(prep, weights.course_building_spread as f64);
+    if w_building > 0.0 {
+        for bs in &v.building_spread {
+            objective += w_building * bs.spread;
+        }
+    }
+
+    if weights.building_switch > 0 {
+        for s in &v.building_switches {
+            let w = scaled(prep, weights.building_switch as f64 * s.distance);
+            objective += w * s.switch;
+        }
+    }
+
+    if weights.course_spacing > 0 {
+        for cs in &v.course_spacing {
+            for &(_, _, short, close) in &cs.close {
+                let w = scaled(prep, weights.course_spacing as f64 * short);
+                objective += w * close;
+            }
+        }
+    }
+
+    let w_rooms_used = scaled(prep, weights.rooms_used as f64);
+    if w_rooms_used > 0.0 {
+        for &(_r, used) in &v.rooms_in_use {
+            objective += w_rooms_used * used;
+        }
+        objective += w_rooms_used * (pinned_rooms_in_use(prep).len() as f64);
+    }
+
+    let w_active = scaled(prep, weights.active_days as f64);
+    if w_active > 0.0 {
+        for d in &v.active_days {
+            objective += w_active * d.active;
+        }
+    }
+
+    let w_load = scaled(prep, weights.load_imbalance as f64);
+    if w_load > 0.0 {
+        for &(_tid, dev) in &v.load_dev {
+            objective += w_load * dev;
+        }
+    }
+
+    objective
+}
+
+pub(crate) fn add_course_count_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    for (ci, c) in prep.inst.courses.iter().enumerate() {
+        let mut sum = Expression::from(0.0);
+        for s in v.starts.iter().filter(|s| s.c == ci) {
+            sum = sum + s.var;
+        }
+        if let Some(&(_, unmet)) = v.unmet.iter().find(|&&(uc, _)| uc == ci) {
+            sum += unmet;
+        }
+        let pinned_cnt = *prep.pinned.count_by_course.get(&ci).unwrap_or(&0);
+        let need = c.required_meetings().saturating_sub(pinned_cnt);
+        model = model.with(sum.eq(need as f64));
+    }
+    model
+}
+
+/// One meeting per (room, slot) and week, or for open-lab rooms the seated
+/// group sizes summed against `student_capacity` regardless of parity. An
+/// `exclusive_room` meeting in an open-lab room leaves no place for others:
+/// `others + n * start <= n` over the `n` other starts covering the slot.
+/// Pinned occupancy is settled when the starts are declared.
+pub(crate) fn add_room_capacity_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    let courses = &prep.inst.courses;
+    for (ri, r) in prep.inst.rooms.iter().enumerate() {
+        let rid = r.id.0.as_str();
+        for k in 0..prep.index.times.len() {
+            let starts: Vec<&StartVar> = v
+                .starts
+                .iter()
+                .filter(|s| s.r == ri && occupies(courses, s, k))
+                .collect();
+            if let Some(seats) = r.student_capacity {
+                let mut sum = Expression::from(0.0);
+                for s in &starts {
+                    let gid = courses[s.c].groupId.0.as_str();
+                    sum += prep.index.group_size.get(gid).copied().unwrap_or(0) as f64 * s.var;
+                }
+                let taken = prep.pinned.occ.seats.get(&(rid, k)).copied().unwrap_or(0);
+                model = model.with(sum.leq(seats.saturating_sub(taken) as f64));
+                for e in starts.iter().filter(|s| courses[s.c].exclusive_room) {
+                    let n = (starts.len() - 1) as f64;
+                    if n == 0.0 {
+                        continue;
+                    }
+                    let mut others = Expression::from(0.0);
+                    for s in starts.iter().filter(|s| s.var != e.var) {
+                        others += s.var;
+                    }
+                    model = model.with((others + n * e.var).leq(n));
+                }
+                continue;
+            }
+            for &week in constraint_weeks(courses, &starts) {
+                let rhs = if prep.pinned.occ.room_busy(rid, k, week) {
+                    0.0
+                } else {
+                    1.0
+                };
+                model = model.with(week_sum(courses, &starts, week).leq(rhs));
+            }
+        }
+    }
+    model
+}
+
+pub(crate) fn add_teacher_capacity_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    let courses = &prep.inst.courses;
+    for &tid in &prep.teacher_ids {
+        for k in 0..prep.index.times.len() {
+            let starts: Vec<&StartVar> = v
+                .starts
+                .iter()
+                .filter(|s| courses[s.c].teacherId.0.as_str() == tid && occupies(courses, s, k))
+                .collect();
+            for &week in constraint_weeks(courses, &starts) {
+                let rhs = if prep.pinned.occ.teacher_busy(tid, k, week) {
+                    0.0
+                } else {
+                    1.0
+                };
+                model = model.with(week_sum(courses, &starts, week).leq(rhs));
+            }
+        }
+    }
+    model
+}
+
+pub(crate) fn add_group_capacity_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    let courses = &prep.inst.courses;
+    for &gid in &prep.group_ids {
+        for k in 0..prep.index.times.len() {
+            let starts: Vec<&StartVar> = v
+                .starts
+                .iter()
+                .filter(|s| courses[s.c].groupId.0.as_str() == gid && occupies(courses, s, k))
+                .collect();
+            for &week in constraint_weeks(courses, &starts) {
+                let rhs = if prep.pinned.occ.group_busy(gid, k, week) {
+                    0.0
+                } else {
+                    1.0
+                };
+                model = model.with(week_sum(courses, &starts, week).leq(rhs));
+            }
+        }
+    }
+    model
+}
+
+/// At most `cap` rooms of a capped building busy per slot, pinned ones included.
+/// Meetings sharing an open-lab room each count here, which is stricter than
+/// the greedy and GA checks but keeps the model free of per-room indicators.
+pub(crate) fn add_building_concurrency_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    let mut caps: Vec<(&String, &u32)> = prep.inst.policy.building_concurrency.iter().collect();
+    caps.sort_unstable();
+    for (b, &cap) in caps {
+        let rooms: Vec<usize> = prep
+            .inst
+            .rooms
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.building.as_deref() == Some(b.as_str()))
+            .map(|(ri, _)| ri)
+            .collect();
+        if rooms.is_empty() {
+            continue;
+        }
+        for k in 0..prep.index.times.len() {
+            let mut sum = Expression::from(0.0);
+            for s in v
+                .starts
+                .iter()
+                .filter(|s| rooms.contains(&s.r) && occupies(&prep.inst.courses, s, k))
+            {
+                sum += s.var;
+            }
+            let pinned = prep
+                .pinned
+                .occ
+                .building
+                .get(&(b.as_str(), k))
+                .copied()
+                .unwrap_or(0);
+            model = model.with(sum.leq(cap.saturating_sub(pinned) as f64));
+        }
+    }
+    model
+}
+
+pub(crate) fn link_occupancy<M: SolverModel>(mut model: M, prep: &Prep, v: &Vars) -> M {
+    let courses = &prep.inst.courses;
+    for (&(tid, k), var) in &v.ot {
+        let starts: Vec<&StartVar> = v
+            .starts
+            .iter()
+            .filter(|s| courses[s.c].teacherId.0.as_str() == tid && occupies(courses, s, k))
+            .collect();
+        let pinned = |week| prep.pinned.occ.teacher_busy(tid, k, week);
+        model = link_busy(model, courses, &starts, pinned, *var);
+    }
+    for (&(gid, k), var) in &v.og {
+        let starts: Vec<&StartVar> = v
+            .starts
+            .iter()
+            .filter(|s| courses[s.c].groupId.0.as_str() == gid && occupies(courses, s, k))
+            .collect();
+        let pinned = |week| prep.pinned.occ.group_busy(gid, k, week);
+        model = link_busy(model, courses, &starts, pinned, *var);
+    }
+    model
+}
+
+/// `busy` is 1 iff the slot is taken in some week. Biweekly starts of
+/// opposite parity may both be on, so then it is bounded from both sides
+/// instead of equated with the sum.
+fn link_busy<M: SolverModel>(
+    mut model: M,
+    courses: &[Course],
+    starts: &[&StartVar],
+    pinned: impl Fn(Parity) -> bool,
+    busy: Variable,
+) -> M {
+    let one_if = |b: bool| if b { 1.0 } else { 0.0 };
+    let weeks = constraint_weeks(courses, starts);
+    if let [Parity::Every] = weeks {
+        let sum = week_sum(courses, starts, Parity::Every);
+        return model.with((sum + one_if(pinned(Parity::Every))).eq(busy));
+    }
+    for &week in weeks {
+        let sum = week_sum(courses, starts, week);
+        model = model.with((sum + one_if(pinned(week))).leq(busy));
+    }
+    let sum = week_sum(courses, starts, Parity::Every);
+    model.with((sum + one_if(pinned(Parity::Every))).geq(busy))
+}
+
+/// Courses of a `no_overlap` pair share no slot; a pinned meeting of either
+/// closes the slot to the other.
+pub(crate) fn add_no_overlap_constraints<M: SolverModel>(mut model: M, prep: &Prep, v: &Vars) -> M {
+    for (a, b) in &prep.inst.policy.no_overlap {
+        let (Some(&ca), Some(&cb)) = (
+            prep.index.idx_course.get(a.0.as_str()),
+            prep.index.idx_course.get(b.0.as_str()),
+        ) else {
+            continue;
+        };
+        for k in 0..prep.index.times.len() {
+            let mut sum = Expression::from(0.0);
+            for s in v
+                .starts
+                .iter()
+                .filter(|s| (s.c == ca || s.c == cb) && occupies(&prep.inst.courses, s, k))
+            {
+                sum += s.var;
+            }
+            let pinned = [a, b]
+                .iter()
+                .any(|id| prep.pinned.occ.course.contains(&(id.0.as_str(), k)));
+            model = model.with(sum.leq(if pinned { 0.0 } else { 1.0 }));
+        }
+    }
+    model
+}
+
+/// Per group with `max_weekly`: its courses' starts plus their pinned
+/// meetings stay within the cap.
+pub(crate) fn add_group_weekly_cap_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    for g in &prep.inst.groups {
+        let Some(cap) = g.max_weekly else {
+            continue;
+        };
+        let of_group = |ci: usize| prep.inst.courses[ci].groupId == g.id;
+        let mut sum = Expression::from(0.0);
+        for s in v.starts.iter().filter(|s| of_group(s.c)) {
+            sum += s.var;
+        }
+        let pinned: u32 = (0..prep.inst.courses.len())
+            .filter(|&ci| of_group(ci))
+            .map(|ci| *prep.pinned.count_by_course.get(&ci).unwrap_or(&0))
+            .sum();
+        model = model.with(sum.leq(cap as f64 - pinned as f64));
+    }
+    model
+}
+
+/// Per teacher with `max_windows`: occupied slots minus adjacent busy pairs,
+/// summed over the week, stays within the cap.
+pub(crate) fn add_teacher_window_cap_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    for &tid in &prep.teacher_ids {
+        let Some(cap) = prep
+            .index
+            .teacher_by_id
+            .get(tid)
+            .and_then(|t| t.prefs.max_windows)
+        else {
+            continue;
+        };
+        let mut sum = Expression::from(0.0);
+        for slots in prep.index.day_slots.values() {
+            for &k in slots {
+                sum += v.ot[&(tid, k)];
+            }
+        }
+        for &(a, (t, _), _) in &v.adj_t {
+            if t == tid {
+                sum -= a;
+            }
+        }
+        model = model.with(sum.leq(cap as f64));
+    }
+    model
+}
+
+/// For `contiguous_days` groups, no day has a free slot between two busy
+/// ones: `og[i] - og[j] + og[l] <= 1` for every `i < j < l` of the day.
+pub(crate) fn add_contiguous_day_constraints<M: SolverModel>(
+    mut model: M,
+    prep: &Prep,
+    v: &Vars,
+) -> M {
+    for &gid in &prep.group_ids {
+        if !prep
+            .inst
+            .groups
+            .iter()
+            .any(|g| g.id.0 == gid && g.contiguous_days)
+        {
+            continue;
+        }
+        for slots in prep.index.day_slots.values() {
+            for (i, &ki) in slots.iter().enumerate() {
+                for (j, &kj) in slots.iter().enumerate().skip(i + 1) {
+                    for &kl in &slots[j + 1..] {
+                        model = model.with(
+                            (v.og[&(gid, ki)] - v.og[&(gid, kj)] + v.og[&(gid, kl)]).leq(1.0),
+                        );
+                    }
+                }
+            }
+        }
+    }
+    model
+}
+
+pub(crate) fn add_adjacency_constraints<M: SolverModel>(mut model: M, v: &Vars) -> M {
+    for &(a, (tid, k), (_tid2, k1)) in &v.adj_t {
+        model = model.with((a - v.ot[&(tid, k)]).leq(0.0));
+        model = model.with((a - v.ot[&(tid, k1)]).leq(0.0));
         model = model.with((a - v.ot[&(tid, k)] - v.ot[&(tid, k1)]).geq(-1.0));
     }
     for &(a, (gid, k), (_gid2, k1)) in &v.adj_g {
@@ -633,9 +2013,10 @@ pub(crate) fn extract_solution(prep: &Prep, v: &Vars, sol: &impl Solution) -> Ve
             let r = &prep.inst.rooms[s.r];
             assignments.push(Assignment {
                 courseId: c.id.clone(),
-                timeslot: TimeslotId(prep.times[s.t].to_string()),
+                timeslot: TimeslotId(prep.index.times[s.t].to_string()),
                 roomId: r.id.clone(),
                 teacherId: c.teacherId.clone(),
+                meta: c.meta.clone(),
             });
         }
     }
@@ -655,6 +2036,17 @@ pub(crate) fn add_partial_lock_constraints<M: SolverModel>(
             sum = sum + s.var;
         }
         model = model.with(sum.eq(1.0));
+        if let (Some(ti), Some(k)) = (lk.t, lk.before) {
+            let mut earlier = Expression::from(
+                (0..ti)
+                    .map(|t| pinned_meetings_at(prep, lk.c, t, false))
+                    .sum::<f64>(),
+            );
+            for s in v.starts.iter().filter(|s| s.c == lk.c && s.t < ti) {
+                earlier += s.var;
+            }
+            model = model.with(earlier.eq(k as f64));
+        }
     }
     model
 }
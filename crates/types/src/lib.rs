@@ -1,6 +1,6 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use utoipa::ToSchema;
 
@@ -24,7 +24,21 @@ id_newtype!(GroupId);
 id_newtype!(RoomId);
 id_newtype!(CourseId);
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Eq, PartialEq, Hash)]
+/// Ordered Monday first.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    JsonSchema,
+    Eq,
+    PartialEq,
+    Hash,
+    PartialOrd,
+    Ord,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum DayOfWeek {
     Mon,
@@ -36,7 +50,49 @@ pub enum DayOfWeek {
     Sun,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Eq, PartialEq, Hash)]
+impl DayOfWeek {
+    pub const ALL: [DayOfWeek; 7] = [
+        DayOfWeek::Mon,
+        DayOfWeek::Tue,
+        DayOfWeek::Wed,
+        DayOfWeek::Thu,
+        DayOfWeek::Fri,
+        DayOfWeek::Sat,
+        DayOfWeek::Sun,
+    ];
+
+    /// Lowercase three-letter abbreviation, as used in timeslot ids.
+    pub fn abbrev(self) -> &'static str {
+        match self {
+            DayOfWeek::Mon => "mon",
+            DayOfWeek::Tue => "tue",
+            DayOfWeek::Wed => "wed",
+            DayOfWeek::Thu => "thu",
+            DayOfWeek::Fri => "fri",
+            DayOfWeek::Sat => "sat",
+            DayOfWeek::Sun => "sun",
+        }
+    }
+
+    /// Day for its lowercase three-letter abbreviation, as used in timeslot
+    /// ids.
+    pub fn from_abbrev(s: &str) -> Option<Self> {
+        Some(match s {
+            "mon" => DayOfWeek::Mon,
+            "tue" => DayOfWeek::Tue,
+            "wed" => DayOfWeek::Wed,
+            "thu" => DayOfWeek::Thu,
+            "fri" => DayOfWeek::Fri,
+            "sat" => DayOfWeek::Sat,
+            "sun" => DayOfWeek::Sun,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(
+    Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Eq, PartialEq, Hash, PartialOrd, Ord,
+)]
 #[serde(rename_all = "snake_case")]
 pub enum Equip {
     Projector,
@@ -54,19 +110,59 @@ pub enum CourseKind {
     Seminar,
 }
 
+/// Which weeks a course meets in. Opposite-parity courses never coincide,
+/// so they may share a room, teacher or group slot.
+#[derive(
+    Clone, Copy, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Default, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Parity {
+    Odd,
+    Even,
+    #[default]
+    Every,
+}
+
+impl Parity {
+    /// Whether two courses with these parities ever meet in the same week.
+    pub fn overlaps(self, other: Parity) -> bool {
+        !matches!(
+            (self, other),
+            (Parity::Odd, Parity::Even) | (Parity::Even, Parity::Odd)
+        )
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Eq, PartialEq, Hash)]
 #[serde(transparent)]
 pub struct TimeslotId(pub String);
 
 impl TimeslotId {
-    pub fn is_valid_format(&self) -> bool {
-        let parts: Vec<_> = self.0.split('.').collect();
-        if parts.len() != 2 {
-            return false;
+    /// Day, period index and sub-slot of the id; see [`TimeslotId::parse_str`].
+    pub fn parse(&self) -> Option<(DayOfWeek, u32, u32)> {
+        Self::parse_str(&self.0)
+    }
+
+    /// Parses `"<day>.<index>"` (`mon.1`) or, on finer grids,
+    /// `"<day>.<index><sub>"` (`mon.1a`, `mon.1b`), where `sub` is one
+    /// lowercase letter splitting the period into equal parts in letter
+    /// order. The sub-slot is 1 for `a`, 2 for `b`, ... and 0 for a whole
+    /// period, so `(index, sub)` orders the slots of a day.
+    pub fn parse_str(s: &str) -> Option<(DayOfWeek, u32, u32)> {
+        let (day, rest) = s.split_once('.')?;
+        let day = DayOfWeek::from_abbrev(day)?;
+        let (digits, sub) = match rest.bytes().last()? {
+            b @ b'a'..=b'z' => (&rest[..rest.len() - 1], (b - b'a') as u32 + 1),
+            _ => (rest, 0),
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
         }
-        let day = parts[0];
-        let idx_ok = parts[1].parse::<u32>().is_ok();
-        matches!(day, "mon" | "tue" | "wed" | "thu" | "fri" | "sat" | "sun") && idx_ok
+        Some((day, digits.parse().ok()?, sub))
+    }
+
+    pub fn is_valid_format(&self) -> bool {
+        self.parse().is_some()
     }
 }
 
@@ -80,6 +176,10 @@ pub struct TeacherPrefs {
     pub morning: bool,
     #[serde(default)]
     pub max_per_day: Option<u8>,
+    /// Hard cap on the week's `windows` count for this teacher (teaching
+    /// blocks per day, summed over days).
+    #[serde(default)]
+    pub max_windows: Option<u32>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
@@ -88,13 +188,35 @@ pub struct Teacher {
     #[serde(default)]
     pub available: Vec<TimeslotId>,
     #[serde(default)]
+    pub soft_available: Vec<TimeslotId>,
+    #[serde(default)]
     pub prefs: TeacherPrefs,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct GroupPrefs {
+    #[serde(default)]
+    pub avoid_slots: Vec<TimeslotId>,
+    #[serde(default)]
+    pub preferred_days: Vec<DayOfWeek>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
 pub struct Group {
     pub id: GroupId,
     pub size: u32,
+    #[serde(default)]
+    pub prefs: GroupPrefs,
+    /// Building the group should preferably stay in (`SoftWeights.away_from_home`).
+    #[serde(default)]
+    pub home_building: Option<String>,
+    /// Hard rule: on every day the group has classes, they form one block
+    /// with no free slot in between.
+    #[serde(default)]
+    pub contiguous_days: bool,
+    /// Hard cap on the group's meetings per week across all its courses.
+    #[serde(default)]
+    pub max_weekly: Option<u32>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
@@ -105,6 +227,24 @@ pub struct Room {
     pub equip: Vec<Equip>,
     #[serde(default)]
     pub building: Option<String>,
+    /// Open-lab seating: the room hosts several meetings per slot as long as
+    /// their group sizes add up to at most this many students. `None` keeps
+    /// the usual one meeting per slot.
+    #[serde(default)]
+    pub student_capacity: Option<u32>,
+    /// Opening window as slot indices (the number after the dot in a
+    /// timeslot id), inclusive; meetings in the room must lie within it.
+    #[serde(default)]
+    pub earliest: Option<u32>,
+    #[serde(default)]
+    pub latest: Option<u32>,
+}
+
+impl Room {
+    /// Whether slot index `idx` lies within the room's opening window.
+    pub fn open_at(&self, idx: u32) -> bool {
+        self.earliest.is_none_or(|e| idx >= e) && self.latest.is_none_or(|l| idx <= l)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
@@ -118,22 +258,294 @@ pub struct Course {
     pub kind: CourseKind,
     #[serde(default)]
     pub needs: Vec<Equip>,
+    #[serde(default)]
+    pub priority: Option<u32>,
+    /// Parallel sections, each needing `countPerWeek` meetings with its own
+    /// room and slot. Sections inherit the course's group and teacher, so
+    /// the usual clash rules keep them from overlapping one another.
+    #[serde(default)]
+    pub sections: Option<u32>,
+    /// Hard room whitelist; when non-empty, no other room is used even if
+    /// it has the capacity and equipment.
+    #[serde(default)]
+    pub allowed_rooms: Vec<RoomId>,
+    /// Biweekly courses meet on odd or even weeks only; `None` is every week.
+    #[serde(default)]
+    pub week_parity: Option<Parity>,
+    /// Hold the room alone, e.g. for exams: in an open-lab room
+    /// (`Room.student_capacity`) no other meeting may share its slots.
+    /// Ordinary rooms already host one meeting at a time.
+    #[serde(default)]
+    pub exclusive_room: bool,
+    /// Copied onto every assignment the solvers create for this course.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Default)]
+impl Course {
+    pub const DEFAULT_PRIORITY: u32 = 1;
+
+    pub fn allows_room(&self, room: &RoomId) -> bool {
+        self.allowed_rooms.is_empty() || self.allowed_rooms.contains(room)
+    }
+
+    pub fn parity(&self) -> Parity {
+        self.week_parity.unwrap_or_default()
+    }
+
+    pub fn priority_or_default(&self) -> u32 {
+        self.priority.unwrap_or(Self::DEFAULT_PRIORITY)
+    }
+
+    pub fn sections_or_default(&self) -> u32 {
+        self.sections.unwrap_or(1)
+    }
+
+    /// Meetings per week across all sections.
+    pub fn required_meetings(&self) -> u32 {
+        self.countPerWeek * self.sections_or_default()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Default, PartialEq)]
 pub struct SoftWeights {
     #[serde(default)]
     pub unpreferred_time: i32,
     #[serde(default)]
     pub windows: i32,
+    /// Overrides `windows` for teachers' windows only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows_teachers: Option<i32>,
+    /// Overrides `windows` for groups' windows only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows_groups: Option<i32>,
+    /// Per back-to-back pair of a teacher's or group's meetings held in
+    /// different buildings, scaled by `Policy.building_distance`.
     #[serde(default)]
     pub building_switch: i32,
+    #[serde(default)]
+    pub load_imbalance: i32,
+    #[serde(default)]
+    pub compactness: i32,
+    #[serde(default)]
+    pub soft_availability: i32,
+    /// Per extra room a course's meetings are spread over.
+    #[serde(default)]
+    pub course_room_consistency: i32,
+    /// Per extra room a teacher teaches in over the week.
+    #[serde(default)]
+    pub teacher_room_spread: i32,
+    /// Per meeting of a group held outside its home building.
+    #[serde(default)]
+    pub away_from_home: i32,
+    /// Per `policy.prefer_adjacent` pair not met back-to-back.
+    #[serde(default)]
+    pub course_adjacency: i32,
+    /// Per meeting of an odd/even course pair (same group, teacher and
+    /// duration) whose counterpart does not start in the same slot.
+    #[serde(default)]
+    pub parity_slot_consistency: i32,
+    /// Per distinct day a teacher or group has any meeting; packs the week
+    /// into fewer days and so pulls against spreading.
+    #[serde(default)]
+    pub active_days: i32,
+    /// Per extra building a course's meetings are spread over. Rooms
+    /// without a building are not counted.
+    #[serde(default)]
+    pub course_building_spread: i32,
+    /// Per distinct room used anywhere in the week; packs meetings into
+    /// fewer rooms.
+    #[serde(default)]
+    pub rooms_used: i32,
+    /// Per day a course's meeting days fall short of its ideal spacing,
+    /// summed over each pair of days it meets on; spreads a twice-weekly
+    /// course to Mon/Thu rather than Mon/Tue.
+    #[serde(default)]
+    pub course_spacing: i32,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Default)]
+/// A soft-weight term the MILP can optimize on its own.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectiveTerm {
+    UnpreferredTime,
+    Windows,
+    LoadImbalance,
+    Compactness,
+    SoftAvailability,
+    CourseRoomConsistency,
+    TeacherRoomSpread,
+    AwayFromHome,
+    CourseAdjacency,
+    ParitySlotConsistency,
+    ActiveDays,
+    CourseBuildingSpread,
+    RoomsUsed,
+    BuildingSwitch,
+    CourseSpacing,
+}
+
+impl SoftWeights {
+    pub fn weight(&self, term: ObjectiveTerm) -> i32 {
+        match term {
+            ObjectiveTerm::UnpreferredTime => self.unpreferred_time,
+            ObjectiveTerm::Windows => self.teacher_windows().max(self.group_windows()),
+            ObjectiveTerm::LoadImbalance => self.load_imbalance,
+            ObjectiveTerm::Compactness => self.compactness,
+            ObjectiveTerm::SoftAvailability => self.soft_availability,
+            ObjectiveTerm::CourseRoomConsistency => self.course_room_consistency,
+            ObjectiveTerm::TeacherRoomSpread => self.teacher_room_spread,
+            ObjectiveTerm::AwayFromHome => self.away_from_home,
+            ObjectiveTerm::CourseAdjacency => self.course_adjacency,
+            ObjectiveTerm::ParitySlotConsistency => self.parity_slot_consistency,
+            ObjectiveTerm::ActiveDays => self.active_days,
+            ObjectiveTerm::CourseBuildingSpread => self.course_building_spread,
+            ObjectiveTerm::RoomsUsed => self.rooms_used,
+            ObjectiveTerm::BuildingSwitch => self.building_switch,
+            ObjectiveTerm::CourseSpacing => self.course_spacing,
+        }
+    }
+
+    /// Weight on teachers' windows: `windows_teachers`, else `windows`.
+    pub fn teacher_windows(&self) -> i32 {
+        self.windows_teachers.unwrap_or(self.windows)
+    }
+
+    /// Weight on groups' windows: `windows_groups`, else `windows`.
+    pub fn group_windows(&self) -> i32 {
+        self.windows_groups.unwrap_or(self.windows)
+    }
+
+    /// Weight 1 on `term`, 0 elsewhere.
+    pub fn only(term: ObjectiveTerm) -> Self {
+        let mut w = Self::default();
+        let slot = match term {
+            ObjectiveTerm::UnpreferredTime => &mut w.unpreferred_time,
+            ObjectiveTerm::Windows => &mut w.windows,
+            ObjectiveTerm::LoadImbalance => &mut w.load_imbalance,
+            ObjectiveTerm::Compactness => &mut w.compactness,
+            ObjectiveTerm::SoftAvailability => &mut w.soft_availability,
+            ObjectiveTerm::CourseRoomConsistency => &mut w.course_room_consistency,
+            ObjectiveTerm::TeacherRoomSpread => &mut w.teacher_room_spread,
+            ObjectiveTerm::AwayFromHome => &mut w.away_from_home,
+            ObjectiveTerm::CourseAdjacency => &mut w.course_adjacency,
+            ObjectiveTerm::ParitySlotConsistency => &mut w.parity_slot_consistency,
+            ObjectiveTerm::ActiveDays => &mut w.active_days,
+            ObjectiveTerm::CourseBuildingSpread => &mut w.course_building_spread,
+            ObjectiveTerm::RoomsUsed => &mut w.rooms_used,
+            ObjectiveTerm::BuildingSwitch => &mut w.building_switch,
+            ObjectiveTerm::CourseSpacing => &mut w.course_spacing,
+        };
+        *slot = 1;
+        w
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
 pub struct Policy {
     #[serde(default)]
     pub soft_weights: SoftWeights,
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default = "default_equip_implies")]
+    pub equip_implies: HashMap<Equip, Vec<Equip>>,
+    /// Max simultaneously occupied rooms per building and slot. Buildings
+    /// not listed, and rooms without a building, are unlimited.
+    #[serde(default)]
+    pub building_concurrency: HashMap<String, u32>,
+    #[serde(default)]
+    pub empty_availability: EmptyAvailability,
+    /// Lexicographic MILP objective: each term is minimized in turn and held
+    /// at its optimum while the next is optimized. Empty means one weighted
+    /// solve.
+    #[serde(default)]
+    pub objective_order: Vec<ObjectiveTerm>,
+    /// Course pairs that must never share a timeslot, e.g. courses of
+    /// different groups with overlapping students.
+    #[serde(default)]
+    pub no_overlap: Vec<(CourseId, CourseId)>,
+    /// Ordered course pairs that should run back-to-back, the second starting
+    /// in the slot right after the first ends (`SoftWeights.course_adjacency`).
+    #[serde(default)]
+    pub prefer_adjacent: Vec<(CourseId, CourseId)>,
+    /// Distance between two buildings, listed under either one; a
+    /// back-to-back switch between them costs `building_switch` times this.
+    /// Unlisted pairs count as 1.
+    #[serde(default)]
+    pub building_distance: HashMap<String, HashMap<String, i32>>,
+    /// Campus-wide whitelist of slots any meeting may use, e.g. nothing
+    /// before 9am or after 6pm. `None` allows every slot.
+    #[serde(default)]
+    pub allowed_slots: Option<Vec<TimeslotId>>,
+    /// Break MILP ties between equally good schedules towards earlier
+    /// slots, so the optimum returned is deterministic. The tie-break term
+    /// is kept below one unit of the real objective and is not reported.
+    #[serde(default)]
+    pub tiebreak_early: bool,
+    /// Count a teacher's windows only over the slots they are available in
+    /// (or teach in), so hours they cannot teach do not split a block.
+    /// Groups are unaffected.
+    #[serde(default)]
+    pub windows_within_availability: bool,
+    /// Course sets whose meetings must all use one room, whichever the
+    /// solver picks, e.g. a sequence taught in a dedicated room.
+    #[serde(default)]
+    pub same_room_groups: Vec<Vec<CourseId>>,
+}
+
+impl Policy {
+    /// Configured distance between buildings `a` and `b`, in either
+    /// direction; 1 when the pair is not listed.
+    pub fn building_distance(&self, a: &str, b: &str) -> i32 {
+        let get = |x: &str, y: &str| self.building_distance.get(x)?.get(y).copied();
+        get(a, b).or_else(|| get(b, a)).unwrap_or(1)
+    }
+
+    /// Whether `allowed_slots`, if set, lists slot `ts`.
+    pub fn allows_slot(&self, ts: &str) -> bool {
+        self.allowed_slots
+            .as_ref()
+            .is_none_or(|s| s.iter().any(|x| x.0 == ts))
+    }
+}
+
+/// Meaning of a teacher with neither `available` nor `soft_available` slots.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, ToSchema, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyAvailability {
+    /// Free in every slot.
+    #[default]
+    AllSlots,
+    /// Free in no slot; availability must be listed explicitly.
+    NoSlots,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            soft_weights: SoftWeights::default(),
+            preset: None,
+            equip_implies: default_equip_implies(),
+            building_concurrency: HashMap::new(),
+            empty_availability: EmptyAvailability::AllSlots,
+            objective_order: Vec::new(),
+            no_overlap: Vec::new(),
+            prefer_adjacent: Vec::new(),
+            building_distance: HashMap::new(),
+            allowed_slots: None,
+            tiebreak_early: false,
+            windows_within_availability: false,
+            same_room_groups: Vec::new(),
+        }
+    }
+}
+
+/// A computer lab is assumed to also have a projector and a whiteboard.
+pub fn default_equip_implies() -> HashMap<Equip, Vec<Equip>> {
+    HashMap::from([(
+        Equip::ComputerLab,
+        vec![Equip::Projector, Equip::Whiteboard],
+    )])
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
@@ -144,14 +556,60 @@ pub struct Instance {
     pub courses: Vec<Course>,
     pub timeslots: Vec<TimeslotId>,
     pub policy: Policy,
+    /// Wall-clock times per slot index (the number after the dot in a
+    /// timeslot id). When given, a two-slot meeting may only span slots
+    /// where the first ends exactly as the second starts.
+    #[serde(default)]
+    pub slot_times: BTreeMap<u32, SlotTime>,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
+pub struct SlotTime {
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM"
+    pub end: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, ToSchema, JsonSchema)]
 pub enum SolverKind {
     Milp,
     Heuristic,
 }
 
+impl SolverKind {
+    pub const NAMES: [&'static str; 2] = ["Milp", "Heuristic"];
+}
+
+/// Hand-written so an unknown value names the accepted ones instead of
+/// serde's generic unknown-variant message.
+impl<'de> Deserialize<'de> for SolverKind {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        match s.as_str() {
+            "Milp" => Ok(SolverKind::Milp),
+            "Heuristic" => Ok(SolverKind::Heuristic),
+            _ => Err(serde::de::Error::custom(format!(
+                "must be one of: {} (got {s:?})",
+                Self::NAMES.join(", ")
+            ))),
+        }
+    }
+}
+
+/// Course order used by the greedy fallback.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, ToSchema, JsonSchema, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GreedyOrder {
+    /// Courses as listed in the instance.
+    Instance,
+    /// Higher `priority` first, instance order among equals.
+    #[default]
+    Priority,
+    /// Fewest feasible (timeslot, room) starts first, then by priority.
+    MostConstrainedFirst,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
 pub struct SolveParams {
     pub solver: SolverKind,
@@ -160,6 +618,54 @@ pub struct SolveParams {
     pub repairLocalSearch: bool,
     #[serde(default)]
     pub repairSteps: Option<u32>,
+    /// The repair result replaces the MILP solution only when it lowers the
+    /// objective by more than this. Defaults to a float-noise threshold.
+    #[serde(default)]
+    pub repairMinGain: Option<f64>,
+    /// Same as `repairMinGain` but as a fraction of the MILP objective; the
+    /// larger of the two applies.
+    #[serde(default)]
+    pub repairMinGainRel: Option<f64>,
+    #[serde(default)]
+    pub relaxPins: bool,
+    #[serde(default)]
+    pub bestEffort: bool,
+    /// Integer factor applied to every MILP objective coefficient (default 1).
+    #[serde(default)]
+    pub objectiveScale: Option<u32>,
+    #[serde(default)]
+    pub greedyOrder: GreedyOrder,
+    /// When false, a MILP request fails instead of falling back to greedy.
+    #[serde(default = "default_true")]
+    pub allowFallback: bool,
+    /// GA tournament size, clamped to `1..=population`.
+    #[serde(default)]
+    pub tournamentSize: Option<u32>,
+    /// GA candidates kept untouched each generation, clamped below the
+    /// population size. Unset keeps the default replace-the-worst scheme.
+    #[serde(default)]
+    pub elitism: Option<u32>,
+    /// Satisficing mode: any schedule with objective at or below this is
+    /// good enough. The MILP solves for feasibility under the bound instead
+    /// of optimizing; the GA stops once its best candidate reaches it.
+    #[serde(default)]
+    pub objectiveBudget: Option<f64>,
+    /// Scoped solve: only courses of these groups are scheduled; other
+    /// groups keep their `base` meetings as fixed occupancy. Empty solves
+    /// every group.
+    #[serde(default)]
+    pub onlyGroups: Vec<GroupId>,
+    /// MILP only: first find any schedule that meets the hard constraints,
+    /// then optimize the soft terms in the time left. A second phase that
+    /// runs out of time returns the first phase's schedule. The second phase
+    /// is not warm-started from the first: good_lp's CBC backend takes no
+    /// MIP start, so it solves from scratch.
+    #[serde(default)]
+    pub twoPhase: bool,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
@@ -168,6 +674,11 @@ pub struct Assignment {
     pub timeslot: TimeslotId,
     pub roomId: RoomId,
     pub teacherId: TeacherId,
+    /// Integrator data carried through solving: taken from the `base` or
+    /// `pinned` input meeting of the same course and slot, else from the
+    /// course's `meta`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, JsonSchema)]
@@ -198,6 +709,18 @@ pub struct SolveEnvelope {
     pub masks: Vec<LockMask>,
     #[serde(default)]
     pub partial_pins: Vec<PartialPin>,
+    /// Days whose `base` assignments are fully locked, e.g. days that have
+    /// already happened when reoptimizing mid-week.
+    #[serde(default)]
+    pub frozen_days: Vec<DayOfWeek>,
+    /// When non-empty, only these days are re-solved: `base` assignments
+    /// on every other day are fully locked (the inverse of `frozen_days`).
+    #[serde(default)]
+    pub window_days: Vec<DayOfWeek>,
+    /// Job queue priority; higher runs first, ties in submission order.
+    /// Unset is 0. Has no effect on the schedule produced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
 }
 
 impl Instance {
@@ -243,4 +766,30 @@ pub struct PartialPin {
     pub timeslot: Option<TimeslotId>,
     #[serde(default)]
     pub roomId: Option<RoomId>,
+    /// Substitute teacher for this one meeting instead of the course's own.
+    #[serde(default)]
+    pub teacherId: Option<TeacherId>,
+    /// Which meeting of the course, counting from 0 in grid order, starts
+    /// at `timeslot`: exactly this many of its meetings start earlier.
+    /// Ignored without a `timeslot`.
+    #[serde(default)]
+    pub meeting: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sub_slot_ids_parse_alongside_whole_periods() {
+        let parse = |s: &str| TimeslotId(s.into()).parse();
+        assert_eq!(parse("mon.1"), Some((DayOfWeek::Mon, 1, 0)));
+        assert_eq!(parse("mon.1a"), Some((DayOfWeek::Mon, 1, 1)));
+        assert_eq!(parse("fri.12c"), Some((DayOfWeek::Fri, 12, 3)));
+        assert!(parse("mon.1") < parse("mon.1a") && parse("mon.1b") < parse("mon.2"));
+
+        for bad in ["mon.a", "mon.1A", "mon.", "mon1", "xyz.1", "mon.1ab"] {
+            assert!(!TimeslotId(bad.into()).is_valid_format(), "{bad}");
+        }
+    }
 }